@@ -0,0 +1,426 @@
+// jsonb_ivm - Aggregates Module
+//
+// Aggregate fields (count, sum, min, max) kept consistent with an embedded
+// array as the array CRUD functions add and remove elements.
+
+use pgrx::prelude::*;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::array_ops::compare_values;
+use crate::counters::numeric_text_to_value;
+use crate::path::{navigate_path, parse_path, set_path, PathSegment};
+use crate::search::value_key;
+use crate::value_type_name;
+
+/// An aggregate over the elements of an array
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Aggregate {
+    /// Number of elements
+    Count,
+    /// Sum of the values of a key (missing and `null` values are skipped)
+    Sum(String),
+    /// Smallest value of a key
+    Min(String),
+    /// Largest value of a key
+    Max(String),
+}
+
+impl Aggregate {
+    /// Parse an aggregate expression: `count`, `sum(key)`, `min(key)` or `max(key)`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for any other expression.
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = expr.trim();
+        let invalid = || {
+            format!("Invalid aggregate '{expr}': expected count, sum(key), min(key) or max(key)")
+        };
+
+        if expr.eq_ignore_ascii_case("count") || expr.eq_ignore_ascii_case("count(*)") {
+            return Ok(Self::Count);
+        }
+
+        let Some((func, rest)) = expr.split_once('(') else {
+            return Err(invalid());
+        };
+        let Some(key) = rest
+            .strip_suffix(')')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+        else {
+            return Err(invalid());
+        };
+
+        match func.trim().to_ascii_lowercase().as_str() {
+            "sum" => Ok(Self::Sum(key.to_string())),
+            "min" => Ok(Self::Min(key.to_string())),
+            "max" => Ok(Self::Max(key.to_string())),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A declared aggregate and the field that holds it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateField {
+    pub path: String,
+    pub segments: Vec<PathSegment>,
+    pub aggregate: Aggregate,
+}
+
+/// Parse an `aggregates` argument: `{"<field path>": "<aggregate>", ...}`
+///
+/// Field paths use the same syntax as `jsonb_ivm_set_path` and are relative to
+/// the document containing the array (e.g., `"stats.post_count"`).
+///
+/// # Errors
+///
+/// Returns an error if `spec` is not an object, a path is invalid, or an
+/// aggregate expression is not recognized.
+pub fn parse_aggregates(spec: &Value) -> Result<Vec<AggregateField>, String> {
+    let Some(spec_obj) = spec.as_object() else {
+        return Err(format!(
+            "aggregates must be a JSONB object, got: {}",
+            value_type_name(spec)
+        ));
+    };
+
+    spec_obj
+        .iter()
+        .map(|(path, expr)| {
+            let Some(expr) = expr.as_str() else {
+                return Err(format!(
+                    "Aggregate for '{path}' must be a string, got: {}",
+                    value_type_name(expr)
+                ));
+            };
+            let segments =
+                parse_path(path).map_err(|e| format!("Invalid aggregate path '{path}': {e}"))?;
+            Ok(AggregateField {
+                path: path.clone(),
+                segments,
+                aggregate: Aggregate::parse(expr)?,
+            })
+        })
+        .collect()
+}
+
+/// Run an array operation and keep the declared aggregates of its array consistent
+///
+/// `op` receives the document and returns it with the array at `array_path`
+/// modified. The elements it added and removed are found by comparing the
+/// array before and after; `count` and `sum` fields are adjusted by them, and
+/// `min`/`max` fields are rescanned only when a removed element held the
+/// current extreme. Missing (or `null`) aggregate fields are computed from the
+/// whole array. Nothing is written when the array did not change.
+pub fn with_aggregates<T>(
+    target: Value,
+    array_path: &str,
+    aggregates: &Value,
+    op: impl FnOnce(Value) -> (Value, T),
+) -> (Value, T) {
+    let fields = parse_aggregates(aggregates).unwrap_or_else(|e| error!("{}", e));
+    if fields.is_empty() {
+        return op(target);
+    }
+
+    let before = target
+        .get(array_path)
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    let (mut result, outcome) = op(target);
+
+    let Some(after) = result.get(array_path).and_then(Value::as_array) else {
+        return (result, outcome);
+    }; // Missing array ignored, nothing to aggregate
+
+    let (removed, added) = diff_elements(&before, after);
+    if removed.is_empty() && added.is_empty() {
+        return (result, outcome);
+    }
+
+    let new_values: Vec<Value> = fields
+        .iter()
+        .map(|field| {
+            let current = navigate_path(&result, &field.segments);
+            aggregate_value(field, current, &removed, &added, after)
+                .unwrap_or_else(|e| error!("{}", e))
+        })
+        .collect();
+
+    for (field, value) in fields.iter().zip(new_values) {
+        set_path(&mut result, &field.segments, value)
+            .unwrap_or_else(|e| error!("Failed to set path '{}': {}", field.path, e));
+    }
+
+    (result, outcome)
+}
+
+/// Elements of `before` missing from `after` (removed) and the other way round (added)
+///
+/// Elements are compared by content, so an updated element shows up as one
+/// removal and one addition.
+fn diff_elements<'a>(before: &'a [Value], after: &'a [Value]) -> (Vec<&'a Value>, Vec<&'a Value>) {
+    let mut unmatched: HashMap<String, usize> = HashMap::with_capacity(before.len());
+    for elem in before {
+        *unmatched.entry(value_key(elem)).or_default() += 1;
+    }
+
+    let mut added = Vec::new();
+    for elem in after {
+        match unmatched.get_mut(&value_key(elem)) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => added.push(elem),
+        }
+    }
+
+    let mut removed = Vec::new();
+    for elem in before {
+        if let Some(count) = unmatched.get_mut(&value_key(elem)) {
+            if *count > 0 {
+                *count -= 1;
+                removed.push(elem);
+            }
+        }
+    }
+
+    (removed, added)
+}
+
+/// New value of an aggregate field, from its `current` value and the array changes
+fn aggregate_value(
+    field: &AggregateField,
+    current: Option<&Value>,
+    removed: &[&Value],
+    added: &[&Value],
+    after: &[Value],
+) -> Result<Value, String> {
+    let current = current.filter(|value| !value.is_null());
+
+    match &field.aggregate {
+        Aggregate::Count => match current {
+            None => Ok(Value::from(after.len())),
+            Some(Value::Number(n)) => {
+                let Some(count) = n.as_i64() else {
+                    return Ok(Value::from(after.len()));
+                };
+                let delta = i64::try_from(added.len()).unwrap_or(i64::MAX)
+                    - i64::try_from(removed.len()).unwrap_or(i64::MAX);
+                Ok(Value::from(count.saturating_add(delta)))
+            }
+            Some(other) => Err(not_a_number(field, other)),
+        },
+        Aggregate::Sum(key) => {
+            let total = match current {
+                None => sum_of(after.iter(), key)?,
+                Some(Value::Number(n)) => {
+                    numeric(n) + sum_of(added.iter().copied(), key)?
+                        - sum_of(removed.iter().copied(), key)?
+                }
+                Some(other) => return Err(not_a_number(field, other)),
+            };
+            numeric_text_to_value(&total.to_string())
+                .map_err(|e| format!("Cannot update aggregate '{}': {}", field.path, e))
+        }
+        Aggregate::Min(key) => Ok(extreme(current, key, Ordering::Less, removed, added, after)),
+        Aggregate::Max(key) => Ok(extreme(
+            current,
+            key,
+            Ordering::Greater,
+            removed,
+            added,
+            after,
+        )),
+    }
+}
+
+/// New `min` (`wanted = Less`) or `max` (`wanted = Greater`) of `key`
+///
+/// Values compare like unsorted array elements (`compare_values`); elements
+/// without the key or with `null` are skipped, and an array without values
+/// gives `null`.
+fn extreme(
+    current: Option<&Value>,
+    key: &str,
+    wanted: Ordering,
+    removed: &[&Value],
+    added: &[&Value],
+    after: &[Value],
+) -> Value {
+    let key_value = |elem: &Value| elem.get(key).filter(|value| !value.is_null()).cloned();
+
+    // Only losing the extreme itself (or not knowing it) requires a rescan
+    let rescan = current.is_none_or(|stored| {
+        removed
+            .iter()
+            .filter_map(|elem| key_value(elem))
+            .any(|value| compare_values(&value, stored) == Ordering::Equal)
+    });
+
+    let candidates: Vec<Value> = if rescan {
+        after.iter().filter_map(key_value).collect()
+    } else {
+        added
+            .iter()
+            .filter_map(|elem| key_value(elem))
+            .chain(current.cloned())
+            .collect()
+    };
+
+    candidates
+        .into_iter()
+        .reduce(|best, value| {
+            if compare_values(&value, &best) == wanted {
+                value
+            } else {
+                best
+            }
+        })
+        .unwrap_or(Value::Null)
+}
+
+/// Sum of the numeric values of `key`, skipping missing and `null` values
+fn sum_of<'a>(elements: impl Iterator<Item = &'a Value>, key: &str) -> Result<AnyNumeric, String> {
+    let mut total = AnyNumeric::from(0);
+    for elem in elements {
+        match elem.get(key) {
+            None | Some(Value::Null) => {}
+            Some(Value::Number(n)) => total = total + numeric(n),
+            Some(other) => {
+                return Err(format!(
+                    "sum({key}): element value is not a number, found: {}",
+                    value_type_name(other)
+                ))
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Convert a JSON number to `numeric` through its text form
+fn numeric(n: &serde_json::Number) -> AnyNumeric {
+    AnyNumeric::from_str(&n.to_string()).unwrap_or_else(|e| error!("{}", e))
+}
+
+fn not_a_number(field: &AggregateField, found: &Value) -> String {
+    format!(
+        "Aggregate field '{}' is not a number, found: {}",
+        field.path,
+        value_type_name(found)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn field(path: &str, expr: &str) -> AggregateField {
+        AggregateField {
+            path: path.to_string(),
+            segments: parse_path(path).unwrap(),
+            aggregate: Aggregate::parse(expr).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_aggregate() {
+        assert_eq!(Aggregate::parse("count"), Ok(Aggregate::Count));
+        assert_eq!(Aggregate::parse("COUNT(*)"), Ok(Aggregate::Count));
+        assert_eq!(
+            Aggregate::parse("sum(likes)"),
+            Ok(Aggregate::Sum("likes".into()))
+        );
+        assert_eq!(
+            Aggregate::parse(" MAX( created_at ) "),
+            Ok(Aggregate::Max("created_at".into()))
+        );
+        assert!(Aggregate::parse("avg(likes)").is_err());
+        assert!(Aggregate::parse("min()").is_err());
+        assert!(Aggregate::parse("max(created_at").is_err());
+    }
+
+    #[test]
+    fn test_parse_aggregates_spec() {
+        let fields = parse_aggregates(&json!({"stats.post_count": "count"})).unwrap();
+        assert_eq!(fields, vec![field("stats.post_count", "count")]);
+        assert!(parse_aggregates(&json!({})).unwrap().is_empty());
+        assert!(parse_aggregates(&json!(["count"])).is_err());
+        assert!(parse_aggregates(&json!({"n": 1})).is_err());
+        assert!(parse_aggregates(&json!({"a..b": "count"})).is_err());
+    }
+
+    #[test]
+    fn test_diff_elements() {
+        let before = vec![json!({"id": 1}), json!({"id": 2}), json!({"id": 2})];
+        let after = vec![json!({"id": 2}), json!({"id": 3})];
+        let (removed, added) = diff_elements(&before, &after);
+        assert_eq!(removed, vec![&json!({"id": 1}), &json!({"id": 2})]);
+        assert_eq!(added, vec![&json!({"id": 3})]);
+    }
+
+    #[test]
+    fn test_count_is_adjusted_or_computed() {
+        let after = vec![json!({"id": 1}), json!({"id": 2})];
+        let added = [&after[1]];
+        let count = field("n", "count");
+        assert_eq!(
+            aggregate_value(&count, Some(&json!(10)), &[], &added, &after),
+            Ok(json!(11))
+        );
+        assert_eq!(
+            aggregate_value(&count, None, &[], &added, &after),
+            Ok(json!(2))
+        );
+        assert!(aggregate_value(&count, Some(&json!("10")), &[], &added, &after).is_err());
+    }
+
+    #[test]
+    fn test_max_rescans_only_when_extreme_removed() {
+        let removed_elem = json!({"at": "2025-01-03"});
+        let after = vec![json!({"at": "2025-01-01"}), json!({"at": "2025-01-02"})];
+        let latest = field("latest", "max(at)");
+
+        // The stored extreme is trusted while it is not removed
+        assert_eq!(
+            aggregate_value(
+                &latest,
+                Some(&json!("2025-01-09")),
+                &[],
+                &[&after[0]],
+                &after
+            ),
+            Ok(json!("2025-01-09"))
+        );
+        assert_eq!(
+            aggregate_value(
+                &latest,
+                Some(&json!("2025-01-03")),
+                &[&removed_elem],
+                &[],
+                &after
+            ),
+            Ok(json!("2025-01-02"))
+        );
+        assert_eq!(
+            aggregate_value(&field("m", "min(at)"), None, &[], &[&after[1]], &after),
+            Ok(json!("2025-01-01"))
+        );
+        assert_eq!(
+            aggregate_value(
+                &latest,
+                Some(&json!("2025-01-03")),
+                &[&removed_elem],
+                &[],
+                &[]
+            ),
+            Ok(Value::Null)
+        );
+    }
+}
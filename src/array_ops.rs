@@ -8,10 +8,17 @@
 use pgrx::prelude::*;
 use pgrx::JsonB;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 // Import from other modules
-use crate::search::find_by_int_id_optimized;
+use crate::aggregates::with_aggregates;
+use crate::counters::{increment_text, JsonbText};
+use crate::merge::UpdateMode;
+use crate::missing::{find_array, find_array_mut, resolve_array, OnMissing};
+use crate::path::{parse_path, PathSegment};
+use crate::search::{find_by_int_id_optimized, value_key};
+use crate::sort::{parse_order_spec, OrderSpec};
+use crate::value_type_name;
 
 /// Update a single element in a JSONB array by matching a key-value predicate
 ///
@@ -20,7 +27,11 @@ use crate::search::find_by_int_id_optimized;
 /// * `array_path` - Path to the array within the document (e.g., `"dns_servers"`)
 /// * `match_key` - Key to match on (e.g., "id")
 /// * `match_value` - Value to match (e.g., 42)
-/// * `updates` - JSONB object to merge into matched element
+/// * `updates` - JSONB object to apply to the matched element
+/// * `mode` - `merge` (default), `deep-merge`, `replace`, or `merge-patch` (RFC 7396)
+/// * `on_missing` - `'create'` (default) or `'ignore'` return the document
+///   unchanged when the array is missing, `'error'` raises
+/// * `aggregates` - Aggregate fields to maintain (see `jsonb_array_insert_where`)
 ///
 /// # Returns
 /// Updated JSONB document with modified array element
@@ -45,6 +56,7 @@ use crate::search::find_by_int_id_optimized;
 /// - O(n) complexity where n = array length
 /// - For nested paths, use `jsonb_set` with `jsonb_array_update_where`
 #[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)] // SQL signature with optional trailing arguments
 #[pg_extern(immutable, parallel_safe, strict)]
 pub fn jsonb_array_update_where(
     target: JsonB,
@@ -52,51 +64,123 @@ pub fn jsonb_array_update_where(
     match_key: &str,
     match_value: JsonB,
     updates: JsonB,
+    mode: default!(&str, "'merge'"),
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
 ) -> JsonB {
     // No Option unwrapping needed - strict guarantees non-NULL
-    let mut target_value: Value = target.0;
-
-    // Navigate to array location (single level for now)
-    let Some(array) = target_value.get_mut(array_path) else {
-        error!("Path '{}' does not exist in document", array_path);
-    };
+    let (result, _stats) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        update_document(
+            target_value,
+            array_path,
+            match_key,
+            &match_value.0,
+            &updates.0,
+            mode,
+            on_missing,
+        )
+    });
+    JsonB(result)
+}
 
-    // Validate it's an array
-    let Some(array_items) = array.as_array_mut() else {
-        error!(
-            "Path '{}' does not point to an array, found: {}",
+/// Update a single array element and report what happened
+///
+/// Same as `jsonb_array_update_where`, but also returns whether an element
+/// matched and whether the update actually changed it, so callers can detect
+/// projection drift.
+///
+/// # Returns
+///
+/// A single row `(result jsonb, matched int, modified bool)`
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT matched, modified FROM jsonb_array_update_where_stats(
+///     '{"dns_servers": [{"id": 42, "ip": "8.8.8.8"}]}'::jsonb,
+///     'dns_servers',
+///     'id',
+///     '42'::jsonb,
+///     '{"ip": "8.8.8.8"}'::jsonb
+/// );
+/// -- Returns: 1, false (element already up to date)
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)] // SQL signature with optional trailing arguments
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_array_update_where_stats(
+    target: JsonB,
+    array_path: &str,
+    match_key: &str,
+    match_value: JsonB,
+    updates: JsonB,
+    mode: default!(&str, "'merge'"),
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
+) -> TableIterator<
+    'static,
+    (
+        name!(result, JsonB),
+        name!(matched, i32),
+        name!(modified, bool),
+    ),
+> {
+    let (result, stats) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        update_document(
+            target_value,
             array_path,
-            value_type_name(array)
-        );
-    };
+            match_key,
+            &match_value.0,
+            &updates.0,
+            mode,
+            on_missing,
+        )
+    });
+    TableIterator::once(stats.into_row(result))
+}
 
-    // Extract match value as serde_json::Value
-    let match_val = match_value.0;
+/// Shared implementation of `jsonb_array_update_where` and its `_stats` variant
+fn update_document(
+    mut target_value: Value,
+    array_path: &str,
+    match_key: &str,
+    match_val: &Value,
+    updates: &Value,
+    mode: &str,
+    on_missing: &str,
+) -> (Value, ArrayOpStats) {
+    let update_mode = UpdateMode::parse(mode).unwrap_or_else(|e| error!("{}", e));
 
     // Security: Validate depth limits to prevent DoS attacks
-    crate::validate_depth(&updates.0, crate::MAX_JSONB_DEPTH).unwrap_or_else(|e| error!("{}", e));
+    crate::validate_depth(updates, crate::MAX_JSONB_DEPTH).unwrap_or_else(|e| error!("{}", e));
 
     // Validate updates is an object
-    let Some(updates_obj) = updates.0.as_object() else {
+    let Some(updates_obj) = updates.as_object() else {
         error!(
             "updates argument must be a JSONB object, got: {}",
-            value_type_name(&updates.0)
+            value_type_name(updates)
         );
     };
 
+    let mut stats = ArrayOpStats::default();
+
+    // Navigate to array location (single level for now)
+    let Some(array_items) = array_at(&mut target_value, array_path, on_missing) else {
+        return (target_value, stats);
+    }; // Missing array ignored, return unchanged
+
     // Find matching element using optimized search
-    let match_idx = find_element_by_match(array_items, match_key, &match_val);
+    let match_idx = find_element_by_match(array_items, match_key, match_val);
 
     // Apply update if match found
     if let Some(idx) = match_idx {
-        if let Some(elem_obj) = array_items[idx].as_object_mut() {
-            for (key, value) in updates_obj {
-                elem_obj.insert(key.clone(), value.clone());
-            }
-        }
+        let before = array_items[idx].clone();
+        update_mode.apply(&mut array_items[idx], updates_obj);
+        stats.record_match(array_items[idx] != before);
     }
 
-    JsonB(target_value)
+    (target_value, stats)
 }
 
 /// Batch update multiple elements in a JSONB array
@@ -105,7 +189,12 @@ pub fn jsonb_array_update_where(
 /// * `target` - JSONB document containing the array
 /// * `array_path` - Path to the array (e.g., `"dns_servers"`)
 /// * `match_key` - Key to match on (e.g., `"id"`)
-/// * `updates_array` - Array of {`match_value`, updates} pairs
+/// * `updates_array` - Array of {`match_value`, updates} pairs; match values
+///   can be integers, strings or UUIDs, compared like `jsonb_array_delete_where_batch`
+/// * `mode` - How each update is applied (see `jsonb_array_update_where`)
+/// * `on_missing` - `'create'` (default) or `'ignore'` return the document
+///   unchanged when the array is missing, `'error'` raises
+/// * `aggregates` - Aggregate fields to maintain (see `jsonb_array_insert_where`)
 ///
 /// # Example
 /// ```sql
@@ -131,23 +220,95 @@ pub fn jsonb_array_update_where_batch(
     array_path: &str,
     match_key: &str,
     updates_array: JsonB,
+    mode: default!(&str, "'merge'"),
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
 ) -> JsonB {
-    let mut target_value: Value = target.0;
+    let (result, _stats) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        update_batch_document(
+            target_value,
+            array_path,
+            match_key,
+            &updates_array.0,
+            mode,
+            on_missing,
+        )
+    });
+    JsonB(result)
+}
 
-    let Some(array) = target_value.get_mut(array_path) else {
-        error!("Path '{}' does not exist in document", array_path)
-    };
+/// Batch update array elements and report what happened
+///
+/// Same as `jsonb_array_update_where_batch`, but also returns how many
+/// elements matched an update spec and whether any of them changed.
+///
+/// # Returns
+///
+/// A single row `(result jsonb, matched int, modified bool)`
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT matched, modified FROM jsonb_array_update_where_batch_stats(
+///     '{"dns_servers": [{"id": 1}, {"id": 2}]}'::jsonb,
+///     'dns_servers',
+///     'id',
+///     '[
+///         {"match_value": 1, "updates": {"ip": "1.1.1.1"}},
+///         {"match_value": 9, "updates": {"ip": "9.9.9.9"}}
+///     ]'::jsonb
+/// );
+/// -- Returns: 1, true
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_array_update_where_batch_stats(
+    target: JsonB,
+    array_path: &str,
+    match_key: &str,
+    updates_array: JsonB,
+    mode: default!(&str, "'merge'"),
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
+) -> TableIterator<
+    'static,
+    (
+        name!(result, JsonB),
+        name!(matched, i32),
+        name!(modified, bool),
+    ),
+> {
+    let (result, stats) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        update_batch_document(
+            target_value,
+            array_path,
+            match_key,
+            &updates_array.0,
+            mode,
+            on_missing,
+        )
+    });
+    TableIterator::once(stats.into_row(result))
+}
 
-    let Some(array_items) = array.as_array_mut() else {
-        error!("Path '{}' does not point to an array", array_path)
-    };
+/// Shared implementation of `jsonb_array_update_where_batch` and its `_stats` variant
+fn update_batch_document(
+    mut target_value: Value,
+    array_path: &str,
+    match_key: &str,
+    updates_array: &Value,
+    mode: &str,
+    on_missing: &str,
+) -> (Value, ArrayOpStats) {
+    let update_mode = UpdateMode::parse(mode).unwrap_or_else(|e| error!("{}", e));
 
-    let Some(updates_list) = updates_array.0.as_array() else {
+    let Some(updates_list) = updates_array.as_array() else {
         error!("updates_array must be a JSONB array")
     };
 
     // Build hashmap of updates for O(1) lookup
-    let mut update_map: HashMap<i64, &serde_json::Map<String, Value>> =
+    let mut update_map: HashMap<String, &serde_json::Map<String, Value>> =
         HashMap::with_capacity(updates_list.len());
 
     for update_spec in updates_list {
@@ -155,10 +316,7 @@ pub fn jsonb_array_update_where_batch(
             continue;
         }; // Skip malformed specs
 
-        let Some(match_value) = spec_obj
-            .get("match_value")
-            .and_then(serde_json::Value::as_i64)
-        else {
+        let Some(match_value) = spec_obj.get("match_value") else {
             continue;
         };
 
@@ -166,24 +324,31 @@ pub fn jsonb_array_update_where_batch(
             continue;
         };
 
-        update_map.insert(match_value, updates_obj);
+        update_map.insert(value_key(match_value), updates_obj);
     }
 
+    let mut stats = ArrayOpStats::default();
+
+    let Some(array_items) = array_at(&mut target_value, array_path, on_missing) else {
+        return (target_value, stats);
+    }; // Missing array ignored, return unchanged
+
     // Single pass through array, apply all matching updates
     for element in array_items.iter_mut() {
-        if let Some(elem_obj) = element.as_object_mut() {
-            if let Some(elem_id) = elem_obj.get(match_key).and_then(serde_json::Value::as_i64) {
-                if let Some(updates_obj) = update_map.get(&elem_id) {
-                    // Apply updates
-                    for (key, value) in *updates_obj {
-                        elem_obj.insert(key.clone(), value.clone());
-                    }
-                }
+        if !element.is_object() {
+            continue;
+        }
+        if let Some(elem_id) = element.get(match_key) {
+            if let Some(updates_obj) = update_map.get(&value_key(elem_id)) {
+                // Apply updates
+                let before = element.clone();
+                update_mode.apply(element, updates_obj);
+                stats.record_match(*element != before);
             }
         }
     }
 
-    JsonB(target_value)
+    (target_value, stats)
 }
 
 /// Batch update arrays across multiple JSONB documents
@@ -193,7 +358,11 @@ pub fn jsonb_array_update_where_batch(
 /// * `array_path` - Path to array in each document
 /// * `match_key` - Key to match on
 /// * `match_value` - Value to match
-/// * `updates` - JSONB object to merge
+/// * `updates` - JSONB object to apply
+/// * `mode` - How the update is applied (see `jsonb_array_update_where`)
+/// * `on_missing` - `'create'` (default) or `'ignore'` return the document
+///   unchanged when the array is missing, `'error'` raises
+/// * `aggregates` - Aggregate fields to maintain (see `jsonb_array_insert_where`)
 ///
 /// # Returns
 /// SETOF jsonb - Set of updated JSONB documents (same order as input)
@@ -231,6 +400,7 @@ pub fn jsonb_array_update_where_batch(
 /// ```
 #[allow(clippy::needless_pass_by_value)]
 #[allow(clippy::needless_collect)]
+#[allow(clippy::too_many_arguments)] // SQL signature with optional trailing arguments
 #[pg_extern(immutable, parallel_safe, strict)]
 pub fn jsonb_array_update_multi_row(
     targets: pgrx::Array<JsonB>,
@@ -238,8 +408,13 @@ pub fn jsonb_array_update_multi_row(
     match_key: &str,
     match_value: JsonB,
     updates: JsonB,
+    mode: default!(&str, "'merge'"),
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
 ) -> TableIterator<'static, (name!(result, JsonB),)> {
     let match_val = match_value.0;
+    let mode_owned = mode.to_string();
+    let on_missing_owned = on_missing.to_string();
     let Some(updates_obj) = updates.0.as_object() else {
         error!("updates argument must be a JSONB object")
     };
@@ -261,6 +436,9 @@ pub fn jsonb_array_update_multi_row(
             &match_key_owned,
             JsonB(match_val.clone()),
             JsonB(Value::Object(updates_obj.clone())),
+            &mode_owned,
+            &on_missing_owned,
+            JsonB(aggregates.0.clone()),
         );
         (result,)
     }))
@@ -277,6 +455,9 @@ pub fn jsonb_array_update_multi_row(
 /// * `array_path` - Path to the array (e.g., "posts")
 /// * `match_key` - Key to match on (e.g., "id")
 /// * `match_value` - Value to match for deletion
+/// * `on_missing` - `'create'` (default) or `'ignore'` return the document
+///   unchanged when the array is missing, `'error'` raises
+/// * `aggregates` - Aggregate fields to maintain (see `jsonb_array_insert_where`)
 ///
 /// # Returns
 ///
@@ -317,6 +498,7 @@ pub fn jsonb_array_update_multi_row(
 /// )
 /// WHERE data->'posts' @> jsonb_build_array(jsonb_build_object('id', OLD.pk_post));
 /// ```
+#[allow(clippy::needless_pass_by_value)]
 #[pg_extern(immutable, parallel_safe, strict)]
 #[must_use]
 pub fn jsonb_array_delete_where(
@@ -324,27 +506,239 @@ pub fn jsonb_array_delete_where(
     array_path: &str,
     match_key: &str,
     match_value: JsonB,
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
 ) -> JsonB {
-    let mut target_value: Value = target.0;
+    let (result, _stats) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        delete_document(
+            target_value,
+            array_path,
+            match_key,
+            &match_value.0,
+            on_missing,
+        )
+    });
+    JsonB(result)
+}
 
-    // Navigate to array location
-    let Some(array) = target_value.get_mut(array_path) else {
-        return JsonB(target_value);
-    }; // Array doesn't exist, return unchanged
+/// Delete an array element and report whether anything was removed
+///
+/// Same as `jsonb_array_delete_where`, but also returns how many elements
+/// were deleted (0 or 1) and whether the document was modified.
+///
+/// # Returns
+///
+/// A single row `(result jsonb, matched int, modified bool)`
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT matched, modified FROM jsonb_array_delete_where_stats(
+///     '{"posts": [{"id": 1}]}'::jsonb,
+///     'posts',
+///     'id',
+///     '999'::jsonb
+/// );
+/// -- Returns: 0, false
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_array_delete_where_stats(
+    target: JsonB,
+    array_path: &str,
+    match_key: &str,
+    match_value: JsonB,
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
+) -> TableIterator<
+    'static,
+    (
+        name!(result, JsonB),
+        name!(matched, i32),
+        name!(modified, bool),
+    ),
+> {
+    let (result, stats) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        delete_document(
+            target_value,
+            array_path,
+            match_key,
+            &match_value.0,
+            on_missing,
+        )
+    });
+    TableIterator::once(stats.into_row(result))
+}
 
-    // Validate it's an array
-    let Some(array_items) = array.as_array_mut() else {
-        return JsonB(target_value);
-    }; // Not an array, return unchanged
+/// Shared implementation of `jsonb_array_delete_where` and its `_stats` variant
+fn delete_document(
+    mut target_value: Value,
+    array_path: &str,
+    match_key: &str,
+    match_val: &Value,
+    on_missing: &str,
+) -> (Value, ArrayOpStats) {
+    let mut stats = ArrayOpStats::default();
 
-    let match_val = match_value.0;
+    // Navigate to array location
+    let Some(array_items) = array_at(&mut target_value, array_path, on_missing) else {
+        return (target_value, stats);
+    }; // Missing array ignored, return unchanged
 
     // Find and remove matching element using optimized search
-    if let Some(idx) = find_element_by_match(array_items, match_key, &match_val) {
+    if let Some(idx) = find_element_by_match(array_items, match_key, match_val) {
         array_items.remove(idx);
+        stats.record_match(true);
     }
 
-    JsonB(target_value)
+    (target_value, stats)
+}
+
+/// Batch delete elements from a JSONB array by matching a set of values
+///
+/// Removes every element whose `match_key` equals one of `match_values` in a
+/// single pass, instead of re-scanning and re-serializing the document once per
+/// deleted element.
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the array
+/// * `array_path` - Path to the array (e.g., `"posts"`)
+/// * `match_key` - Key to match on (e.g., `"id"`)
+/// * `match_values` - JSONB array of values to delete (e.g., `'[1, 2, 3]'`)
+/// * `on_missing` - `'create'` (default) or `'ignore'` return the document
+///   unchanged when the array is missing, `'error'` raises
+/// * `aggregates` - Aggregate fields to maintain (see `jsonb_array_insert_where`)
+///
+/// # Returns
+///
+/// Updated JSONB with all matching elements removed (or unchanged if no match)
+///
+/// # Examples
+///
+/// ```sql
+/// -- Bulk unfollow: remove users 2 and 3
+/// SELECT jsonb_array_delete_where_batch(
+///     '{"following": [{"id": 1}, {"id": 2}, {"id": 3}, {"id": 4}]}'::jsonb,
+///     'following',
+///     'id',
+///     '[2, 3]'::jsonb
+/// );
+/// -- Result: {"following": [{"id": 1}, {"id": 4}]}
+/// ```
+///
+/// # Performance
+/// - O(n + m) where n = array length, m = number of values to delete
+/// - Match values are looked up in a hash set
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_array_delete_where_batch(
+    target: JsonB,
+    array_path: &str,
+    match_key: &str,
+    match_values: JsonB,
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
+) -> JsonB {
+    let (result, _stats) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        delete_batch_document(
+            target_value,
+            array_path,
+            match_key,
+            &match_values.0,
+            on_missing,
+        )
+    });
+    JsonB(result)
+}
+
+/// Batch delete array elements and report how many were removed
+///
+/// Same as `jsonb_array_delete_where_batch`, but also returns the number of
+/// deleted elements and whether the document was modified.
+///
+/// # Returns
+///
+/// A single row `(result jsonb, matched int, modified bool)`
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT matched FROM jsonb_array_delete_where_batch_stats(
+///     '{"following": [{"id": 1}, {"id": 2}, {"id": 3}]}'::jsonb,
+///     'following',
+///     'id',
+///     '[2, 3, 4]'::jsonb
+/// );
+/// -- Returns: 2
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_array_delete_where_batch_stats(
+    target: JsonB,
+    array_path: &str,
+    match_key: &str,
+    match_values: JsonB,
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
+) -> TableIterator<
+    'static,
+    (
+        name!(result, JsonB),
+        name!(matched, i32),
+        name!(modified, bool),
+    ),
+> {
+    let (result, stats) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        delete_batch_document(
+            target_value,
+            array_path,
+            match_key,
+            &match_values.0,
+            on_missing,
+        )
+    });
+    TableIterator::once(stats.into_row(result))
+}
+
+/// Shared implementation of `jsonb_array_delete_where_batch` and its `_stats` variant
+fn delete_batch_document(
+    mut target_value: Value,
+    array_path: &str,
+    match_key: &str,
+    match_values: &Value,
+    on_missing: &str,
+) -> (Value, ArrayOpStats) {
+    let Some(values_list) = match_values.as_array() else {
+        error!(
+            "match_values must be a JSONB array, got: {}",
+            value_type_name(match_values)
+        );
+    };
+
+    let mut stats = ArrayOpStats::default();
+
+    // Navigate to array location
+    let Some(array_items) = array_at(&mut target_value, array_path, on_missing) else {
+        return (target_value, stats);
+    }; // Missing array ignored, return unchanged
+
+    let delete_set: HashSet<String> = values_list.iter().map(value_key).collect();
+
+    // Single pass: keep elements whose key is not in the delete set
+    let original_len = array_items.len();
+    array_items.retain(|elem| {
+        !elem
+            .get(match_key)
+            .is_some_and(|v| delete_set.contains(&value_key(v)))
+    });
+
+    stats.record_removed(original_len - array_items.len());
+
+    (target_value, stats)
 }
 
 /// Insert an element into a JSONB array with optional sort order maintenance
@@ -357,8 +751,22 @@ pub fn jsonb_array_delete_where(
 /// * `target` - JSONB document containing (or to contain) the array
 /// * `array_path` - Path to the array (e.g., `"posts"`)
 /// * `new_element` - Element to insert
-/// * `sort_key` - Optional key to maintain sort order (e.g., `"created_at"`)
-/// * `sort_order` - Sort direction: "ASC" (default) or "DESC"
+/// * `sort_key` - Optional sort specification: a key (e.g., `"created_at"`) or an
+///   ORDER BY-style list with optional casts
+///   (e.g., `"priority DESC, created_at::timestamptz ASC NULLS LAST, id"`)
+/// * `sort_order` - Direction for terms without one: "ASC" (default) or "DESC"
+/// * `max_len` - Optional maximum array length; surplus elements are evicted from
+///   the tail for DESC arrays and from the head for ASC or unsorted arrays
+/// * `assume_sorted` - Caller asserts the array is already sorted by `sort_key`,
+///   enabling binary search for the insertion point (O(log n) instead of O(n));
+///   not checked, so an unsorted array gets the element at an unspecified position
+/// * `collation` - Optional collation for string sort values (e.g., `"fr_FR"`,
+///   `"und-x-icu"`), for terms without their own `COLLATE` clause
+/// * `on_missing` - `'create'` (default) creates a missing array, `'ignore'`
+///   returns the document unchanged, `'error'` raises
+/// * `aggregates` - Aggregate fields to keep consistent with the array, as
+///   `{"<field path>": "count" | "sum(key)" | "min(key)" | "max(key)"}`
+///   (e.g., `{"stats.post_count": "count", "stats.latest_at": "max(created_at)"}`)
 ///
 /// # Returns
 ///
@@ -388,6 +796,18 @@ pub fn jsonb_array_delete_where(
 /// );
 /// -- Result: Inserts id=2 between id=1 and id=3
 ///
+/// -- Multi-key order: highest priority first, then oldest first
+/// SELECT jsonb_array_insert_where(
+///     '{"tasks": [
+///         {"id": 1, "priority": 2, "created_at": "2025-01-01"},
+///         {"id": 2, "priority": 1, "created_at": "2025-01-01"}
+///     ]}'::jsonb,
+///     'tasks',
+///     '{"id": 3, "priority": 2, "created_at": "2025-01-02"}'::jsonb,
+///     'priority DESC, created_at ASC NULLS LAST, id'
+/// );
+/// -- Result: ids in order 1, 3, 2
+///
 /// -- Create array if doesn't exist
 /// SELECT jsonb_array_insert_where(
 ///     '{}'::jsonb,
@@ -406,86 +826,1561 @@ pub fn jsonb_array_delete_where(
 ///     'DESC'
 /// )
 /// WHERE fk_user = NEW.fk_author;
+///
+/// -- Large sorted array: binary search for the insertion point
+/// SELECT jsonb_array_insert_where(
+///     data,
+///     'events',
+///     '{"id": 10001, "seq": 5000}'::jsonb,
+///     'seq',
+///     'ASC',
+///     NULL,
+///     true
+/// )
+/// FROM tv_timeline;
+///
+/// -- Members ordered by name as French speakers expect
+/// UPDATE tv_team
+/// SET data = jsonb_array_insert_where(
+///     data,
+///     'members',
+///     to_jsonb(NEW.*),
+///     'name',
+///     collation => 'fr_FR'
+/// )
+/// WHERE pk_team = NEW.fk_team;
+///
+/// -- Bounded feed: keep only the latest 50 posts
+/// UPDATE tv_feed
+/// SET data = jsonb_array_insert_where(
+///     data,
+///     'posts',
+///     to_jsonb(NEW.*),
+///     'created_at',
+///     'DESC',
+///     50
+/// )
+/// WHERE fk_user = NEW.fk_author;
 /// ```
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)] // SQL signature with optional trailing arguments
 #[pg_extern(immutable, parallel_safe)]
 pub fn jsonb_array_insert_where(
     target: JsonB,
     array_path: &str,
     new_element: JsonB,
-    sort_key: Option<&str>,
-    sort_order: Option<&str>,
+    sort_key: default!(Option<&str>, "NULL"),
+    sort_order: default!(Option<&str>, "NULL"),
+    max_len: default!(Option<i32>, "NULL"),
+    assume_sorted: default!(bool, false),
+    collation: default!(Option<&str>, "NULL"),
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
 ) -> JsonB {
-    let mut target_value: Value = target.0;
-    let new_elem = new_element.0;
+    let order_spec = parse_sort_spec(sort_key, sort_order, collation);
+    let (result, _stats) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        insert_document(
+            target_value,
+            array_path,
+            new_element.0,
+            order_spec.as_ref(),
+            max_len,
+            assume_sorted,
+            on_missing,
+        )
+    });
+    JsonB(result)
+}
 
-    // Get or create array at path
-    let Some(target_obj) = target_value.as_object_mut() else {
-        error!(
-            "target must be a JSONB object, got: {}",
-            value_type_name(&target_value)
-        );
-    };
+/// Insert an element and report whether the array changed
+///
+/// Same as `jsonb_array_insert_where`, but also returns the number of
+/// inserted elements (always 1) and whether the document was modified. With
+/// `max_len`, `modified` is false when the new element was evicted straight
+/// away, e.g. an old event that does not make the top N.
+///
+/// # Returns
+///
+/// A single row `(result jsonb, matched int, modified bool)`
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT modified FROM jsonb_array_insert_where_stats(
+///     '{"posts": [{"id": 3, "created_at": "2025-01-03"}]}'::jsonb,
+///     'posts',
+///     '{"id": 1, "created_at": "2025-01-01"}'::jsonb,
+///     'created_at',
+///     'DESC',
+///     1
+/// );
+/// -- Returns: false
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)] // SQL signature with optional trailing arguments
+#[pg_extern(immutable, parallel_safe)]
+#[must_use]
+pub fn jsonb_array_insert_where_stats(
+    target: JsonB,
+    array_path: &str,
+    new_element: JsonB,
+    sort_key: default!(Option<&str>, "NULL"),
+    sort_order: default!(Option<&str>, "NULL"),
+    max_len: default!(Option<i32>, "NULL"),
+    assume_sorted: default!(bool, false),
+    collation: default!(Option<&str>, "NULL"),
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
+) -> TableIterator<
+    'static,
+    (
+        name!(result, JsonB),
+        name!(matched, i32),
+        name!(modified, bool),
+    ),
+> {
+    let order_spec = parse_sort_spec(sort_key, sort_order, collation);
+    let (result, stats) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        insert_document(
+            target_value,
+            array_path,
+            new_element.0,
+            order_spec.as_ref(),
+            max_len,
+            assume_sorted,
+            on_missing,
+        )
+    });
+    TableIterator::once(stats.into_row(result))
+}
 
-    let array = target_obj
-        .entry(array_path.to_string())
-        .or_insert_with(|| Value::Array(vec![]));
+/// Shared implementation of `jsonb_array_insert_where` and its `_stats` variant
+fn insert_document(
+    mut target_value: Value,
+    array_path: &str,
+    new_elem: Value,
+    order_spec: Option<&OrderSpec>,
+    max_len: Option<i32>,
+    assume_sorted: bool,
+    on_missing: &str,
+) -> (Value, ArrayOpStats) {
+    // Get or create array at path
+    let created = target_value.get(array_path).is_none();
+    let Some(array_items) = array_at_or_create(&mut target_value, array_path, on_missing) else {
+        return (target_value, ArrayOpStats::default());
+    }; // Missing array ignored, return unchanged
 
-    let Some(array_items) = array.as_array_mut() else {
-        error!(
-            "path '{}' must point to an array or not exist, got: {}",
-            array_path,
-            value_type_name(array)
-        );
-    };
+    // Eviction can undo the insert, so bounded arrays are compared afterwards
+    let before = max_len.map(|_| array_items.clone());
 
-    if let Some(key) = sort_key {
+    if let Some(spec) = order_spec {
         // Find insertion point to maintain sort order
-        let new_sort_val = new_elem.get(key);
-        let order = sort_order.unwrap_or("ASC");
-        let insert_pos = find_insertion_point(array_items, new_sort_val, key, order);
+        let insert_pos = if assume_sorted {
+            find_insertion_point_sorted(array_items, &new_elem, spec)
+        } else {
+            find_insertion_point(array_items, &new_elem, spec)
+        };
         array_items.insert(insert_pos, new_elem);
     } else {
         // No sort - append to end
         array_items.push(new_elem);
     }
 
-    JsonB(target_value)
-}
-
-/// Find the insertion point to maintain sort order
-#[inline]
-#[must_use]
-pub fn find_insertion_point(
-    array: &[Value],
-    new_val: Option<&Value>,
-    sort_key: &str,
-    sort_order: &str,
-) -> usize {
-    let Some(new_val) = new_val else {
-        return array.len();
-    }; // No sort value, insert at end
+    if let Some(max_len) = max_len {
+        evict_to_max_len(array_items, max_len, order_spec);
+    }
 
-    array
-        .iter()
-        .position(|elem| {
-            let Some(elem_val) = elem.get(sort_key) else {
-                return false;
-            }; // Element has no sort key, continue searching
-
-            // Compare values based on sort order
-            if sort_order.eq_ignore_ascii_case("ASC") {
-                compare_values(new_val, elem_val) == std::cmp::Ordering::Less
-            } else {
-                compare_values(new_val, elem_val) == std::cmp::Ordering::Greater
-            }
-        })
-        .unwrap_or(array.len())
+    let mut stats = ArrayOpStats::default();
+    stats.record_match(created || before.is_none_or(|before| before != *array_items));
+    (target_value, stats)
 }
 
-/// Compare two JSON values for ordering
-#[inline]
-#[must_use]
-pub fn compare_values(a: &Value, b: &Value) -> std::cmp::Ordering {
+/// Insert a batch of elements into a JSONB array with optional sort order maintenance
+///
+/// Batch counterpart of `jsonb_array_insert_where`. When `sort_key` is provided,
+/// the batch is merged into the existing sorted array in a single O(n + m) pass
+/// instead of one scan per inserted element. Without `sort_key`, the batch is
+/// appended in order.
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing (or to contain) the array
+/// * `array_path` - Path to the array (e.g., `"posts"`)
+/// * `new_elements` - JSONB array of elements to insert
+/// * `sort_key` - Optional sort specification (same syntax as `jsonb_array_insert_where`)
+/// * `sort_order` - Direction for terms without one: "ASC" (default) or "DESC"
+/// * `max_len` - Optional maximum array length (same eviction as `jsonb_array_insert_where`)
+/// * `collation` - Optional collation for string sort values
+/// * `on_missing` - `'create'` (default) creates a missing array, `'ignore'`
+///   returns the document unchanged, `'error'` raises
+/// * `aggregates` - Aggregate fields to maintain (see `jsonb_array_insert_where`)
+///
+/// # Returns
+///
+/// Updated JSONB with all elements inserted
+///
+/// # Examples
+///
+/// ```sql
+/// -- Bulk post import into a feed sorted by created_at DESC
+/// SELECT jsonb_array_insert_batch(
+///     '{"posts": [
+///         {"id": 4, "created_at": "2025-01-04"},
+///         {"id": 1, "created_at": "2025-01-01"}
+///     ]}'::jsonb,
+///     'posts',
+///     '[
+///         {"id": 3, "created_at": "2025-01-03"},
+///         {"id": 2, "created_at": "2025-01-02"}
+///     ]'::jsonb,
+///     'created_at',
+///     'DESC'
+/// );
+/// -- Result: ids in order 4, 3, 2, 1
+/// ```
+///
+/// # Notes
+/// - Equal sort values are inserted after existing elements, in batch order
+///   (same placement as repeated `jsonb_array_insert_where` calls)
+/// - Elements without a sort value (missing key or `null`) sort as NULLs
+/// - The batch is stably sorted first; pre-sorted batches keep the merge linear
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)] // SQL signature with optional trailing arguments
+#[pg_extern(immutable, parallel_safe)]
+#[must_use]
+pub fn jsonb_array_insert_batch(
+    target: JsonB,
+    array_path: &str,
+    new_elements: JsonB,
+    sort_key: default!(Option<&str>, "NULL"),
+    sort_order: default!(Option<&str>, "NULL"),
+    max_len: default!(Option<i32>, "NULL"),
+    collation: default!(Option<&str>, "NULL"),
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
+) -> JsonB {
+    let order_spec = parse_sort_spec(sort_key, sort_order, collation);
+    let (result, _stats) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        insert_batch_document(
+            target_value,
+            array_path,
+            new_elements.0,
+            order_spec.as_ref(),
+            max_len,
+            on_missing,
+        )
+    });
+    JsonB(result)
+}
+
+/// Insert a batch of elements and report what happened
+///
+/// Same as `jsonb_array_insert_batch`, but also returns the number of
+/// inserted elements and whether the document was modified (see
+/// `jsonb_array_insert_where_stats` for the `max_len` case).
+///
+/// # Returns
+///
+/// A single row `(result jsonb, matched int, modified bool)`
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT matched, modified FROM jsonb_array_insert_batch_stats(
+///     '{"posts": []}'::jsonb,
+///     'posts',
+///     '[{"id": 1}, {"id": 2}]'::jsonb
+/// );
+/// -- Returns: 2, true
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)] // SQL signature with optional trailing arguments
+#[pg_extern(immutable, parallel_safe)]
+#[must_use]
+pub fn jsonb_array_insert_batch_stats(
+    target: JsonB,
+    array_path: &str,
+    new_elements: JsonB,
+    sort_key: default!(Option<&str>, "NULL"),
+    sort_order: default!(Option<&str>, "NULL"),
+    max_len: default!(Option<i32>, "NULL"),
+    collation: default!(Option<&str>, "NULL"),
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
+) -> TableIterator<
+    'static,
+    (
+        name!(result, JsonB),
+        name!(matched, i32),
+        name!(modified, bool),
+    ),
+> {
+    let order_spec = parse_sort_spec(sort_key, sort_order, collation);
+    let (result, stats) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        insert_batch_document(
+            target_value,
+            array_path,
+            new_elements.0,
+            order_spec.as_ref(),
+            max_len,
+            on_missing,
+        )
+    });
+    TableIterator::once(stats.into_row(result))
+}
+
+/// Shared implementation of `jsonb_array_insert_batch` and its `_stats` variant
+fn insert_batch_document(
+    mut target_value: Value,
+    array_path: &str,
+    new_elements: Value,
+    order_spec: Option<&OrderSpec>,
+    max_len: Option<i32>,
+    on_missing: &str,
+) -> (Value, ArrayOpStats) {
+    // Security: Validate depth limits to prevent DoS attacks
+    crate::validate_depth(&new_elements, crate::MAX_JSONB_DEPTH)
+        .unwrap_or_else(|e| error!("{}", e));
+
+    let Value::Array(batch) = new_elements else {
+        error!(
+            "new_elements must be a JSONB array, got: {}",
+            value_type_name(&new_elements)
+        );
+    };
+
+    // Get or create array at path
+    let created = target_value.get(array_path).is_none();
+    let Some(array_items) = array_at_or_create(&mut target_value, array_path, on_missing) else {
+        return (target_value, ArrayOpStats::default());
+    }; // Missing array ignored, return unchanged
+
+    // Eviction can undo the insert, so bounded arrays are compared afterwards
+    let before = max_len.map(|_| array_items.clone());
+    let inserted = batch.len();
+
+    if let Some(spec) = order_spec {
+        let existing = std::mem::take(array_items);
+        *array_items = merge_sorted_batch(existing, batch, spec);
+    } else {
+        // No sort - append to end
+        array_items.extend(batch);
+    }
+
+    if let Some(max_len) = max_len {
+        evict_to_max_len(array_items, max_len, order_spec);
+    }
+
+    let stats = ArrayOpStats {
+        matched: inserted,
+        modified: created || before.map_or(inserted > 0, |before| before != *array_items),
+    };
+    (target_value, stats)
+}
+
+/// Insert an element into an array nested inside a matched array element
+///
+/// Two-level counterpart of `jsonb_array_insert_where`: locates the element of
+/// `array_path` whose `match_key` equals `match_value`, then inserts into its
+/// `nested_path` array (e.g., a comment into `posts[id=42].comments`).
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the outer array
+/// * `array_path` - Path to the outer array (e.g., `"posts"`)
+/// * `match_key` - Key identifying the outer element (e.g., `"id"`)
+/// * `match_value` - Value of `match_key` for the outer element
+/// * `nested_path` - Path to the inner array within that element (e.g., `"comments"`)
+/// * `new_element` - Element to insert into the inner array
+/// * `sort_key` - Optional sort specification for the inner array (same syntax
+///   as `jsonb_array_insert_where`)
+/// * `sort_order` - Direction for terms without one: "ASC" (default) or "DESC"
+/// * `on_missing` - Policy for both arrays: `'create'` (default) creates a
+///   missing inner array, `'ignore'` returns the document unchanged, `'error'` raises
+///
+/// # Returns
+///
+/// Updated JSONB with the element inserted (unchanged if no outer element matches)
+///
+/// # Examples
+///
+/// ```sql
+/// -- New comment on post 42, newest first
+/// UPDATE tv_feed
+/// SET data = jsonb_array_insert_nested(
+///     data,
+///     'posts', 'id', to_jsonb(NEW.fk_post),
+///     'comments',
+///     to_jsonb(NEW.*),
+///     'created_at',
+///     'DESC'
+/// )
+/// WHERE data->'posts' @> jsonb_build_array(jsonb_build_object('id', NEW.fk_post));
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)] // SQL signature with optional trailing arguments
+#[pg_extern(immutable, parallel_safe)]
+#[must_use]
+pub fn jsonb_array_insert_nested(
+    target: JsonB,
+    array_path: &str,
+    match_key: &str,
+    match_value: JsonB,
+    nested_path: &str,
+    new_element: JsonB,
+    sort_key: default!(Option<&str>, "NULL"),
+    sort_order: default!(Option<&str>, "NULL"),
+    on_missing: default!(&str, "'create'"),
+) -> JsonB {
+    let order_spec = parse_sort_spec(sort_key, sort_order, None);
+    let result = update_matched_element(
+        target.0,
+        array_path,
+        match_key,
+        &match_value.0,
+        on_missing,
+        |element| {
+            let (element, _stats) = insert_document(
+                element,
+                nested_path,
+                new_element.0,
+                order_spec.as_ref(),
+                None,
+                false,
+                on_missing,
+            );
+            element
+        },
+    );
+    JsonB(result)
+}
+
+/// Delete an element from an array nested inside a matched array element
+///
+/// Two-level counterpart of `jsonb_array_delete_where` (e.g., remove a
+/// reaction from `posts[id=42].reactions`).
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the outer array
+/// * `array_path` - Path to the outer array (e.g., `"posts"`)
+/// * `match_key` - Key identifying the outer element (e.g., `"id"`)
+/// * `match_value` - Value of `match_key` for the outer element
+/// * `nested_path` - Path to the inner array within that element (e.g., `"reactions"`)
+/// * `nested_match_key` - Key identifying the inner element
+/// * `nested_match_value` - Value of `nested_match_key` for the element to delete
+/// * `on_missing` - Policy for both arrays: `'create'` (default) or `'ignore'`
+///   return the document unchanged when an array is missing, `'error'` raises
+///
+/// # Returns
+///
+/// Updated JSONB with the inner element removed (or unchanged if no match)
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT jsonb_array_delete_nested(
+///     '{"posts": [{"id": 42, "reactions": [{"id": 1}, {"id": 2}]}]}'::jsonb,
+///     'posts', 'id', '42'::jsonb,
+///     'reactions', 'id', '1'::jsonb
+/// );
+/// -- Result: {"posts": [{"id": 42, "reactions": [{"id": 2}]}]}
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)] // SQL signature with optional trailing arguments
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_array_delete_nested(
+    target: JsonB,
+    array_path: &str,
+    match_key: &str,
+    match_value: JsonB,
+    nested_path: &str,
+    nested_match_key: &str,
+    nested_match_value: JsonB,
+    on_missing: default!(&str, "'create'"),
+) -> JsonB {
+    let result = update_matched_element(
+        target.0,
+        array_path,
+        match_key,
+        &match_value.0,
+        on_missing,
+        |element| {
+            let (element, _stats) = delete_document(
+                element,
+                nested_path,
+                nested_match_key,
+                &nested_match_value.0,
+                on_missing,
+            );
+            element
+        },
+    );
+    JsonB(result)
+}
+
+/// Update an element of an array nested inside a matched array element
+///
+/// Two-level counterpart of `jsonb_array_update_where` (e.g., edit comment 7
+/// of `posts[id=42].comments`).
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the outer array
+/// * `array_path` - Path to the outer array (e.g., `"posts"`)
+/// * `match_key` - Key identifying the outer element (e.g., `"id"`)
+/// * `match_value` - Value of `match_key` for the outer element
+/// * `nested_path` - Path to the inner array within that element (e.g., `"comments"`)
+/// * `nested_match_key` - Key identifying the inner element
+/// * `nested_match_value` - Value of `nested_match_key` for the element to update
+/// * `updates` - JSONB object to apply to the inner element
+/// * `mode` - `merge` (default), `deep-merge`, `replace`, or `merge-patch` (RFC 7396)
+/// * `on_missing` - Policy for both arrays: `'create'` (default) or `'ignore'`
+///   return the document unchanged when an array is missing, `'error'` raises
+///
+/// # Returns
+///
+/// Updated JSONB with the inner element modified (or unchanged if no match)
+///
+/// # Examples
+///
+/// ```sql
+/// -- Comment 7 was edited
+/// UPDATE tv_feed
+/// SET data = jsonb_array_update_nested(
+///     data,
+///     'posts', 'id', to_jsonb(NEW.fk_post),
+///     'comments', 'id', to_jsonb(NEW.pk_comment),
+///     jsonb_build_object('body', NEW.body, 'edited', true)
+/// )
+/// WHERE data->'posts' @> jsonb_build_array(jsonb_build_object('id', NEW.fk_post));
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)] // SQL signature with optional trailing arguments
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_array_update_nested(
+    target: JsonB,
+    array_path: &str,
+    match_key: &str,
+    match_value: JsonB,
+    nested_path: &str,
+    nested_match_key: &str,
+    nested_match_value: JsonB,
+    updates: JsonB,
+    mode: default!(&str, "'merge'"),
+    on_missing: default!(&str, "'create'"),
+) -> JsonB {
+    let result = update_matched_element(
+        target.0,
+        array_path,
+        match_key,
+        &match_value.0,
+        on_missing,
+        |element| {
+            let (element, _stats) = update_document(
+                element,
+                nested_path,
+                nested_match_key,
+                &nested_match_value.0,
+                &updates.0,
+                mode,
+                on_missing,
+            );
+            element
+        },
+    );
+    JsonB(result)
+}
+
+/// Add `delta` to a numeric field of a matched array element
+///
+/// Array-element counterpart of `jsonb_ivm_increment` (e.g., bump
+/// `posts[id=42].like_count` when a like is inserted). A missing field counts
+/// as zero and is created; integer counters stay integers and `numeric`
+/// results keep every digit.
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the array
+/// * `array_path` - Path to the array (e.g., `"posts"`)
+/// * `match_key` - Key identifying the element (e.g., `"id"`)
+/// * `match_value` - Value of `match_key` for the element to update
+/// * `field_path` - Path to the counter within the element (e.g., `"stats.like_count"`)
+/// * `delta` - Amount to add (negative to decrement)
+/// * `on_missing` - `'create'` (default) or `'ignore'` return the document
+///   unchanged when the array is missing, `'error'` raises
+///
+/// # Returns
+///
+/// Updated JSONB with the counter incremented (or unchanged if no match)
+///
+/// # Notes
+///
+/// Raises an error if the field holds a value that is not a number.
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT jsonb_array_increment_where(
+///     '{"posts": [{"id": 42, "like_count": 7}]}'::jsonb,
+///     'posts', 'id', '42'::jsonb,
+///     'like_count', 1
+/// );
+/// -- Result: {"posts": [{"id": 42, "like_count": 8}]}
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_array_increment_where(
+    target: JsonbText,
+    array_path: &str,
+    match_key: &str,
+    match_value: JsonB,
+    field_path: &str,
+    delta: AnyNumeric,
+    on_missing: default!(&str, "'create'"),
+) -> JsonbText {
+    let segments = parse_path(field_path)
+        .unwrap_or_else(|e| error!("Invalid field path '{}': {}", field_path, e));
+
+    // Locate the element in the parsed document, then edit the exact text
+    let mut target_value: Value =
+        serde_json::from_str(&target.0).unwrap_or_else(|e| error!("{}", e));
+    let Some(idx) = array_at(&mut target_value, array_path, on_missing)
+        .and_then(|array_items| find_element_by_match(array_items, match_key, &match_value.0))
+    else {
+        return target;
+    };
+
+    let element_segments: Vec<PathSegment> = [
+        PathSegment::Key(array_path.to_string()),
+        PathSegment::Index(idx),
+    ]
+    .into_iter()
+    .chain(segments)
+    .collect();
+    JsonbText(increment_text(
+        &target.0,
+        &element_segments,
+        field_path,
+        &delta,
+    ))
+}
+
+/// Replace the element of the outer array matching `match_key` with `op(element)`
+///
+/// Shared locator of the `_nested` functions. The document is returned
+/// unchanged when no element matches.
+fn update_matched_element(
+    mut target_value: Value,
+    array_path: &str,
+    match_key: &str,
+    match_val: &Value,
+    on_missing: &str,
+    op: impl FnOnce(Value) -> Value,
+) -> Value {
+    let Some(array_items) = array_at(&mut target_value, array_path, on_missing) else {
+        return target_value;
+    }; // Missing array ignored, return unchanged
+
+    if let Some(idx) = find_element_by_match(array_items, match_key, match_val) {
+        let element = std::mem::take(&mut array_items[idx]);
+        array_items[idx] = op(element);
+    }
+
+    target_value
+}
+
+/// Truncate a JSONB array to at most `n` elements
+///
+/// Standalone trimming for bounded arrays (feeds, recent-activity lists).
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the array
+/// * `array_path` - Path to the array (e.g., `"posts"`)
+/// * `n` - Maximum number of elements to keep
+/// * `from_end` - Drop surplus elements from the end (default) or from the start
+/// * `on_missing` - `'create'` (default) or `'ignore'` return the document
+///   unchanged when the array is missing, `'error'` raises
+///
+/// # Returns
+///
+/// Updated JSONB with the array truncated (or unchanged if it doesn't exist)
+///
+/// # Examples
+///
+/// ```sql
+/// -- Keep the 2 first elements
+/// SELECT jsonb_array_truncate(
+///     '{"posts": [{"id": 5}, {"id": 4}, {"id": 3}]}'::jsonb,
+///     'posts',
+///     2
+/// );
+/// -- Result: {"posts": [{"id": 5}, {"id": 4}]}
+///
+/// -- Keep the 2 last elements
+/// SELECT jsonb_array_truncate(
+///     '{"posts": [{"id": 1}, {"id": 2}, {"id": 3}]}'::jsonb,
+///     'posts',
+///     2,
+///     false
+/// );
+/// -- Result: {"posts": [{"id": 2}, {"id": 3}]}
+/// ```
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_array_truncate(
+    target: JsonB,
+    array_path: &str,
+    n: i32,
+    from_end: default!(bool, true),
+    on_missing: default!(&str, "'create'"),
+) -> JsonB {
+    let mut target_value: Value = target.0;
+
+    let Ok(keep) = usize::try_from(n) else {
+        error!("n must not be negative, got: {}", n);
+    };
+
+    // Navigate to array location
+    let Some(array_items) = array_at(&mut target_value, array_path, on_missing) else {
+        return JsonB(target_value);
+    }; // Missing array ignored, return unchanged
+
+    truncate_array(array_items, keep, from_end);
+
+    JsonB(target_value)
+}
+
+/// Check whether a JSONB array is sorted by a key
+///
+/// Debug helper for `jsonb_array_insert_where(..., assume_sorted => true)`:
+/// binary search is only correct when the array satisfies the sort invariant,
+/// so callers can validate projections before (or while) relying on it.
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the array
+/// * `array_path` - Path to the array (e.g., `"posts"`)
+/// * `sort_key` - Sort specification the array is expected to follow (a key or
+///   an ORDER BY-style list, as in `jsonb_array_insert_where`)
+/// * `sort_order` - Direction for terms without one: "ASC" (default) or "DESC"
+/// * `collation` - Optional collation for string sort values (default NULL: bytewise)
+/// * `on_missing` - `'create'` (default) or `'ignore'` report a missing array as
+///   sorted, `'error'` raises
+///
+/// # Returns
+///
+/// true if the elements are in order (ties allowed, missing keys sorting as
+/// NULLs); true for a missing array, `NULL` for a `NULL` target
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT jsonb_array_is_sorted(
+///     '{"posts": [{"seq": 1}, {"seq": 2}, {"seq": 2}, {"title": "no seq"}]}'::jsonb,
+///     'posts',
+///     'seq'
+/// );
+/// -- Returns: true
+///
+/// -- Find projections that violate the invariant
+/// SELECT pk FROM tv_feed
+/// WHERE NOT jsonb_array_is_sorted(data, 'posts', 'created_at', 'DESC');
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern(immutable, parallel_safe)]
+#[must_use]
+pub fn jsonb_array_is_sorted(
+    target: Option<JsonB>,
+    array_path: &str,
+    sort_key: &str,
+    sort_order: default!(&str, "'ASC'"),
+    collation: default!(Option<&str>, "NULL"),
+    on_missing: default!(&str, "'create'"),
+) -> Option<bool> {
+    let target = target?;
+    let mut order_spec = parse_order_spec(sort_key, sort_order).unwrap_or_else(|e| error!("{}", e));
+    order_spec.resolve_collations(collation);
+
+    let policy = OnMissing::parse(on_missing).unwrap_or_else(|e| error!("{}", e));
+    let sorted = find_array(&target.0, array_path, policy)
+        .unwrap_or_else(|e| error!("{}", e))
+        .is_none_or(|array_items| order_spec.is_sorted(array_items));
+    Some(sorted)
+}
+
+/// Find the array at `array_path` under the `on_missing` policy
+///
+/// Returns `None` when there is nothing to do (see `find_array_mut`); policy
+/// violations raise.
+fn array_at<'a>(
+    target: &'a mut Value,
+    array_path: &str,
+    on_missing: &str,
+) -> Option<&'a mut Vec<Value>> {
+    let policy = OnMissing::parse(on_missing).unwrap_or_else(|e| error!("{}", e));
+    find_array_mut(target, array_path, policy).unwrap_or_else(|e| error!("{}", e))
+}
+
+/// Get or create the array at `array_path` under the `on_missing` policy
+///
+/// For functions that add elements; returns `None` when a missing array is ignored.
+fn array_at_or_create<'a>(
+    target: &'a mut Value,
+    array_path: &str,
+    on_missing: &str,
+) -> Option<&'a mut Vec<Value>> {
+    let policy = OnMissing::parse(on_missing).unwrap_or_else(|e| error!("{}", e));
+    resolve_array(target, array_path, policy).unwrap_or_else(|e| error!("{}", e))
+}
+
+/// Parse the `sort_key`/`sort_order`/`collation` arguments of the sorted array functions
+///
+/// `sort_key` holds an ORDER BY-style specification; `sort_order` is the
+/// direction and `collation` the collation of terms that don't specify one.
+fn parse_sort_spec(
+    sort_key: Option<&str>,
+    sort_order: Option<&str>,
+    collation: Option<&str>,
+) -> Option<OrderSpec> {
+    let spec = sort_key?;
+    let mut order_spec =
+        parse_order_spec(spec, sort_order.unwrap_or("ASC")).unwrap_or_else(|e| error!("{}", e));
+    order_spec.resolve_collations(collation);
+    Some(order_spec)
+}
+
+/// Enforce `max_len` after an insertion, evicting according to the sort order
+///
+/// Bounded arrays keep the most recent entries: an array sorted DESC (by its
+/// first sort term) holds the newest first, so the tail is evicted; ASC and
+/// unsorted (append-only) arrays hold the newest last, so the head is evicted.
+fn evict_to_max_len(array_items: &mut Vec<Value>, max_len: i32, order_spec: Option<&OrderSpec>) {
+    let Ok(keep) = usize::try_from(max_len) else {
+        error!("max_len must not be negative, got: {}", max_len);
+    };
+
+    let descending = order_spec.is_some_and(OrderSpec::is_descending);
+    truncate_array(array_items, keep, descending);
+}
+
+/// Keep at most `keep` elements, dropping the surplus from the end or the start
+#[inline]
+pub fn truncate_array(array_items: &mut Vec<Value>, keep: usize, from_end: bool) {
+    if array_items.len() <= keep {
+        return;
+    }
+
+    if from_end {
+        array_items.truncate(keep);
+    } else {
+        array_items.drain(..array_items.len() - keep);
+    }
+}
+
+/// Merge a batch of elements into a sorted array in a single pass
+///
+/// Produces the same placement as inserting each batch element in turn with
+/// `find_insertion_point`: a new element goes before the first existing element
+/// that sorts strictly after it, so ties keep existing elements first.
+#[must_use]
+pub fn merge_sorted_batch(
+    existing: Vec<Value>,
+    mut batch: Vec<Value>,
+    order_spec: &OrderSpec,
+) -> Vec<Value> {
+    batch.sort_by(|a, b| order_spec.compare(a, b));
+
+    let mut merged = Vec::with_capacity(existing.len() + batch.len());
+    let mut existing_iter = existing.into_iter().peekable();
+
+    for new_elem in batch {
+        let new_key = order_spec.sort_key(&new_elem);
+        while let Some(elem) =
+            existing_iter.next_if(|elem| order_spec.compare_key(&new_key, elem).is_ge())
+        {
+            merged.push(elem);
+        }
+        merged.push(new_elem);
+    }
+
+    merged.extend(existing_iter);
+    merged
+}
+
+/// Reconcile an embedded array against a desired set of elements
+///
+/// Turns the array at `array_path` into exactly the elements of `desired`,
+/// identified by `match_key`, without rebuilding it: elements missing from
+/// `desired` are deleted, changed elements are replaced in place, and new
+/// elements are appended. Untouched elements keep their position.
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing (or to contain) the array
+/// * `array_path` - Path to the array (e.g., `"members"`)
+/// * `match_key` - Key identifying elements (e.g., `"id"`)
+/// * `desired` - JSONB array with the complete desired set of elements; `NULL`
+///   (e.g., `jsonb_agg` over no rows) means no elements
+/// * `on_missing` - `'create'` (default) creates a missing array, `'ignore'`
+///   returns the document unchanged, `'error'` raises
+/// * `aggregates` - Aggregate fields to maintain (see `jsonb_array_insert_where`)
+///
+/// # Returns
+///
+/// Updated JSONB whose array matches `desired` by key; `NULL` for a `NULL` target
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT jsonb_array_sync(
+///     '{"members": [{"id": 1, "role": "admin"}, {"id": 2, "role": "dev"}, {"id": 3}]}'::jsonb,
+///     'members',
+///     'id',
+///     '[{"id": 3}, {"id": 1, "role": "owner"}, {"id": 4}]'::jsonb
+/// );
+/// -- Result: {"members": [{"id": 1, "role": "owner"}, {"id": 3}, {"id": 4}]}
+///
+/// -- Periodic reconciliation job
+/// UPDATE tv_team t
+/// SET data = jsonb_array_sync(
+///     data,
+///     'members',
+///     'id',
+///     (SELECT jsonb_agg(m.data) FROM v_member m WHERE m.fk_team = t.pk)
+/// );
+/// ```
+///
+/// # Notes
+/// - Every element of `desired` must contain `match_key`, and keys must be unique
+/// - Existing elements without `match_key`, or with a duplicate key, are deleted
+/// - Use `jsonb_array_sync_summary` to also get the keys that changed
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern(immutable, parallel_safe)]
+#[must_use]
+pub fn jsonb_array_sync(
+    target: Option<JsonB>,
+    array_path: &str,
+    match_key: &str,
+    desired: Option<JsonB>,
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
+) -> Option<JsonB> {
+    let desired = desired.map_or_else(|| Value::Array(Vec::new()), |desired| desired.0);
+    let (result, _summary) =
+        with_aggregates(target?.0, array_path, &aggregates.0, |target_value| {
+            sync_document(target_value, array_path, match_key, desired, on_missing)
+        });
+    Some(JsonB(result))
+}
+
+/// Reconcile an embedded array and report what changed
+///
+/// Same as `jsonb_array_sync`, but also returns a summary listing the keys of
+/// inserted, updated and deleted elements, for logging or drift detection.
+///
+/// # Returns
+///
+/// A single row `(result jsonb, summary jsonb)` where `summary` is
+/// `{"inserted": [...], "updated": [...], "deleted": [...]}`
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT summary FROM jsonb_array_sync_summary(
+///     '{"members": [{"id": 1, "role": "admin"}, {"id": 2}]}'::jsonb,
+///     'members',
+///     'id',
+///     '[{"id": 1, "role": "owner"}, {"id": 3}]'::jsonb
+/// );
+/// -- Returns: {"deleted": [2], "updated": [1], "inserted": [3]}
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern(immutable, parallel_safe)]
+#[must_use]
+pub fn jsonb_array_sync_summary(
+    target: Option<JsonB>,
+    array_path: &str,
+    match_key: &str,
+    desired: Option<JsonB>,
+    on_missing: default!(&str, "'create'"),
+    aggregates: default!(JsonB, "'{}'"),
+) -> TableIterator<'static, (name!(result, Option<JsonB>), name!(summary, Option<JsonB>))> {
+    let Some(target) = target else {
+        return TableIterator::once((None, None));
+    };
+    let desired = desired.map_or_else(|| Value::Array(Vec::new()), |desired| desired.0);
+    let (result, summary) = with_aggregates(target.0, array_path, &aggregates.0, |target_value| {
+        sync_document(target_value, array_path, match_key, desired, on_missing)
+    });
+    TableIterator::once((Some(JsonB(result)), Some(JsonB(summary.to_json()))))
+}
+
+/// Outcome of an array operation, reported by the `_stats` variants
+///
+/// `matched` counts the elements the operation applied to (updated, deleted
+/// or inserted); `modified` tells whether the document actually changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ArrayOpStats {
+    pub matched: usize,
+    pub modified: bool,
+}
+
+impl ArrayOpStats {
+    /// Count one matched element, which `changed` or was already up to date
+    const fn record_match(&mut self, changed: bool) {
+        self.matched += 1;
+        self.modified |= changed;
+    }
+
+    /// Count `count` removed elements
+    const fn record_removed(&mut self, count: usize) {
+        self.matched += count;
+        self.modified |= count > 0;
+    }
+
+    /// Build the `(result, matched, modified)` row returned to SQL
+    fn into_row(self, result: Value) -> (JsonB, i32, bool) {
+        let matched = i32::try_from(self.matched).unwrap_or(i32::MAX);
+        (JsonB(result), matched, self.modified)
+    }
+}
+
+/// Keys of the elements changed by an array synchronization
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SyncSummary {
+    pub inserted: Vec<Value>,
+    pub updated: Vec<Value>,
+    pub deleted: Vec<Value>,
+}
+
+impl SyncSummary {
+    /// Render the summary as a JSONB-ready object
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "inserted": self.inserted,
+            "updated": self.updated,
+            "deleted": self.deleted,
+        })
+    }
+}
+
+/// Shared implementation of `jsonb_array_sync` and `jsonb_array_sync_summary`
+fn sync_document(
+    mut target_value: Value,
+    array_path: &str,
+    match_key: &str,
+    desired: Value,
+    on_missing: &str,
+) -> (Value, SyncSummary) {
+    // Security: Validate depth limits to prevent DoS attacks
+    crate::validate_depth(&desired, crate::MAX_JSONB_DEPTH).unwrap_or_else(|e| error!("{}", e));
+
+    let Value::Array(desired_items) = desired else {
+        error!(
+            "desired must be a JSONB array, got: {}",
+            value_type_name(&desired)
+        );
+    };
+
+    // Get or create array at path
+    let Some(array_items) = array_at_or_create(&mut target_value, array_path, on_missing) else {
+        return (target_value, SyncSummary::default());
+    }; // Missing array ignored, return unchanged
+
+    let summary =
+        sync_array(array_items, match_key, desired_items).unwrap_or_else(|e| error!("{}", e));
+
+    (target_value, summary)
+}
+
+/// Reconcile `items` in place so that it holds exactly `desired`, keyed by `match_key`
+///
+/// Existing elements are kept in their current order (replaced when their
+/// content differs), elements absent from `desired` are removed, and new
+/// elements are appended in `desired` order.
+///
+/// # Errors
+///
+/// Returns an error if a desired element has no `match_key` or if two desired
+/// elements share the same key.
+pub fn sync_array(
+    items: &mut Vec<Value>,
+    match_key: &str,
+    desired: Vec<Value>,
+) -> Result<SyncSummary, String> {
+    // Index desired elements by key; `None` marks an element already placed
+    let mut desired_slots: Vec<Option<Value>> = Vec::with_capacity(desired.len());
+    let mut desired_index: HashMap<String, usize> = HashMap::with_capacity(desired.len());
+
+    for (idx, elem) in desired.into_iter().enumerate() {
+        let Some(key_val) = elem.get(match_key) else {
+            return Err(format!(
+                "desired element at index {idx} has no '{match_key}' key"
+            ));
+        };
+        if desired_index.insert(value_key(key_val), idx).is_some() {
+            return Err(format!(
+                "duplicate '{match_key}' in desired elements: {key_val}"
+            ));
+        }
+        desired_slots.push(Some(elem));
+    }
+
+    let mut summary = SyncSummary::default();
+    let mut synced = Vec::with_capacity(desired_slots.len());
+
+    for elem in std::mem::take(items) {
+        let slot = elem
+            .get(match_key)
+            .and_then(|key_val| desired_index.get(&value_key(key_val)))
+            .and_then(|&idx| desired_slots[idx].take());
+
+        match slot {
+            Some(wanted) => {
+                if wanted != elem {
+                    summary.updated.push(wanted[match_key].clone());
+                }
+                synced.push(wanted);
+            }
+            None => {
+                summary
+                    .deleted
+                    .push(elem.get(match_key).cloned().unwrap_or(Value::Null));
+            }
+        }
+    }
+
+    // Whatever was not matched is new
+    for wanted in desired_slots.into_iter().flatten() {
+        summary.inserted.push(wanted[match_key].clone());
+        synced.push(wanted);
+    }
+
+    *items = synced;
+    Ok(summary)
+}
+
+/// Sort a JSONB array in place of `jsonb_array_elements` + `jsonb_agg(... ORDER BY ...)`
+///
+/// Uses the same ordering as the sorted inserts (`jsonb_array_insert_where`),
+/// so an array sorted here stays sorted under subsequent inserts with the same
+/// specification.
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the array
+/// * `array_path` - Path to the array (e.g., `"posts"`)
+/// * `order_spec` - ORDER BY-style specification (e.g., `"created_at::timestamptz DESC, id"`)
+/// * `collation` - Optional collation for string sort values (default NULL: bytewise)
+/// * `on_missing` - `'create'` (default) or `'ignore'` return the document
+///   unchanged when the array is missing, `'error'` raises
+///
+/// # Returns
+///
+/// Updated JSONB with the array sorted (or unchanged if it doesn't exist);
+/// `NULL` for a `NULL` target
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT jsonb_array_sort(
+///     '{"posts": [{"id": 1, "score": 5}, {"id": 2, "score": 9}, {"id": 3}]}'::jsonb,
+///     'posts',
+///     'score DESC NULLS LAST, id'
+/// );
+/// -- Result: {"posts": [{"id": 2, "score": 9}, {"id": 1, "score": 5}, {"id": 3}]}
+///
+/// -- Fix projections after a bulk backfill
+/// UPDATE tv_feed
+/// SET data = jsonb_array_sort(data, 'posts', 'created_at DESC')
+/// WHERE NOT jsonb_array_is_sorted(data, 'posts', 'created_at', 'DESC');
+/// ```
+///
+/// # Notes
+/// - The sort is stable: elements with equal sort values keep their order
+#[pg_extern(immutable, parallel_safe)]
+#[must_use]
+pub fn jsonb_array_sort(
+    target: Option<JsonB>,
+    array_path: &str,
+    order_spec: &str,
+    collation: default!(Option<&str>, "NULL"),
+    on_missing: default!(&str, "'create'"),
+) -> Option<JsonB> {
+    let mut target_value: Value = target?.0;
+
+    let mut spec = parse_order_spec(order_spec, "ASC").unwrap_or_else(|e| error!("{}", e));
+    spec.resolve_collations(collation);
+
+    // Navigate to array location
+    let Some(array_items) = array_at(&mut target_value, array_path, on_missing) else {
+        return Some(JsonB(target_value));
+    }; // Missing array ignored, return unchanged
+
+    spec.sort(array_items);
+
+    Some(JsonB(target_value))
+}
+
+/// Remove elements with duplicate keys from a JSONB array
+///
+/// Keeps one element per `match_key` value: the first occurrence or the last
+/// one. Kept elements stay at their position. Elements without `match_key` are
+/// never considered duplicates.
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the array
+/// * `array_path` - Path to the array (e.g., `"members"`)
+/// * `match_key` - Key identifying elements (e.g., `"id"`)
+/// * `keep` - `'first'` (default) or `'last'` occurrence to keep
+/// * `on_missing` - `'create'` (default) or `'ignore'` return the document
+///   unchanged when the array is missing, `'error'` raises
+///
+/// # Returns
+///
+/// Updated JSONB without duplicates (or unchanged if the array doesn't exist)
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT jsonb_array_dedupe(
+///     '{"members": [{"id": 1, "v": 1}, {"id": 2}, {"id": 1, "v": 2}]}'::jsonb,
+///     'members',
+///     'id',
+///     'last'
+/// );
+/// -- Result: {"members": [{"id": 2}, {"id": 1, "v": 2}]}
+/// ```
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_array_dedupe(
+    target: JsonB,
+    array_path: &str,
+    match_key: &str,
+    keep: default!(&str, "'first'"),
+    on_missing: default!(&str, "'create'"),
+) -> JsonB {
+    let mut target_value: Value = target.0;
+
+    let keep_last = match keep.to_ascii_lowercase().as_str() {
+        "first" => false,
+        "last" => true,
+        _ => error!("keep must be 'first' or 'last', got: '{}'", keep),
+    };
+
+    // Navigate to array location
+    let Some(array_items) = array_at(&mut target_value, array_path, on_missing) else {
+        return JsonB(target_value);
+    }; // Missing array ignored, return unchanged
+
+    dedupe_array(array_items, match_key, keep_last);
+
+    JsonB(target_value)
+}
+
+/// Keep the first (or last) element for each `match_key` value
+pub fn dedupe_array(array_items: &mut Vec<Value>, match_key: &str, keep_last: bool) {
+    let mut seen: HashSet<String> = HashSet::with_capacity(array_items.len());
+
+    // Keeping the last occurrence is keeping the first one of the reversed array
+    if keep_last {
+        array_items.reverse();
+    }
+    array_items.retain(|elem| {
+        elem.get(match_key)
+            .is_none_or(|v| seen.insert(value_key(v)))
+    });
+    if keep_last {
+        array_items.reverse();
+    }
+}
+
+/// Move an element of a JSONB array to a new position
+///
+/// Surgical counterpart of drag-and-drop reordering: the element is moved
+/// without touching its contents or rebuilding the array.
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the array
+/// * `array_path` - Path to the array (e.g., `"cards"`)
+/// * `match_key` - Key identifying the element (e.g., `"id"`)
+/// * `match_value` - Value of `match_key` for the element to move
+/// * `to_position` - 0-based position of the element after the move; negative
+///   positions count from the end (-1 is last). Out-of-range positions are clamped.
+/// * `on_missing` - `'create'` (default) or `'ignore'` return the document
+///   unchanged when the array is missing, `'error'` raises
+///
+/// # Returns
+///
+/// Updated JSONB with the element moved (or unchanged if no match)
+///
+/// # Examples
+///
+/// ```sql
+/// -- Move card 3 to the top of the column
+/// SELECT jsonb_array_move_where(
+///     '{"cards": [{"id": 1}, {"id": 2}, {"id": 3}]}'::jsonb,
+///     'cards',
+///     'id',
+///     '3'::jsonb,
+///     0
+/// );
+/// -- Result: {"cards": [{"id": 3}, {"id": 1}, {"id": 2}]}
+///
+/// -- Move card 1 to the bottom
+/// SELECT jsonb_array_move_where(data, 'cards', 'id', '1'::jsonb, -1)
+/// FROM tv_board;
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_array_move_where(
+    target: JsonB,
+    array_path: &str,
+    match_key: &str,
+    match_value: JsonB,
+    to_position: i32,
+    on_missing: default!(&str, "'create'"),
+) -> JsonB {
+    let mut target_value: Value = target.0;
+
+    // Navigate to array location
+    let Some(array_items) = array_at(&mut target_value, array_path, on_missing) else {
+        return JsonB(target_value);
+    }; // Missing array ignored, return unchanged
+
+    if let Some(from) = find_element_by_match(array_items, match_key, &match_value.0) {
+        let to = resolve_position(to_position, array_items.len());
+        move_element(array_items, from, to);
+    }
+
+    JsonB(target_value)
+}
+
+/// Move an element of a JSONB array next to another element
+///
+/// Places the element immediately before `before_value` or immediately after
+/// `after_value` (both matched on `match_key`), which is what a drop between
+/// two cards reports. Exactly one of the two must be given.
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the array
+/// * `array_path` - Path to the array (e.g., `"cards"`)
+/// * `match_key` - Key identifying elements (e.g., `"id"`)
+/// * `match_value` - Value of `match_key` for the element to move
+/// * `before_value` - Move the element just before the element with this value
+/// * `after_value` - Move the element just after the element with this value
+/// * `on_missing` - `'create'` (default) or `'ignore'` return the document
+///   unchanged when the array is missing, `'error'` raises
+///
+/// # Returns
+///
+/// Updated JSONB with the element moved (unchanged if either element is missing)
+///
+/// # Examples
+///
+/// ```sql
+/// -- Drop card 1 between cards 2 and 3
+/// SELECT jsonb_array_move_relative(
+///     '{"cards": [{"id": 1}, {"id": 2}, {"id": 3}]}'::jsonb,
+///     'cards',
+///     'id',
+///     '1'::jsonb,
+///     after_value => '2'::jsonb
+/// );
+/// -- Result: {"cards": [{"id": 2}, {"id": 1}, {"id": 3}]}
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern(immutable, parallel_safe)]
+#[must_use]
+pub fn jsonb_array_move_relative(
+    target: JsonB,
+    array_path: &str,
+    match_key: &str,
+    match_value: JsonB,
+    before_value: default!(Option<JsonB>, "NULL"),
+    after_value: default!(Option<JsonB>, "NULL"),
+    on_missing: default!(&str, "'create'"),
+) -> JsonB {
+    let mut target_value: Value = target.0;
+
+    let (anchor_value, place_after) = match (before_value, after_value) {
+        (Some(before), None) => (before.0, false),
+        (None, Some(after)) => (after.0, true),
+        _ => error!("exactly one of before_value or after_value must be provided"),
+    };
+
+    // Navigate to array location
+    let Some(array_items) = array_at(&mut target_value, array_path, on_missing) else {
+        return JsonB(target_value);
+    }; // Missing array ignored, return unchanged
+
+    let Some(from) = find_element_by_match(array_items, match_key, &match_value.0) else {
+        return JsonB(target_value);
+    };
+    let Some(anchor) = find_element_by_match(array_items, match_key, &anchor_value) else {
+        return JsonB(target_value);
+    };
+
+    // Final index of the element; removing it first shifts a later anchor left
+    let to = match (from < anchor, place_after) {
+        (true, false) => anchor - 1,
+        (true, true) | (false, false) => anchor,
+        (false, true) => anchor + 1,
+    };
+    if from != anchor {
+        move_element(array_items, from, to);
+    }
+
+    JsonB(target_value)
+}
+
+/// Reorder a JSONB array to follow a list of ids
+///
+/// Applies a complete ordering (e.g., a playlist saved by the UI) without
+/// touching element contents. Elements whose `match_key` appears in
+/// `ordered_ids` come first, in that order; the others follow in their
+/// original order.
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the array
+/// * `array_path` - Path to the array (e.g., `"tracks"`)
+/// * `match_key` - Key identifying elements (e.g., `"id"`)
+/// * `ordered_ids` - JSONB array of `match_key` values in the desired order
+/// * `on_missing` - `'create'` (default) or `'ignore'` return the document
+///   unchanged when the array is missing, `'error'` raises
+///
+/// # Returns
+///
+/// Updated JSONB with the array reordered (or unchanged if it doesn't exist)
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT jsonb_array_reorder(
+///     '{"tracks": [{"id": 1}, {"id": 2}, {"id": 3}, {"id": 4}]}'::jsonb,
+///     'tracks',
+///     'id',
+///     '[3, 1]'::jsonb
+/// );
+/// -- Result: {"tracks": [{"id": 3}, {"id": 1}, {"id": 2}, {"id": 4}]}
+/// ```
+///
+/// # Notes
+/// - Ids in `ordered_ids` without a matching element are ignored
+/// - If an id is listed twice, its first position wins
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_array_reorder(
+    target: JsonB,
+    array_path: &str,
+    match_key: &str,
+    ordered_ids: JsonB,
+    on_missing: default!(&str, "'create'"),
+) -> JsonB {
+    let mut target_value: Value = target.0;
+
+    let Some(ids) = ordered_ids.0.as_array() else {
+        error!(
+            "ordered_ids must be a JSONB array, got: {}",
+            value_type_name(&ordered_ids.0)
+        );
+    };
+
+    // Navigate to array location
+    let Some(array_items) = array_at(&mut target_value, array_path, on_missing) else {
+        return JsonB(target_value);
+    }; // Missing array ignored, return unchanged
+
+    reorder_array(array_items, match_key, ids);
+
+    JsonB(target_value)
+}
+
+/// Resolve a 0-based position (negative counts from the end) within `len` elements
+///
+/// Out-of-range positions are clamped to the first or last element.
+#[inline]
+fn resolve_position(position: i32, len: usize) -> usize {
+    usize::try_from(position).map_or_else(
+        |_| len.saturating_sub(position.unsigned_abs() as usize),
+        |pos| pos.min(len.saturating_sub(1)),
+    )
+}
+
+/// Move the element at `from` to index `to`, shifting the elements in between
+#[inline]
+pub fn move_element(array_items: &mut [Value], from: usize, to: usize) {
+    if from < to {
+        array_items[from..=to].rotate_left(1);
+    } else if to < from {
+        array_items[to..=from].rotate_right(1);
+    }
+}
+
+/// Stable reorder: listed ids first in list order, then the rest in original order
+pub fn reorder_array(array_items: &mut [Value], match_key: &str, ordered_ids: &[Value]) {
+    let mut rank: HashMap<String, usize> = HashMap::with_capacity(ordered_ids.len());
+    for (i, id) in ordered_ids.iter().enumerate() {
+        rank.entry(value_key(id)).or_insert(i);
+    }
+
+    array_items.sort_by_cached_key(|elem| {
+        elem.get(match_key)
+            .and_then(|v| rank.get(&value_key(v)).copied())
+            .unwrap_or(usize::MAX)
+    });
+}
+
+/// Find the insertion point to maintain sort order
+///
+/// The new element goes before the first existing element that sorts strictly
+/// after it, so ties keep existing elements first.
+#[inline]
+#[must_use]
+pub fn find_insertion_point(array: &[Value], new_elem: &Value, order_spec: &OrderSpec) -> usize {
+    let new_key = order_spec.sort_key(new_elem);
+
+    array
+        .iter()
+        .position(|elem| order_spec.compare_key(&new_key, elem).is_lt())
+        .unwrap_or(array.len())
+}
+
+/// Find the insertion point in an array known to be sorted, using binary search
+///
+/// Returns the same position as `find_insertion_point` when the array is
+/// ordered by `order_spec` (see `OrderSpec::is_sorted`). The order is not
+/// checked, which would cost O(n): for an unsorted array the position is
+/// unspecified (but always within `0..=array.len()`).
+///
+/// O(log n) comparisons instead of O(n).
+#[inline]
+#[must_use]
+pub fn find_insertion_point_sorted(
+    array: &[Value],
+    new_elem: &Value,
+    order_spec: &OrderSpec,
+) -> usize {
+    let new_key = order_spec.sort_key(new_elem);
+    array.partition_point(|elem| order_spec.compare_key(&new_key, elem).is_ge())
+}
+
+/// Compare two JSON values for ordering
+#[inline]
+#[must_use]
+pub fn compare_values(a: &Value, b: &Value) -> std::cmp::Ordering {
     use std::cmp::Ordering;
 
     match (a, b) {
@@ -531,15 +2426,3 @@ fn find_element_by_match(array: &[Value], match_key: &str, match_value: &Value)
         .iter()
         .position(|elem| elem.get(match_key) == Some(match_value))
 }
-
-// Helper function
-const fn value_type_name(value: &Value) -> &'static str {
-    match value {
-        Value::Null => "null",
-        Value::Bool(_) => "boolean",
-        Value::Number(_) => "number",
-        Value::String(_) => "string",
-        Value::Array(_) => "array",
-        Value::Object(_) => "object",
-    }
-}
@@ -0,0 +1,728 @@
+// jsonb_ivm - Cascade Planner Module
+//
+// Propagates a source row change through every registered level of the
+// dependency graph (company → user → post → feed), in topological order.
+
+use pgrx::datum::DatumWithOid;
+use pgrx::prelude::*;
+use pgrx::JsonB;
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+
+use crate::queue::enqueue;
+use crate::registry::{load_dependencies, DependencyKind, RegisteredDependency};
+
+/// Find a cycle in the graph of `(source, target)` table edges
+///
+/// Returns the tables along the first cycle found, starting and ending with
+/// the same table, or `None` if the graph is acyclic.
+pub fn find_cycle<'a>(edges: &[(&'a str, &'a str)]) -> Option<Vec<&'a str>> {
+    let mut adjacency: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for &(source, target) in edges {
+        adjacency.entry(source).or_default().push(target);
+    }
+
+    let mut finished = Vec::new();
+    let mut path = Vec::new();
+    adjacency
+        .keys()
+        .find_map(|&node| visit(node, &adjacency, &mut finished, &mut path))
+}
+
+/// Depth-first search from `node`; `path` holds the tables being visited
+fn visit<'a>(
+    node: &'a str,
+    adjacency: &BTreeMap<&'a str, Vec<&'a str>>,
+    finished: &mut Vec<&'a str>,
+    path: &mut Vec<&'a str>,
+) -> Option<Vec<&'a str>> {
+    if let Some(pos) = path.iter().position(|&on_path| on_path == node) {
+        let mut cycle = path[pos..].to_vec();
+        cycle.push(node);
+        return Some(cycle);
+    }
+    if finished.contains(&node) {
+        return None;
+    }
+
+    path.push(node);
+    let cycle = adjacency.get(node).and_then(|targets| {
+        targets
+            .iter()
+            .find_map(|&target| visit(target, adjacency, finished, path))
+    });
+    path.pop();
+    finished.push(node);
+    cycle
+}
+
+/// Order in which to apply the edges reachable from `start`
+///
+/// Returns indexes into `edges`. The edges leaving a table come after every
+/// reachable edge entering it, so a projection embedded through several
+/// paths is only propagated once all its embedded documents are up to date.
+/// Edges leaving the same table keep their relative order.
+///
+/// # Errors
+///
+/// Returns the tables of a cycle reachable from `start`.
+pub fn cascade_order<'a>(
+    edges: &[(&'a str, &'a str)],
+    start: &'a str,
+) -> Result<Vec<usize>, Vec<&'a str>> {
+    let mut reachable = vec![start];
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        for &(source, target) in edges {
+            if source == node && !reachable.contains(&target) {
+                reachable.push(target);
+                queue.push_back(target);
+            }
+        }
+    }
+
+    let reachable_edges: Vec<usize> = (0..edges.len())
+        .filter(|&idx| reachable.contains(&edges[idx].0))
+        .collect();
+    let mut pending_inputs: BTreeMap<&str, usize> = BTreeMap::new();
+    for &idx in &reachable_edges {
+        *pending_inputs.entry(edges[idx].1).or_default() += 1;
+    }
+
+    let mut order = Vec::with_capacity(reachable_edges.len());
+    let mut ready = VecDeque::new();
+    if !pending_inputs.contains_key(start) {
+        ready.push_back(start);
+    }
+    while let Some(node) = ready.pop_front() {
+        for &idx in &reachable_edges {
+            if edges[idx].0 != node {
+                continue;
+            }
+            order.push(idx);
+            let inputs = pending_inputs.entry(edges[idx].1).or_default();
+            *inputs -= 1;
+            if *inputs == 0 {
+                ready.push_back(edges[idx].1);
+            }
+        }
+    }
+
+    if order.len() == reachable_edges.len() {
+        Ok(order)
+    } else {
+        let reachable_pairs: Vec<_> = reachable_edges.iter().map(|&idx| edges[idx]).collect();
+        Err(find_cycle(&reachable_pairs).unwrap_or_default())
+    }
+}
+
+/// Group a cascade order into steps
+///
+/// `ordered` lists `(source, target, kind)` in cascade order. Object
+/// dependencies leaving the same table for the same projection (e.g.,
+/// `post.author` and `post.reviewer` from `tv_user`) form one step, applied
+/// by a single combined update; every other dependency is a step of its own.
+/// Returns indexes into `ordered`.
+pub fn combine_steps(ordered: &[(&str, &str, DependencyKind)]) -> Vec<Vec<usize>> {
+    let mut steps: Vec<Vec<usize>> = Vec::new();
+    for (idx, &(source, target, kind)) in ordered.iter().enumerate() {
+        // Dependencies of one source table are contiguous in cascade order
+        let combined = steps
+            .iter_mut()
+            .rev()
+            .take_while(|step| ordered[step[0]].0 == source)
+            .find(|step| {
+                let (_, step_target, step_kind) = ordered[step[0]];
+                kind == DependencyKind::Object
+                    && step_kind == DependencyKind::Object
+                    && step_target == target
+            });
+        match combined {
+            Some(step) => step.push(idx),
+            None => steps.push(vec![idx]),
+        }
+    }
+    steps
+}
+
+/// Steps to apply after a change to `source_table`, in cascade order
+fn plan(
+    dependencies: &[RegisteredDependency],
+    source_table: &str,
+) -> Vec<Vec<RegisteredDependency>> {
+    let edges: Vec<(&str, &str)> = dependencies
+        .iter()
+        .map(|dep| (dep.source_table.as_str(), dep.target_table.as_str()))
+        .collect();
+
+    let order = cascade_order(&edges, source_table)
+        .unwrap_or_else(|cycle| error!("Dependency graph has a cycle: {}", cycle.join(" -> ")));
+    let ordered: Vec<_> = order
+        .iter()
+        .map(|&idx| (edges[idx].0, edges[idx].1, dependencies[idx].kind))
+        .collect();
+
+    combine_steps(&ordered)
+        .into_iter()
+        .map(|step| {
+            step.into_iter()
+                .map(|pos| dependencies[order[pos]].clone())
+                .collect()
+        })
+        .collect()
+}
+
+/// Show the cascade that a change to `source_table` triggers
+///
+/// Lists the registered dependencies reachable from `source_table`, in the
+/// order `jsonb_ivm_cascade` applies them. Object dependencies sharing a step
+/// are applied by one combined update.
+///
+/// # Arguments
+///
+/// * `source_table` - Table whose rows change (e.g., `"v_company"`)
+///
+/// # Returns
+///
+/// One row per dependency: `step`, `dependency_id`, `source_table`,
+/// `target_table` and `kind`
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT step, source_table, target_table FROM jsonb_ivm_cascade_plan('v_company');
+/// -- 1 | v_company | tv_user
+/// -- 2 | tv_user   | tv_post
+/// -- 3 | tv_post   | tv_feed
+/// ```
+#[pg_extern]
+pub fn jsonb_ivm_cascade_plan(
+    source_table: &str,
+) -> TableIterator<
+    'static,
+    (
+        name!(step, i32),
+        name!(dependency_id, i64),
+        name!(source_table, String),
+        name!(target_table, String),
+        name!(kind, String),
+    ),
+> {
+    let source_table = table_name(source_table);
+    let steps = plan(&load_dependencies(None), &source_table);
+
+    TableIterator::new(steps.into_iter().enumerate().flat_map(|(idx, step)| {
+        step.into_iter().map(move |dep| {
+            (
+                step_number(idx),
+                dep.id,
+                dep.source_table,
+                dep.target_table,
+                dep.kind.as_str().to_string(),
+            )
+        })
+    }))
+}
+
+/// Propagate a change to a source row through every registered level
+///
+/// Applies the dependencies reachable from `source_table` in topological
+/// order. The first level patches the projections embedding the changed row;
+/// each following level patches the projections embedding the rows updated
+/// by the previous ones, so a projection reached through several paths is
+/// patched from its final upstream documents.
+///
+/// # Arguments
+///
+/// * `source_table` - Table of the changed row (e.g., `"v_company"`)
+/// * `old_row` - Row before the change, as `to_jsonb(OLD)`; NULL for inserts
+/// * `new_row` - Row after the change, as `to_jsonb(NEW)`; NULL for deletes
+///
+/// # Returns
+///
+/// One row per applied dependency: `step` (as in `jsonb_ivm_cascade_plan`),
+/// `dependency_id`, `target_table` and the number of `rows_updated` by the step
+///
+/// # Patch functions
+///
+/// - `object` dependencies: `jsonb_smart_patch_nested`; a deleted source row
+///   leaves `null` in the embedding documents
+/// - `array` dependencies: `jsonb_array_delete_where_batch`,
+///   `jsonb_array_update_where_batch` and `jsonb_array_insert_batch`, combined
+///   into one update per projection row (a changed foreign key moves the element)
+///
+/// # Examples
+///
+/// ```sql
+/// CREATE FUNCTION cascade_company() RETURNS trigger LANGUAGE plpgsql AS $$
+/// BEGIN
+///     PERFORM jsonb_ivm_cascade(TG_RELID::regclass::text, to_jsonb(OLD), to_jsonb(NEW));
+///     RETURN NULL;
+/// END $$;
+///
+/// CREATE TRIGGER cascade_company AFTER INSERT OR UPDATE OR DELETE ON v_company
+/// FOR EACH ROW EXECUTE FUNCTION cascade_company();
+/// ```
+///
+/// # Notes
+/// - Attach it to base tables only: the projections it updates must not run
+///   cascade triggers of their own (including `jsonb_ivm_install_triggers` ones)
+/// - For bulk statements, `jsonb_ivm_cascade_trigger` applies the same
+///   cascade once per statement
+/// - Cycles are rejected by `jsonb_ivm_register_dependency`
+#[pg_extern]
+pub fn jsonb_ivm_cascade(
+    source_table: &str,
+    old_row: Option<JsonB>,
+    new_row: Option<JsonB>,
+) -> TableIterator<
+    'static,
+    (
+        name!(step, i32),
+        name!(dependency_id, i64),
+        name!(target_table, String),
+        name!(rows_updated, i64),
+    ),
+> {
+    if old_row.is_none() && new_row.is_none() {
+        error!("jsonb_ivm_cascade needs the old row, the new row or both");
+    }
+
+    let old_rows: Vec<Value> = old_row.map(|row| row.0).into_iter().collect();
+    let new_rows: Vec<Value> = new_row.map(|row| row.0).into_iter().collect();
+    TableIterator::new(propagate(&table_name(source_table), &old_rows, &new_rows))
+}
+
+/// Statement-level trigger propagating a bulk change through every level
+///
+/// Reads the statement's transition tables and runs the same cascade as
+/// `jsonb_ivm_cascade`, once for all changed rows: each projection row is
+/// updated once per step, however many of its embedded rows changed.
+///
+/// # Examples
+///
+/// ```sql
+/// -- Transition tables need one trigger per event
+/// CREATE TRIGGER v_company_cascade_insert AFTER INSERT ON v_company
+/// REFERENCING NEW TABLE AS new_rows
+/// FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger();
+///
+/// CREATE TRIGGER v_company_cascade_update AFTER UPDATE ON v_company
+/// REFERENCING OLD TABLE AS old_rows NEW TABLE AS new_rows
+/// FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger();
+///
+/// CREATE TRIGGER v_company_cascade_delete AFTER DELETE ON v_company
+/// REFERENCING OLD TABLE AS old_rows
+/// FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger();
+/// ```
+///
+/// # Deferred mode
+///
+/// With the `'deferred'` argument, the changes are queued in
+/// `jsonb_ivm.change_queue` instead, for `jsonb_ivm_flush` to apply later.
+///
+/// ```sql
+/// CREATE TRIGGER tv_user_cascade_update AFTER UPDATE ON tv_user
+/// REFERENCING OLD TABLE AS old_rows NEW TABLE AS new_rows
+/// FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger('deferred');
+/// ```
+///
+/// # Notes
+/// - Must be an `AFTER ... FOR EACH STATEMENT` trigger with transition tables
+/// - As with `jsonb_ivm_cascade`, attach it to base tables only
+#[pg_trigger]
+pub fn jsonb_ivm_cascade_trigger<'a>(
+    trigger: &'a PgTrigger<'a>,
+) -> Result<Option<PgHeapTuple<'a, AllocatedByPostgres>>, PgTriggerError> {
+    if trigger.level() != PgTriggerLevel::Statement || trigger.when()? != PgTriggerWhen::After {
+        error!("jsonb_ivm_cascade_trigger must be fired AFTER ... FOR EACH STATEMENT");
+    }
+
+    let deferred = match trigger.extra_args()?.as_slice() {
+        [] => false,
+        [mode] if mode.eq_ignore_ascii_case("deferred") => true,
+        args => error!(
+            "jsonb_ivm_cascade_trigger takes no argument or 'deferred', got {:?}",
+            args
+        ),
+    };
+
+    let old_table = trigger.old_transition_table_name()?;
+    let new_table = trigger.new_transition_table_name()?;
+    if old_table.is_none() && new_table.is_none() {
+        error!("jsonb_ivm_cascade_trigger needs REFERENCING OLD TABLE and/or NEW TABLE");
+    }
+
+    let (old_rows, new_rows) = Spi::connect(|client| {
+        // Transition tables are only visible to queries once registered with SPI
+        // SAFETY: the trigger data comes from PostgreSQL for this trigger call
+        let status = unsafe {
+            pg_sys::SPI_register_trigger_data(std::ptr::from_ref(trigger.trigger_data()).cast_mut())
+        };
+        let registered = i32::try_from(pg_sys::SPI_OK_TD_REGISTER)
+            .unwrap_or_else(|_| error!("SPI_OK_TD_REGISTER does not fit in an int"));
+        if status != registered {
+            error!("Failed to register the transition tables of the trigger");
+        }
+
+        let read = |table: Option<&str>| -> Result<Vec<Value>, spi::Error> {
+            let Some(table) = table else {
+                return Ok(Vec::new());
+            };
+            let rows = client
+                .select(
+                    &format!(
+                        "SELECT COALESCE(jsonb_agg(to_jsonb(r)), '[]') FROM {} r",
+                        spi::quote_identifier(table)
+                    ),
+                    None,
+                    &[],
+                )?
+                .next()
+                .and_then(|row| row.get::<JsonB>(1).transpose())
+                .transpose()?;
+            Ok(match rows {
+                Some(JsonB(Value::Array(rows))) => rows,
+                _ => Vec::new(),
+            })
+        };
+        Ok::<_, spi::Error>((read(old_table)?, read(new_table)?))
+    })
+    .unwrap_or_else(|e| error!("{}", e));
+
+    let source_table = table_name(&format!(
+        "{}.{}",
+        spi::quote_identifier(trigger.table_schema()?),
+        spi::quote_identifier(trigger.table_name()?)
+    ));
+    if deferred {
+        enqueue(&source_table, old_rows, new_rows);
+    } else {
+        propagate(&source_table, &old_rows, &new_rows);
+    }
+
+    Ok(None)
+}
+
+/// Apply the cascade of a change to `source_table`
+///
+/// `old_rows` and `new_rows` are row images of the changed rows, before and
+/// after the change (rows present on one side only were inserted or deleted).
+/// Returns the report rows of `jsonb_ivm_cascade`.
+pub fn propagate(
+    source_table: &str,
+    old_rows: &[Value],
+    new_rows: &[Value],
+) -> Vec<(i32, i64, String, i64)> {
+    let steps = plan(&load_dependencies(None), source_table);
+
+    // Latest image of every updated projection row, by table and key
+    let mut changed: BTreeMap<String, BTreeMap<String, Value>> = BTreeMap::new();
+    let mut report = Vec::new();
+
+    for (idx, step) in steps.iter().enumerate() {
+        let first = &step[0];
+        let (old, new) = if first.source_table == source_table {
+            (old_rows.to_vec(), new_rows.to_vec())
+        } else {
+            // Projection rows updated by earlier steps keep their parents
+            let Some(rows) = changed.get(&first.source_table) else {
+                continue;
+            };
+            let rows: Vec<Value> = rows.values().cloned().collect();
+            (rows.clone(), rows)
+        };
+
+        let sql = match first.kind {
+            DependencyKind::Object => object_statement(step),
+            DependencyKind::Array => array_statement(first),
+        };
+        let updated = update_rows(&sql, old, new);
+
+        let rows_updated = i64::try_from(updated.len()).unwrap_or(i64::MAX);
+        for dep in step {
+            report.push((
+                step_number(idx),
+                dep.id,
+                dep.target_table.clone(),
+                rows_updated,
+            ));
+        }
+        changed
+            .entry(first.target_table.clone())
+            .or_default()
+            .extend(updated);
+    }
+
+    report
+}
+
+/// Combined update applying object dependencies between the same two tables
+///
+/// `$1` and `$2` are the old and new source row images. Each projection row
+/// referencing a changed source row gets every patch in one update: a
+/// `LATERAL` chain threads the document through one patch per dependency.
+fn object_statement(step: &[RegisteredDependency]) -> String {
+    let RegisteredDependency {
+        function_schema: schema,
+        source_table,
+        source_key,
+        source_data,
+        target_table,
+        target_key,
+        target_data,
+        ..
+    } = &step[0];
+
+    let mut joins = String::new();
+    let mut candidates = Vec::new();
+    let mut doc = format!("t.{target_data}");
+    for (i, dep) in step.iter().enumerate() {
+        let RegisteredDependency {
+            fk_column,
+            embed_path,
+            ..
+        } = dep;
+        // Writing to a String cannot fail
+        let _ = write!(
+            joins,
+            "
+             LEFT JOIN new_rows n{i} ON n{i}.{source_key} = t.{fk_column}
+             LEFT JOIN gone_rows g{i} ON g{i}.{source_key} = t.{fk_column}
+             CROSS JOIN LATERAL (SELECT CASE
+                 WHEN n{i}.{source_key} IS NOT NULL
+                     THEN {schema}.jsonb_smart_patch_nested({doc}, n{i}.{source_data}, {embed_path})
+                 WHEN g{i}.{source_key} IS NOT NULL
+                     THEN jsonb_set({doc}, {embed_path}, 'null'::jsonb, false)
+                 ELSE {doc}
+             END) p{i}(doc)"
+        );
+        candidates.push(format!("t.{fk_column} IN (SELECT key FROM changed_keys)"));
+        doc = format!("p{i}.doc");
+    }
+    let candidates = candidates.join(" OR ");
+
+    format!(
+        "WITH new_rows AS (
+             SELECT * FROM jsonb_populate_recordset(NULL::{source_table}, $2)
+             WHERE {source_data} IS NOT NULL
+         ), gone_rows AS (
+             SELECT o.* FROM jsonb_populate_recordset(NULL::{source_table}, $1) o
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM jsonb_populate_recordset(NULL::{source_table}, $2) n
+                 WHERE n.{source_key} = o.{source_key}
+             )
+         ), changed_keys AS (
+             SELECT {source_key} AS key FROM new_rows
+             UNION ALL
+             SELECT {source_key} FROM gone_rows
+         ), patched AS (
+             SELECT t.{target_key} AS key, {doc} AS doc
+             FROM {target_table} t{joins}
+             WHERE {candidates}
+         )
+         UPDATE {target_table} t
+         SET {target_data} = patched.doc
+         FROM patched
+         WHERE t.{target_key} = patched.key
+         RETURNING t.{target_key}::text, to_jsonb(t)"
+    )
+}
+
+/// Update applying an array dependency, once per projection row
+///
+/// `$1` and `$2` are the old and new source row images, paired by key.
+/// Deleted elements and elements moved to another parent are removed,
+/// inserted and moved-in elements added, and the others replaced; all
+/// changes to one projection row go through one nested batch call.
+fn array_statement(dep: &RegisteredDependency) -> String {
+    let RegisteredDependency {
+        function_schema: schema,
+        source_table,
+        source_key,
+        source_data,
+        target_table,
+        target_key,
+        target_data,
+        fk_column,
+        embed_path,
+        ..
+    } = dep;
+    let match_key = dep.match_key.as_deref().unwrap_or("NULL");
+
+    format!(
+        "WITH changes AS (
+             SELECT o.{fk_column} AS old_parent, n.{fk_column} AS new_parent,
+                    o.{source_data} -> {match_key} AS old_match, n.{source_data} AS new_doc
+             FROM jsonb_populate_recordset(NULL::{source_table}, $1) o
+             FULL JOIN jsonb_populate_recordset(NULL::{source_table}, $2) n
+                 ON n.{source_key} = o.{source_key}
+         ), patches AS (
+             SELECT parent,
+                    jsonb_agg(doc) FILTER (WHERE op = 'delete') AS deletes,
+                    jsonb_agg(doc) FILTER (WHERE op = 'update') AS updates,
+                    jsonb_agg(doc) FILTER (WHERE op = 'insert') AS inserts
+             FROM (
+                 SELECT old_parent, 'delete', old_match FROM changes
+                 WHERE old_parent IS DISTINCT FROM new_parent AND old_match IS NOT NULL
+                 UNION ALL
+                 SELECT new_parent, 'insert', new_doc FROM changes
+                 WHERE old_parent IS DISTINCT FROM new_parent AND new_doc IS NOT NULL
+                 UNION ALL
+                 SELECT new_parent, 'update',
+                        jsonb_build_object('match_value', new_doc -> {match_key}, 'updates', new_doc)
+                 FROM changes
+                 WHERE old_parent IS NOT DISTINCT FROM new_parent AND new_doc -> {match_key} IS NOT NULL
+             ) c(parent, op, doc)
+             WHERE parent IS NOT NULL
+             GROUP BY parent
+         )
+         UPDATE {target_table} t
+         SET {target_data} = {schema}.jsonb_array_insert_batch(
+                 {schema}.jsonb_array_update_where_batch(
+                     {schema}.jsonb_array_delete_where_batch(
+                         t.{target_data}, {embed_path}, {match_key}, COALESCE(p.deletes, '[]'), 'ignore'),
+                     {embed_path}, {match_key}, COALESCE(p.updates, '[]'), 'replace', 'ignore'),
+                 {embed_path}, COALESCE(p.inserts, '[]'), NULL, NULL,
+                 on_missing => CASE WHEN p.inserts IS NULL THEN 'ignore' ELSE 'create' END)
+         FROM patches p
+         WHERE t.{target_key} = p.parent
+         RETURNING t.{target_key}::text, to_jsonb(t)"
+    )
+}
+
+/// Run an `UPDATE ... RETURNING key, row` statement; returns `(key, row image)` pairs
+fn update_rows(sql: &str, old_rows: Vec<Value>, new_rows: Vec<Value>) -> Vec<(String, Value)> {
+    let args: [DatumWithOid; 2] = [
+        JsonB(Value::Array(old_rows)).into(),
+        JsonB(Value::Array(new_rows)).into(),
+    ];
+
+    Spi::connect_mut(|client| {
+        client
+            .update(sql, None, &args)?
+            .map(|row| {
+                Ok((
+                    row.get::<String>(1)?.unwrap_or_default(),
+                    row.get::<JsonB>(2)?.map_or(Value::Null, |image| image.0),
+                ))
+            })
+            .collect::<Result<Vec<_>, spi::Error>>()
+    })
+    .unwrap_or_else(|e| error!("{}", e))
+}
+
+/// Catalog spelling of a table name (`regclass` output), as stored in the registry
+fn table_name(table: &str) -> String {
+    Spi::get_one_with_args::<String>("SELECT $1::regclass::text", &[table.into()])
+        .unwrap_or_else(|e| error!("{}", e))
+        .unwrap_or_else(|| table.to_string())
+}
+
+/// 1-based step number of the plan entry at `idx`
+fn step_number(idx: usize) -> i32 {
+    i32::try_from(idx + 1).unwrap_or(i32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAIN: [(&str, &str); 3] = [
+        ("tv_post", "tv_feed"),
+        ("v_company", "tv_user"),
+        ("tv_user", "tv_post"),
+    ];
+
+    #[test]
+    fn test_find_cycle() {
+        assert_eq!(find_cycle(&CHAIN), None);
+        assert_eq!(
+            find_cycle(&[("a", "b"), ("b", "c"), ("c", "a")]),
+            Some(vec!["a", "b", "c", "a"])
+        );
+        assert_eq!(find_cycle(&[("a", "a")]), Some(vec!["a", "a"]));
+    }
+
+    #[test]
+    fn test_cascade_order_follows_chain() {
+        // Registration order does not matter
+        assert_eq!(cascade_order(&CHAIN, "v_company"), Ok(vec![1, 2, 0]));
+        assert_eq!(cascade_order(&CHAIN, "tv_user"), Ok(vec![2, 0]));
+        assert_eq!(cascade_order(&CHAIN, "tv_feed"), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_cascade_order_waits_for_all_inputs() {
+        // tv_feed embeds both users and posts; posts embed users
+        let edges = [
+            ("tv_user", "tv_feed"),
+            ("tv_feed", "tv_digest"),
+            ("tv_user", "tv_post"),
+            ("tv_post", "tv_feed"),
+        ];
+        assert_eq!(cascade_order(&edges, "tv_user"), Ok(vec![0, 2, 3, 1]));
+    }
+
+    #[test]
+    fn test_combine_steps() {
+        use DependencyKind::{Array, Object};
+
+        // tv_post embeds its author and its reviewer, both from tv_user
+        let ordered = [
+            ("tv_user", "tv_post", Object),
+            ("tv_user", "tv_feed", Array),
+            ("tv_user", "tv_post", Object),
+            ("tv_post", "tv_feed", Array),
+            ("tv_post", "tv_feed", Array),
+        ];
+        assert_eq!(
+            combine_steps(&ordered),
+            vec![vec![0, 2], vec![1], vec![3], vec![4]]
+        );
+    }
+
+    #[test]
+    #[allow(clippy::literal_string_with_formatting_args)] // `'{author}'` is a SQL array literal
+    fn test_object_statement_chains_patches() {
+        let author = RegisteredDependency {
+            id: 1,
+            kind: DependencyKind::Object,
+            function_schema: "public".to_string(),
+            source_table: "tv_user".to_string(),
+            source_key: "pk".to_string(),
+            source_data: "data".to_string(),
+            target_table: "tv_post".to_string(),
+            target_key: "pk".to_string(),
+            target_data: "data".to_string(),
+            fk_column: "fk_author".to_string(),
+            embed_path: "'{author}'::text[]".to_string(),
+            match_key: None,
+        };
+        let reviewer = RegisteredDependency {
+            id: 2,
+            fk_column: "fk_reviewer".to_string(),
+            embed_path: "'{reviewer}'::text[]".to_string(),
+            ..author.clone()
+        };
+
+        let sql = object_statement(&[author, reviewer]);
+        assert!(
+            sql.contains("public.jsonb_smart_patch_nested(t.data, n0.data, '{author}'::text[])")
+        );
+        assert!(
+            sql.contains("public.jsonb_smart_patch_nested(p0.doc, n1.data, '{reviewer}'::text[])")
+        );
+        assert!(sql.contains("SELECT t.pk AS key, p1.doc AS doc"));
+        assert!(sql.contains(
+            "WHERE t.fk_author IN (SELECT key FROM changed_keys) OR t.fk_reviewer IN (SELECT key FROM changed_keys)"
+        ));
+    }
+
+    #[test]
+    fn test_cascade_order_reports_cycle() {
+        let edges = [("a", "b"), ("b", "c"), ("c", "b")];
+        assert_eq!(cascade_order(&edges, "a"), Err(vec!["b", "c", "b"]));
+    }
+}
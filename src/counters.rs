@@ -0,0 +1,311 @@
+// jsonb_ivm - Counters Module
+//
+// In-place increments of numeric fields embedded in projections
+// (post_count, like_count, total_amount) without recomputing aggregates.
+
+use pgrx::callconv::{Arg, ArgAbi, BoxRet, FcInfo};
+use pgrx::datum::Datum;
+use pgrx::pgrx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+use pgrx::prelude::*;
+use pgrx::{direct_function_call, direct_function_call_as_datum};
+use serde_json::value::RawValue;
+use serde_json::{Number, Value};
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString};
+use std::str::FromStr;
+
+use crate::path::{parse_path, PathSegment};
+use crate::value_type_name;
+
+/// A `jsonb` value as its exact text
+///
+/// `JsonB` goes through `serde_json::Value`, which turns numbers beyond the
+/// `i64`/`u64` range and decimals into `f64`. Counters read and write this
+/// text instead, so totals keep every digit and so does the rest of the
+/// document.
+pub struct JsonbText(pub String);
+
+impl FromDatum for JsonbText {
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null {
+            return None;
+        }
+        // SAFETY: `datum` is a non-null jsonb datum; `jsonb_out` detoasts it
+        // and returns a palloc'd cstring
+        let cstr = unsafe { direct_function_call::<&CStr>(pg_sys::jsonb_out, &[Some(datum)]) }?;
+        let text = cstr.to_string_lossy().into_owned();
+        // SAFETY: the cstring was allocated by `jsonb_out` and is no longer used
+        unsafe { pg_sys::pfree(cstr.as_ptr().cast_mut().cast()) };
+        Some(Self(text))
+    }
+}
+
+impl IntoDatum for JsonbText {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let Ok(text) = CString::new(self.0) else {
+            error!("jsonb text contains a NUL byte");
+        };
+        // SAFETY: `text` is a valid NUL-terminated cstring that outlives the
+        // call; `jsonb_in` raises an error on invalid input
+        unsafe { direct_function_call_as_datum(pg_sys::jsonb_in, &[text.as_c_str().into_datum()]) }
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::JSONBOID
+    }
+}
+
+// SAFETY: arguments are unboxed with `FromDatum`, like pgrx's own `JsonB`
+unsafe impl<'fcx> ArgAbi<'fcx> for JsonbText {
+    unsafe fn unbox_arg_unchecked(arg: Arg<'_, 'fcx>) -> Self {
+        let index = arg.index();
+        unsafe { arg.unbox_arg_using_from_datum() }
+            .unwrap_or_else(|| panic!("argument {index} must not be null"))
+    }
+}
+
+// SAFETY: the datum is returned through `IntoDatum`, like pgrx's own `JsonB`
+unsafe impl BoxRet for JsonbText {
+    unsafe fn box_into<'fcx>(self, fcinfo: &mut FcInfo<'fcx>) -> Datum<'fcx> {
+        match self.into_datum() {
+            Some(datum) => unsafe { fcinfo.return_raw_datum(datum) },
+            None => fcinfo.return_null(),
+        }
+    }
+}
+
+// SAFETY: the SQL type is `jsonb`, the type converted by `FromDatum`/`IntoDatum`
+unsafe impl SqlTranslatable for JsonbText {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("jsonb"))
+    }
+
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("jsonb")))
+    }
+}
+
+/// Add `delta` to the numeric field at `path`
+///
+/// The addition uses `numeric` arithmetic and the result keeps all
+/// its digits, so decimal amounts do not pick up floating-point error. A
+/// missing (or JSON `null`) field counts as zero and is created, along with
+/// any missing intermediate objects.
+///
+/// # Arguments
+///
+/// * `target` - JSONB document containing the counter
+/// * `path` - Path to the counter, dot notation and array indexing supported
+///   (e.g., `"stats.post_count"`)
+/// * `delta` - Amount to add (negative to decrement)
+///
+/// # Returns
+///
+/// Updated JSONB with the counter incremented. Integer counters stay
+/// integers; a counter or delta with a fractional part produces a decimal.
+///
+/// # Notes
+///
+/// Raises an error if the field holds a value that is not a number.
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT jsonb_ivm_increment('{"stats": {"post_count": 4}}'::jsonb, 'stats.post_count', 1);
+/// -- Result: {"stats": {"post_count": 5}}
+///
+/// SELECT jsonb_ivm_increment('{"id": 1}'::jsonb, 'total_amount', 19.99);
+/// -- Result: {"id": 1, "total_amount": 19.99}
+///
+/// -- Keep an order total in sync with its lines
+/// UPDATE tv_customer
+/// SET data = jsonb_ivm_increment(data, 'total_amount', NEW.amount - OLD.amount)
+/// WHERE id = NEW.fk_customer;
+/// ```
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern(immutable, parallel_safe, strict)]
+#[must_use]
+pub fn jsonb_ivm_increment(target: JsonbText, path: &str, delta: AnyNumeric) -> JsonbText {
+    let segments = parse_path(path).unwrap_or_else(|e| error!("Invalid path '{}': {}", path, e));
+    JsonbText(increment_text(&target.0, &segments, path, &delta))
+}
+
+/// Add `delta` to the numeric field at `segments` of the JSON text `doc`
+///
+/// Shared by `jsonb_ivm_increment` and `jsonb_array_increment_where`; `path`
+/// is the original path text, used in error messages.
+#[must_use]
+pub fn increment_text(
+    doc: &str,
+    segments: &[PathSegment],
+    path: &str,
+    delta: &AnyNumeric,
+) -> String {
+    replace_text(doc, segments, |current| {
+        let current = current_numeric_text(current).unwrap_or_else(|type_name| {
+            error!("Field '{}' is not a number, found: {}", path, type_name)
+        });
+
+        let current = AnyNumeric::from_str(&current).unwrap_or_else(|e| error!("{}", e));
+        let sum = (current + delta.clone()).to_string();
+        // The only `numeric` values without a JSON form
+        if matches!(sum.as_str(), "NaN" | "Infinity" | "-Infinity") {
+            error!(
+                "Cannot increment field '{}': result {} cannot be represented as a JSON number",
+                path, sum
+            );
+        }
+        sum
+    })
+}
+
+/// Replace the value at `segments` of the JSON text `doc` with `op(current)`
+///
+/// `current` is the text of the value (`"null"` when missing). Only the
+/// containers along the path are rewritten, everything else keeps its text;
+/// missing or mistyped containers are created like `set_path` does.
+fn replace_text(doc: &str, segments: &[PathSegment], op: impl FnOnce(&str) -> String) -> String {
+    let Some((segment, rest)) = segments.split_first() else {
+        return op(doc);
+    };
+
+    let written = match segment {
+        PathSegment::Key(key) => {
+            let mut object: BTreeMap<String, Box<RawValue>> =
+                serde_json::from_str(doc).unwrap_or_default();
+            let updated = replace_text(object.get(key).map_or("null", |raw| raw.get()), rest, op);
+            object.insert(key.clone(), raw_value(updated));
+            serde_json::to_string(&object)
+        }
+        PathSegment::Index(idx) => {
+            let mut array: Vec<Box<RawValue>> = serde_json::from_str(doc).unwrap_or_default();
+            while array.len() <= *idx {
+                array.push(raw_value("null".to_string()));
+            }
+            let updated = replace_text(array[*idx].get(), rest, op);
+            array[*idx] = raw_value(updated);
+            serde_json::to_string(&array)
+        }
+    };
+    written.unwrap_or_else(|e| error!("{}", e))
+}
+
+/// Wrap JSON text produced by `replace_text`
+fn raw_value(text: String) -> Box<RawValue> {
+    RawValue::from_string(text).unwrap_or_else(|e| error!("{}", e))
+}
+
+/// Text of the current counter value; missing and `null` fields count as zero
+///
+/// `current` is JSON text, kept as is for numbers of any size; returns the
+/// type name of the value when it is not a number.
+fn current_numeric_text(current: &str) -> Result<String, &'static str> {
+    let current = current.trim();
+    if current.starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+        return Ok(current.to_string());
+    }
+    match serde_json::from_str(current) {
+        Ok(Value::Null) => Ok("0".to_string()),
+        Ok(other) => Err(value_type_name(&other)),
+        Err(_) => Err("invalid JSON"),
+    }
+}
+
+/// Convert `numeric` output text to a JSON number
+///
+/// Text without a fractional part or exponent becomes an integer (falling
+/// back to a float beyond the `u64` range); anything else becomes a decimal.
+///
+/// # Errors
+/// Returns an error for `NaN` and infinities, which JSON cannot represent.
+pub fn numeric_text_to_value(text: &str) -> Result<Value, String> {
+    let is_integer = !text.contains(['.', 'e', 'E']);
+    if is_integer {
+        if let Ok(int_val) = text.parse::<i64>() {
+            return Ok(Value::from(int_val));
+        }
+        if let Ok(uint_val) = text.parse::<u64>() {
+            return Ok(Value::from(uint_val));
+        }
+    }
+
+    text.parse::<f64>()
+        .ok()
+        .and_then(Number::from_f64)
+        .map(Value::Number)
+        .ok_or_else(|| format!("result {text} cannot be represented as a JSON number"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_current_numeric_text() {
+        assert_eq!(current_numeric_text("null"), Ok("0".to_string()));
+        assert_eq!(current_numeric_text("41"), Ok("41".to_string()));
+        assert_eq!(current_numeric_text("2.5"), Ok("2.5".to_string()));
+        assert_eq!(
+            current_numeric_text("98765432109876543210.987654321"),
+            Ok("98765432109876543210.987654321".to_string())
+        );
+        assert_eq!(current_numeric_text(&"9".repeat(400)), Ok("9".repeat(400)));
+        assert_eq!(current_numeric_text("\"41\""), Err("string"));
+        assert_eq!(current_numeric_text("[1]"), Err("array"));
+    }
+
+    #[test]
+    fn test_replace_text_keeps_other_numbers() {
+        let doc = r#"{"id": 12345678901234567891, "stats": {"total": 1.10, "count": 4}}"#;
+        let segments = parse_path("stats.count").unwrap();
+        let updated = replace_text(doc, &segments, |current| {
+            assert_eq!(current, "4");
+            "5".to_string()
+        });
+        assert_eq!(
+            updated,
+            r#"{"id":12345678901234567891,"stats":{"count":5,"total":1.10}}"#
+        );
+    }
+
+    #[test]
+    fn test_replace_text_creates_missing_containers() {
+        let segments = parse_path("stats.counts[1]").unwrap();
+        let updated = replace_text(r#"{"stats": 7}"#, &segments, |current| {
+            assert_eq!(current, "null");
+            "1".to_string()
+        });
+        assert_eq!(updated, r#"{"stats":{"counts":[null,1]}}"#);
+    }
+
+    #[test]
+    fn test_numeric_text_to_value_keeps_integers() {
+        assert_eq!(numeric_text_to_value("42"), Ok(json!(42)));
+        assert_eq!(numeric_text_to_value("-3"), Ok(json!(-3)));
+        assert_eq!(
+            numeric_text_to_value("18446744073709551615"),
+            Ok(json!(u64::MAX))
+        );
+    }
+
+    #[test]
+    fn test_numeric_text_to_value_keeps_decimals() {
+        let value = numeric_text_to_value("11.0").unwrap();
+        assert!(value.is_f64());
+        assert_eq!(value, json!(11.0));
+        assert_eq!(numeric_text_to_value("0.3"), Ok(json!(0.3)));
+    }
+
+    #[test]
+    fn test_numeric_text_to_value_rejects_special_values() {
+        assert!(numeric_text_to_value("NaN").is_err());
+        assert!(numeric_text_to_value("Infinity").is_err());
+    }
+}
@@ -0,0 +1,740 @@
+// jsonb_ivm - Logical Decoding Module
+//
+// Trigger-free maintenance: committed changes to base tables are read back
+// from a test_decoding replication slot and applied through the cascade
+// engine, so writes to the base tables carry no maintenance cost.
+
+use pgrx::prelude::*;
+use pgrx::JsonB;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use crate::cascade::propagate;
+use crate::queue::{coalesce, QueuedChange};
+
+extension_sql!(
+    r"
+-- Last transaction applied from each replication slot by jsonb_ivm_consume_changes
+CREATE TABLE jsonb_ivm.decoding_progress (
+    slot_name name PRIMARY KEY,
+    applied_lsn pg_lsn NOT NULL,
+    transactions bigint NOT NULL DEFAULT 0,
+    changes bigint NOT NULL DEFAULT 0,
+    consumed_at timestamptz NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE jsonb_ivm.decoding_progress IS
+    'Commit LSN of the last transaction applied from each logical replication slot';
+",
+    name = "decoding_progress",
+    requires = ["projection_registry"]
+);
+
+/// Apply committed base table changes read from a logical replication slot
+///
+/// Reads the transactions decoded by a `test_decoding` slot, keeps the row
+/// changes of base tables (registered dependency sources that are not
+/// projections themselves), coalesces them per row and runs the cascade, as
+/// `jsonb_ivm_flush` does for queued changes. No trigger is needed on the
+/// base tables.
+///
+/// # Arguments
+///
+/// * `slot_name` - Logical replication slot using the `test_decoding` plugin
+///   (default `'jsonb_ivm'`)
+/// * `max_changes` - Decoded changes to read per call (default 10000); the
+///   last transaction is always read whole
+///
+/// # Returns
+///
+/// The number of base table row changes consumed; 0 when there is nothing new
+///
+/// # Examples
+///
+/// ```sql
+/// -- Once (wal_level = logical); base tables need their full old row in the WAL
+/// ALTER TABLE tv_user REPLICA IDENTITY FULL;
+/// SELECT pg_create_logical_replication_slot('jsonb_ivm', 'test_decoding');
+///
+/// -- Then periodically (or set jsonb_ivm.worker_slot)
+/// SELECT jsonb_ivm_consume_changes();
+/// ```
+///
+/// # Notes
+/// - Progress is stored in `jsonb_ivm.decoding_progress` in the consuming
+///   transaction, and the slot is advanced to it on the next call: a failed
+///   call applies nothing and leaves the changes to the next one
+/// - Calls on the same slot are serialized; a concurrent call returns 0
+/// - Writes to projections (including the cascade's own) are ignored
+/// - `TRUNCATE` of a base table cannot be cascaded: it is skipped with a
+///   warning and its projections must be rebuilt
+#[pg_extern]
+pub fn jsonb_ivm_consume_changes(
+    slot_name: default!(&str, "'jsonb_ivm'"),
+    max_changes: default!(i32, 10000),
+) -> i64 {
+    if max_changes < 1 {
+        error!("max_changes must be at least 1, got {}", max_changes);
+    }
+
+    consume(slot_name, max_changes)
+}
+
+/// Release the WAL of the transactions applied by committed calls
+///
+/// Returns the LSN of the last applied commit record (0 when none was).
+fn release_applied(slot_name: &str) -> u64 {
+    let applied = Spi::get_one_with_args::<String>(
+        "SELECT applied_lsn::text FROM jsonb_ivm.decoding_progress WHERE slot_name = $1",
+        &[slot_name.into()],
+    )
+    .unwrap_or_else(|e| error!("{}", e));
+    if let Some(lsn) = &applied {
+        Spi::run_with_args(
+            "SELECT pg_replication_slot_advance(slot_name, $2::pg_lsn)
+             FROM pg_replication_slots
+             WHERE slot_name = $1 AND confirmed_flush_lsn < $2::pg_lsn",
+            &[slot_name.into(), lsn.as_str().into()],
+        )
+        .unwrap_or_else(|e| error!("{}", e));
+    }
+    applied.as_deref().and_then(parse_lsn).unwrap_or(0)
+}
+
+/// Read, apply and record up to `max_changes` decoded changes of `slot_name`
+///
+/// Returns the number of base table row changes consumed.
+pub fn consume(slot_name: &str, max_changes: i32) -> i64 {
+    check_slot(slot_name);
+
+    let locked = Spi::get_one_with_args::<bool>(
+        "SELECT pg_try_advisory_xact_lock(hashtext('jsonb_ivm.decoding_progress'), hashtext($1))",
+        &[slot_name.into()],
+    )
+    .unwrap_or_else(|e| error!("{}", e))
+    .unwrap_or(false);
+    if !locked {
+        return 0;
+    }
+
+    // A transaction is skipped once the slot is past its commit record
+    let applied = release_applied(slot_name);
+
+    let decoded = Spi::connect(|client| {
+        client
+            .select(
+                "SELECT lsn::text, data
+                 FROM pg_logical_slot_peek_changes($1, NULL, $2,
+                                                   'include-xids', '0', 'skip-empty-xacts', '1')",
+                None,
+                &[slot_name.into(), max_changes.into()],
+            )?
+            .map(|row| {
+                Ok((
+                    row.get::<String>(1)?.unwrap_or_default(),
+                    row.get::<String>(2)?.unwrap_or_default(),
+                ))
+            })
+            .collect::<Result<Vec<_>, spi::Error>>()
+    })
+    .unwrap_or_else(|e| error!("{}", e));
+
+    let base_tables = base_tables();
+    let mut committed: Vec<(&BaseTable, DecodedChange)> = Vec::new();
+    let mut open: Vec<(&BaseTable, DecodedChange)> = Vec::new();
+    let mut last_commit: Option<&str> = None;
+    let mut transactions: i64 = 0;
+
+    for (lsn, data) in &decoded {
+        let line = parse_line(data)
+            .unwrap_or_else(|e| error!("Cannot parse decoded change at {}: {}", lsn, e));
+        match line {
+            DecodedLine::Begin => open.clear(),
+            DecodedLine::Commit => {
+                if parse_lsn(lsn).is_some_and(|commit| commit > applied) {
+                    committed.append(&mut open);
+                    last_commit = Some(lsn);
+                    transactions += 1;
+                }
+                open.clear();
+            }
+            DecodedLine::Change(change) => {
+                if let Some(table) = base_tables.get(&change.table) {
+                    open.push((table, change));
+                }
+            }
+            DecodedLine::Truncate(tables) => {
+                for table in tables.iter().filter_map(|table| base_tables.get(table)) {
+                    warning!(
+                        "TRUNCATE of '{}' at {} cannot be cascaded; rebuild its projections",
+                        table.name,
+                        lsn
+                    );
+                }
+            }
+            DecodedLine::Other => {}
+        }
+    }
+
+    let Some(last_commit) = last_commit else {
+        return 0;
+    };
+
+    let consumed = i64::try_from(committed.len()).unwrap_or(i64::MAX);
+    for (source_table, old_rows, new_rows) in coalesce(queued_changes(committed)) {
+        propagate(&source_table, &old_rows, &new_rows);
+    }
+
+    Spi::run_with_args(
+        "INSERT INTO jsonb_ivm.decoding_progress AS p (slot_name, applied_lsn, transactions, changes)
+         VALUES ($1, $2::pg_lsn, $3, $4)
+         ON CONFLICT (slot_name) DO UPDATE
+         SET applied_lsn = EXCLUDED.applied_lsn,
+             transactions = p.transactions + EXCLUDED.transactions,
+             changes = p.changes + EXCLUDED.changes,
+             consumed_at = now()",
+        &[
+            slot_name.into(),
+            last_commit.into(),
+            transactions.into(),
+            consumed.into(),
+        ],
+    )
+    .unwrap_or_else(|e| error!("{}", e));
+
+    consumed
+}
+
+/// Check that `slot_name` is a `test_decoding` slot of the current database
+fn check_slot(slot_name: &str) {
+    let slot = Spi::connect(|client| {
+        client
+            .select(
+                "SELECT plugin::text, database = current_database()
+                 FROM pg_replication_slots WHERE slot_name = $1",
+                None,
+                &[slot_name.into()],
+            )?
+            .next()
+            .map(|row| {
+                Ok::<_, spi::Error>((
+                    row.get::<String>(1)?.unwrap_or_default(),
+                    row.get::<bool>(2)?.unwrap_or(false),
+                ))
+            })
+            .transpose()
+    })
+    .unwrap_or_else(|e| error!("{}", e));
+
+    match slot {
+        None => error!("Replication slot '{}' not found", slot_name),
+        Some((plugin, _)) if plugin != "test_decoding" => error!(
+            "Replication slot '{}' uses plugin '{}', jsonb_ivm_consume_changes needs 'test_decoding'",
+            slot_name,
+            plugin
+        ),
+        Some((_, false)) => error!(
+            "Replication slot '{}' belongs to another database",
+            slot_name
+        ),
+        Some(_) => {}
+    }
+}
+
+/// A base table, by its name in the decoding output
+struct BaseTable {
+    /// Name as stored in the registry
+    name: String,
+    key_column: String,
+    full_identity: bool,
+}
+
+/// Registered dependency sources that are not projections themselves
+///
+/// Keyed by schema-qualified, quoted name, as `test_decoding` prints it.
+fn base_tables() -> BTreeMap<String, BaseTable> {
+    Spi::connect(|client| {
+        client
+            .select(
+                "SELECT quote_ident(n.nspname) || '.' || quote_ident(c.relname),
+                        p.table_name::text,
+                        p.key_column::text,
+                        c.relreplident = 'f'
+                 FROM jsonb_ivm.projection p
+                 JOIN pg_catalog.pg_class c ON c.oid = p.table_name
+                 JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+                 WHERE EXISTS (SELECT 1 FROM jsonb_ivm.dependency d WHERE d.source_table = p.table_name)
+                   AND NOT EXISTS (SELECT 1 FROM jsonb_ivm.dependency d WHERE d.target_table = p.table_name)",
+                None,
+                &[],
+            )?
+            .map(|row| {
+                Ok((
+                    row.get::<String>(1)?.unwrap_or_default(),
+                    BaseTable {
+                        name: row.get::<String>(2)?.unwrap_or_default(),
+                        key_column: row.get::<String>(3)?.unwrap_or_default(),
+                        full_identity: row.get::<bool>(4)?.unwrap_or(false),
+                    },
+                ))
+            })
+            .collect::<Result<BTreeMap<_, _>, spi::Error>>()
+    })
+    .unwrap_or_else(|e| error!("{}", e))
+}
+
+/// Turn decoded changes into queue entries with `to_jsonb(row)` images
+fn queued_changes(changes: Vec<(&BaseTable, DecodedChange)>) -> Vec<QueuedChange> {
+    // Text images, converted to typed images in one query per column list
+    let mut images: Vec<Option<Value>> = Vec::new();
+    let mut by_columns: BTreeMap<Vec<(String, String)>, Vec<usize>> = BTreeMap::new();
+    let mut entries = Vec::new();
+
+    for (table, change) in changes {
+        if !table.full_identity || (change.kind == ChangeKind::Update && change.old.is_none()) {
+            error!(
+                "Table '{}' needs REPLICA IDENTITY FULL for logical decoding maintenance",
+                table.name
+            );
+        }
+
+        let mut slot = |tuple: Option<&Tuple>, old: Option<&Tuple>| {
+            let idx = images.len();
+            let Some(tuple) = tuple else {
+                images.push(None);
+                return idx;
+            };
+            let image = text_image(tuple, old).unwrap_or_else(|e| error!("{}", e));
+            images.push(Some(image));
+            by_columns
+                .entry(
+                    tuple
+                        .iter()
+                        .map(|column| (column.name.clone(), column.type_name.clone()))
+                        .collect(),
+                )
+                .or_default()
+                .push(idx);
+            idx
+        };
+        let old = slot(change.old.as_ref(), None);
+        let new = slot(change.new.as_ref(), change.old.as_ref());
+        entries.push((table, old, new));
+    }
+
+    for (columns, indexes) in by_columns {
+        let text: Vec<Value> = indexes
+            .iter()
+            .map(|&idx| images[idx].take().unwrap_or_default())
+            .collect();
+        for (idx, image) in indexes.into_iter().zip(typed_images(&columns, text)) {
+            images[idx] = Some(image);
+        }
+    }
+
+    entries
+        .into_iter()
+        .map(|(table, old, new)| {
+            let old_row = images[old].take();
+            let new_row = images[new].take();
+            let row_key = new_row
+                .as_ref()
+                .or(old_row.as_ref())
+                .and_then(|image| image.get(&table.key_column))
+                .map(|key| match key {
+                    Value::String(key) => key.clone(),
+                    key => key.to_string(),
+                })
+                .unwrap_or_default();
+            QueuedChange {
+                source_table: table.name.clone(),
+                row_key,
+                old_row,
+                new_row,
+            }
+        })
+        .collect()
+}
+
+/// Cast text images with the column types of the decoded tuples
+fn typed_images(columns: &[(String, String)], text: Vec<Value>) -> Vec<Value> {
+    let select = columns
+        .iter()
+        .map(|(name, type_name)| {
+            format!(
+                "(e.r ->> {})::{} AS {}",
+                spi::quote_literal(name),
+                type_name,
+                spi::quote_identifier(name)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "SELECT COALESCE(jsonb_agg(to_jsonb(t) ORDER BY e.n), '[]')
+         FROM jsonb_array_elements($1) WITH ORDINALITY AS e(r, n),
+              LATERAL (SELECT {select}) t"
+    );
+
+    match Spi::get_one_with_args::<JsonB>(&sql, &[JsonB(Value::Array(text)).into()])
+        .unwrap_or_else(|e| error!("{}", e))
+    {
+        Some(JsonB(Value::Array(images))) => images,
+        _ => Vec::new(),
+    }
+}
+
+/// JSON object of the text values of `tuple`
+///
+/// Unchanged TOAST values (not written to the WAL) are taken from `old`.
+fn text_image(tuple: &Tuple, old: Option<&Tuple>) -> Result<Value, String> {
+    let mut image = Map::new();
+    for column in tuple {
+        let value = match &column.value {
+            ColumnValue::Null => Value::Null,
+            ColumnValue::Text(text) => Value::String(text.clone()),
+            ColumnValue::UnchangedToast => old
+                .and_then(|old| old.iter().find(|o| o.name == column.name))
+                .and_then(|o| match &o.value {
+                    ColumnValue::Text(text) => Some(Value::String(text.clone())),
+                    ColumnValue::Null => Some(Value::Null),
+                    ColumnValue::UnchangedToast => None,
+                })
+                .ok_or_else(|| format!("No value for unchanged TOAST column {}", column.name))?,
+        };
+        image.insert(column.name.clone(), value);
+    }
+    Ok(Value::Object(image))
+}
+
+/// Parse a `pg_lsn` (`X/Y`) into its 64-bit position
+pub fn parse_lsn(lsn: &str) -> Option<u64> {
+    let (high, low) = lsn.split_once('/')?;
+    let high = u64::from_str_radix(high, 16).ok()?;
+    let low = u64::from_str_radix(low, 16).ok()?;
+    Some((high << 32) | low)
+}
+
+/// One line of `test_decoding` output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodedLine {
+    Begin,
+    Commit,
+    Change(DecodedChange),
+    /// Truncated tables, as printed
+    Truncate(Vec<String>),
+    /// Anything else (e.g., logical messages)
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// A decoded row change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedChange {
+    /// Schema-qualified, quoted table name
+    pub table: String,
+    pub kind: ChangeKind,
+    /// Replica identity image; the whole row with REPLICA IDENTITY FULL
+    pub old: Option<Tuple>,
+    pub new: Option<Tuple>,
+}
+
+pub type Tuple = Vec<Column>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub name: String,
+    /// SQL type, as printed by `format_type`
+    pub type_name: String,
+    pub value: ColumnValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnValue {
+    Null,
+    /// Out-of-line value left unchanged by an update
+    UnchangedToast,
+    /// Text output of the value
+    Text(String),
+}
+
+/// Parse one line of `test_decoding` output (`include-xids` off)
+///
+/// Changes look like
+/// `table public.tv_user: UPDATE: old-key: pk[integer]:1 ... new-tuple: pk[integer]:1 ...`.
+pub fn parse_line(line: &str) -> Result<DecodedLine, String> {
+    if line == "BEGIN" || line.starts_with("BEGIN ") {
+        return Ok(DecodedLine::Begin);
+    }
+    if line == "COMMIT" || line.starts_with("COMMIT ") {
+        return Ok(DecodedLine::Commit);
+    }
+    let Some(mut rest) = line.strip_prefix("table ") else {
+        return Ok(DecodedLine::Other);
+    };
+
+    let mut tables = Vec::new();
+    loop {
+        let (_, after) = identifier(rest)?;
+        let after = match after.strip_prefix('.') {
+            Some(after) => identifier(after)?.1,
+            None => after,
+        };
+        tables.push(rest[..rest.len() - after.len()].to_string());
+        rest = after;
+        match rest.strip_prefix(", ") {
+            Some(after) => rest = after,
+            None => break,
+        }
+    }
+
+    let rest = rest
+        .strip_prefix(": ")
+        .ok_or("expected ': ' after the table name")?;
+    let (action, rest) = rest.split_once(':').ok_or("missing change type")?;
+    let kind = match action {
+        "TRUNCATE" => return Ok(DecodedLine::Truncate(tables)),
+        "INSERT" => ChangeKind::Insert,
+        "UPDATE" => ChangeKind::Update,
+        "DELETE" => ChangeKind::Delete,
+        _ => return Err(format!("unknown change type '{action}'")),
+    };
+    let [table] = <[String; 1]>::try_from(tables).map_err(|_| "expected one table")?;
+
+    let rest = rest.trim_start_matches(' ');
+    let (old_key, tuple) = if rest == "(no-tuple-data)" {
+        (None, None)
+    } else {
+        let (old_key, rest) = match rest.strip_prefix("old-key:") {
+            Some(after) => {
+                let (columns, after) = columns(after)?;
+                let after = after
+                    .strip_prefix("new-tuple:")
+                    .ok_or("expected 'new-tuple:' after the old key")?;
+                (Some(columns), after)
+            }
+            None => (None, rest),
+        };
+        let (tuple, after) = columns(rest)?;
+        if !after.is_empty() {
+            return Err(format!("unexpected '{after}'"));
+        }
+        (old_key, Some(tuple))
+    };
+
+    let (old, new) = match kind {
+        ChangeKind::Insert => (None, tuple),
+        ChangeKind::Update => (old_key, tuple),
+        ChangeKind::Delete => (tuple, None),
+    };
+    Ok(DecodedLine::Change(DecodedChange {
+        table,
+        kind,
+        old,
+        new,
+    }))
+}
+
+/// Parse `name[type]:value` columns up to the end or `new-tuple:`
+fn columns(mut rest: &str) -> Result<(Tuple, &str), String> {
+    let mut tuple = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(' ');
+        if rest.is_empty() || rest.starts_with("new-tuple:") {
+            return Ok((tuple, rest));
+        }
+
+        let (name, after) = identifier(rest)?;
+        let after = after
+            .strip_prefix('[')
+            .ok_or_else(|| format!("expected '[' after column {name}"))?;
+        // Array types end with "[]", so the type ends at the first "]:"
+        let (type_name, after) = after
+            .split_once("]:")
+            .ok_or_else(|| format!("missing type of column {name}"))?;
+        let (value, after) = column_value(after)?;
+
+        tuple.push(Column {
+            name,
+            type_name: type_name.to_string(),
+            value,
+        });
+        rest = after;
+    }
+}
+
+/// Parse a possibly double-quoted identifier; returns it unquoted
+fn identifier(s: &str) -> Result<(String, &str), String> {
+    if let Some(quoted) = s.strip_prefix('"') {
+        let mut name = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((idx, c)) = chars.next() {
+            if c != '"' {
+                name.push(c);
+            } else if quoted[idx + 1..].starts_with('"') {
+                name.push('"');
+                chars.next();
+            } else {
+                return Ok((name, &quoted[idx + 1..]));
+            }
+        }
+        return Err(format!("unterminated identifier {s}"));
+    }
+
+    let end = s.find(['.', '[', ':', ',', ' ']).unwrap_or(s.len());
+    if end == 0 {
+        return Err(format!("expected an identifier at '{s}'"));
+    }
+    Ok((s[..end].to_string(), &s[end..]))
+}
+
+/// Parse a column value: a quoted literal, `null`, `unchanged-toast-datum`
+/// or an unquoted number, boolean or bit string
+fn column_value(s: &str) -> Result<(ColumnValue, &str), String> {
+    if let Some(quoted) = s.strip_prefix('\'') {
+        let mut text = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((idx, c)) = chars.next() {
+            if c != '\'' {
+                text.push(c);
+            } else if quoted[idx + 1..].starts_with('\'') {
+                text.push('\'');
+                chars.next();
+            } else {
+                return Ok((ColumnValue::Text(text), &quoted[idx + 1..]));
+            }
+        }
+        return Err("unterminated literal".to_string());
+    }
+
+    let end = s.find(' ').unwrap_or(s.len());
+    let value = match &s[..end] {
+        "null" => ColumnValue::Null,
+        "unchanged-toast-datum" => ColumnValue::UnchangedToast,
+        bits if bits.starts_with("B'") && bits.ends_with('\'') && bits.len() >= 3 => {
+            ColumnValue::Text(bits[2..bits.len() - 1].to_string())
+        }
+        text => ColumnValue::Text(text.to_string()),
+    };
+    Ok((value, &s[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, type_name: &str, value: ColumnValue) -> Column {
+        Column {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            value,
+        }
+    }
+
+    fn text(value: &str) -> ColumnValue {
+        ColumnValue::Text(value.to_string())
+    }
+
+    #[test]
+    fn test_parse_transaction_markers() {
+        assert_eq!(parse_line("BEGIN"), Ok(DecodedLine::Begin));
+        assert_eq!(parse_line("COMMIT"), Ok(DecodedLine::Commit));
+        assert_eq!(
+            parse_line("message: transactional: 1 prefix: x, sz: 1 content:y"),
+            Ok(DecodedLine::Other)
+        );
+    }
+
+    #[test]
+    fn test_parse_insert() {
+        let line = r#"table public.tv_user: INSERT: pk[integer]:1 data[jsonb]:'{"name": "O''Brien"}' tags[text[]]:'{a,b}' note[text]:null"#;
+        assert_eq!(
+            parse_line(line),
+            Ok(DecodedLine::Change(DecodedChange {
+                table: "public.tv_user".to_string(),
+                kind: ChangeKind::Insert,
+                old: None,
+                new: Some(vec![
+                    column("pk", "integer", text("1")),
+                    column("data", "jsonb", text(r#"{"name": "O'Brien"}"#)),
+                    column("tags", "text[]", text("{a,b}")),
+                    column("note", "text", ColumnValue::Null),
+                ]),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_update_with_old_row() {
+        let line = "table public.tv_user: UPDATE: old-key: pk[integer]:1 data[jsonb]:'{}' \
+                    new-tuple: pk[integer]:1 data[jsonb]:unchanged-toast-datum";
+        let Ok(DecodedLine::Change(change)) = parse_line(line) else {
+            panic!("expected a change");
+        };
+        assert_eq!(change.kind, ChangeKind::Update);
+        assert_eq!(
+            change.old,
+            Some(vec![
+                column("pk", "integer", text("1")),
+                column("data", "jsonb", text("{}")),
+            ])
+        );
+        assert_eq!(
+            change.new.as_ref().map(|new| new[1].value.clone()),
+            Some(ColumnValue::UnchangedToast)
+        );
+        assert_eq!(
+            text_image(change.new.as_ref().unwrap(), change.old.as_ref()),
+            Ok(serde_json::json!({"pk": "1", "data": "{}"}))
+        );
+    }
+
+    #[test]
+    fn test_parse_quoted_names_and_delete() {
+        let line = r#"table "My Schema"."User ""X""": DELETE: "Id"[bigint]:7 flags[bit(3)]:B'101'"#;
+        assert_eq!(
+            parse_line(line),
+            Ok(DecodedLine::Change(DecodedChange {
+                table: r#""My Schema"."User ""X""""#.to_string(),
+                kind: ChangeKind::Delete,
+                old: Some(vec![
+                    column("Id", "bigint", text("7")),
+                    column("flags", "bit(3)", text("101")),
+                ]),
+                new: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_truncate() {
+        assert_eq!(
+            parse_line("table public.tv_user, public.v_company: TRUNCATE: (no-flags)"),
+            Ok(DecodedLine::Truncate(vec![
+                "public.tv_user".to_string(),
+                "public.v_company".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_line("table public.t: INSERT: pk[integer:1").is_err());
+        assert!(parse_line("table public.t: INSERT: pk[integer]:'open").is_err());
+        assert!(parse_line("table public.t: UPSERT: pk[integer]:1").is_err());
+    }
+
+    #[test]
+    fn test_parse_lsn() {
+        assert_eq!(parse_lsn("0/16B3748"), Some(0x016B_3748));
+        assert_eq!(parse_lsn("1/0"), Some(1 << 32));
+        assert!(parse_lsn("1/0").unwrap() > parse_lsn("0/FFFFFFFF").unwrap());
+        assert_eq!(parse_lsn("garbage"), None);
+    }
+}
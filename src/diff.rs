@@ -0,0 +1,575 @@
+// jsonb_ivm - Structural Diff Module
+//
+// Path-level comparison of two JSON documents, used to detect and explain
+// drift between stored projections and their canonical definition.
+
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeMap;
+
+/// Arrays compared regardless of element order, by path
+///
+/// Maps a document path (e.g., `"posts"`) to the key identifying elements,
+/// if any. Keyed arrays pair elements by key and report differences inside
+/// them; arrays without a key are compared as multisets.
+pub type UnorderedArrays = BTreeMap<String, Option<String>>;
+
+/// One difference between a stored document and the expected one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// Location of the difference (`""` for the whole document), e.g.,
+    /// `"author.name"`, `"tags[2]"`, `"posts[id=3].title"` or `"labels[*]"`
+    /// for an unmatched element of an unordered array
+    pub path: String,
+    /// Stored value, `None` when missing from the stored document
+    pub stored: Option<Value>,
+    /// Expected value, `None` when missing from the expected document
+    pub expected: Option<Value>,
+}
+
+impl Difference {
+    fn new(path: &str, stored: Option<&Value>, expected: Option<&Value>) -> Self {
+        Self {
+            path: path.to_string(),
+            stored: stored.cloned(),
+            expected: expected.cloned(),
+        }
+    }
+
+    /// JSON form: `{"path": ..., "stored": ..., "expected": ...}`, omitting missing sides
+    #[must_use]
+    pub fn to_value(&self) -> Value {
+        let mut entry = Map::new();
+        entry.insert("path".to_string(), Value::String(self.path.clone()));
+        if let Some(stored) = &self.stored {
+            entry.insert("stored".to_string(), stored.clone());
+        }
+        if let Some(expected) = &self.expected {
+            entry.insert("expected".to_string(), expected.clone());
+        }
+        Value::Object(entry)
+    }
+}
+
+/// Compare a stored document with the expected one
+///
+/// Objects are compared key by key and arrays element by element, except
+/// arrays listed in `unordered`. Numbers compare by value, so `1` and `1.0`
+/// are equal as in `jsonb`, while integers keep every digit: ids above 2^53
+/// that only differ past `f64` precision are still reported.
+///
+/// # Returns
+///
+/// The differences in document order; empty when the documents match.
+///
+/// # Examples
+/// ```
+/// use jsonb_ivm::diff::{structural_diff, UnorderedArrays};
+/// use serde_json::json;
+///
+/// let stored = json!({"id": 1, "posts": [{"id": 2, "title": "Old"}, {"id": 1}]});
+/// let expected = json!({"id": 1, "posts": [{"id": 1}, {"id": 2, "title": "New"}]});
+///
+/// let unordered = UnorderedArrays::from([("posts".to_string(), Some("id".to_string()))]);
+/// let diff = structural_diff(&stored, &expected, &unordered);
+///
+/// assert_eq!(diff.len(), 1);
+/// assert_eq!(diff[0].path, "posts[id=2].title");
+/// ```
+#[must_use]
+pub fn structural_diff(
+    stored: &Value,
+    expected: &Value,
+    unordered: &UnorderedArrays,
+) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    diff_at("", stored, expected, unordered, &mut differences);
+    differences
+}
+
+/// Whether two values are equal, with numbers compared by value
+///
+/// Arrays listed in `unordered` are not special-cased here: this decides
+/// whether elements of a multiset match.
+#[must_use]
+pub fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => number_text(x) == number_text(y),
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(a, b)| values_equal(a, b))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(key, a)| y.get(key).is_some_and(|b| values_equal(a, b)))
+        }
+        _ => a == b,
+    }
+}
+
+/// Text of a number that is the same for equal values
+///
+/// Integers keep every digit and integral floats are written out in full, so
+/// `1` and `1.0` give `1` while ids above 2^53 stay distinct.
+fn number_text(n: &Number) -> String {
+    match (n.as_i64(), n.as_u64(), n.as_f64()) {
+        (Some(i), _, _) => i.to_string(),
+        (None, Some(u), _) => u.to_string(),
+        // `-0.0` is zero
+        (None, None, Some(0.0)) => "0".to_string(),
+        (None, None, Some(f)) if f.fract() == 0.0 => format!("{f:.0}"),
+        _ => n.to_string(),
+    }
+}
+
+/// Text identifying an element key, with numbers written as by
+/// [`number_text`]
+fn key_text(key: &Value) -> String {
+    match key {
+        Value::Number(n) => number_text(n),
+        other => other.to_string(),
+    }
+}
+
+fn diff_at(
+    path: &str,
+    stored: &Value,
+    expected: &Value,
+    unordered: &UnorderedArrays,
+    out: &mut Vec<Difference>,
+) {
+    match (stored, expected) {
+        (Value::Object(stored_obj), Value::Object(expected_obj)) => {
+            for (key, stored_val) in stored_obj {
+                let child = child_path(path, key);
+                match expected_obj.get(key) {
+                    Some(expected_val) => diff_at(&child, stored_val, expected_val, unordered, out),
+                    None => out.push(Difference::new(&child, Some(stored_val), None)),
+                }
+            }
+            for (key, expected_val) in expected_obj {
+                if !stored_obj.contains_key(key) {
+                    out.push(Difference::new(
+                        &child_path(path, key),
+                        None,
+                        Some(expected_val),
+                    ));
+                }
+            }
+        }
+        (Value::Array(stored_arr), Value::Array(expected_arr)) => match unordered.get(path) {
+            Some(Some(match_key)) => {
+                diff_keyed(path, stored_arr, expected_arr, match_key, unordered, out);
+            }
+            Some(None) => diff_multiset(path, stored_arr, expected_arr, out),
+            None => diff_positional(path, stored_arr, expected_arr, unordered, out),
+        },
+        _ => {
+            if !values_equal(stored, expected) {
+                out.push(Difference::new(path, Some(stored), Some(expected)));
+            }
+        }
+    }
+}
+
+/// Compare arrays element by element, reporting extra elements on either side
+fn diff_positional(
+    path: &str,
+    stored: &[Value],
+    expected: &[Value],
+    unordered: &UnorderedArrays,
+    out: &mut Vec<Difference>,
+) {
+    for idx in 0..stored.len().max(expected.len()) {
+        let child = format!("{path}[{idx}]");
+        match (stored.get(idx), expected.get(idx)) {
+            (Some(s), Some(e)) => diff_at(&child, s, e, unordered, out),
+            (s, e) => out.push(Difference::new(&child, s, e)),
+        }
+    }
+}
+
+/// Pair elements by `match_key` and compare the pairs; elements without a
+/// key are compared as a multiset
+fn diff_keyed(
+    path: &str,
+    stored: &[Value],
+    expected: &[Value],
+    match_key: &str,
+    unordered: &UnorderedArrays,
+    out: &mut Vec<Difference>,
+) {
+    let key_of = |elem: &Value| elem.get(match_key).map(key_text);
+
+    let mut unkeyed_stored = Vec::new();
+    let mut stored_by_key: BTreeMap<String, &Value> = BTreeMap::new();
+    for elem in stored {
+        match key_of(elem) {
+            // A duplicate key is left to the multiset comparison
+            Some(key) if !stored_by_key.contains_key(&key) => {
+                stored_by_key.insert(key, elem);
+            }
+            _ => unkeyed_stored.push(elem.clone()),
+        }
+    }
+
+    let mut unkeyed_expected = Vec::new();
+    for elem in expected {
+        match key_of(elem).and_then(|key| stored_by_key.remove(&key).map(|s| (key, s))) {
+            Some((key, stored_elem)) => {
+                let child = format!("{path}[{match_key}={key}]");
+                diff_at(&child, stored_elem, elem, unordered, out);
+            }
+            None => unkeyed_expected.push(elem.clone()),
+        }
+    }
+
+    unkeyed_stored.extend(stored_by_key.into_values().cloned());
+    diff_multiset(path, &unkeyed_stored, &unkeyed_expected, out);
+}
+
+/// Compare arrays as multisets, reporting unmatched elements at `path[*]`
+fn diff_multiset(path: &str, stored: &[Value], expected: &[Value], out: &mut Vec<Difference>) {
+    let mut unmatched_expected: Vec<&Value> = expected.iter().collect();
+    let mut unmatched_stored = Vec::new();
+
+    for elem in stored {
+        match unmatched_expected
+            .iter()
+            .position(|candidate| values_equal(elem, candidate))
+        {
+            Some(pos) => {
+                unmatched_expected.remove(pos);
+            }
+            None => unmatched_stored.push(elem),
+        }
+    }
+
+    let child = format!("{path}[*]");
+    for elem in unmatched_stored {
+        out.push(Difference::new(&child, Some(elem), None));
+    }
+    for elem in unmatched_expected {
+        out.push(Difference::new(&child, None, Some(elem)));
+    }
+}
+
+/// Bring a stored document in line with the expected one, changing as little as possible
+///
+/// The result has no `structural_diff` with `expected`, but keeps every
+/// stored value that already matches: unchanged subtrees and equal numbers
+/// spelled differently (`1.0` for `1`) stay as stored, and elements of unordered arrays
+/// keep their stored position (unmatched stored elements are dropped and
+/// missing ones appended in expected order).
+///
+/// # Examples
+/// ```
+/// use jsonb_ivm::diff::{reconcile, UnorderedArrays};
+/// use serde_json::json;
+///
+/// let stored = json!({"posts": [{"id": 2, "title": "Old"}, {"id": 1}, {"id": 9}]});
+/// let expected = json!({"posts": [{"id": 1}, {"id": 2, "title": "New"}, {"id": 3}]});
+///
+/// let unordered = UnorderedArrays::from([("posts".to_string(), Some("id".to_string()))]);
+/// assert_eq!(
+///     reconcile(&stored, &expected, &unordered),
+///     json!({"posts": [{"id": 2, "title": "New"}, {"id": 1}, {"id": 3}]})
+/// );
+/// ```
+#[must_use]
+pub fn reconcile(stored: &Value, expected: &Value, unordered: &UnorderedArrays) -> Value {
+    reconcile_at("", stored, expected, unordered)
+}
+
+fn reconcile_at(
+    path: &str,
+    stored: &Value,
+    expected: &Value,
+    unordered: &UnorderedArrays,
+) -> Value {
+    match (stored, expected) {
+        (Value::Object(stored_obj), Value::Object(expected_obj)) => Value::Object(
+            expected_obj
+                .iter()
+                .map(|(key, expected_val)| {
+                    let value = stored_obj.get(key).map_or_else(
+                        || expected_val.clone(),
+                        |stored_val| {
+                            reconcile_at(
+                                &child_path(path, key),
+                                stored_val,
+                                expected_val,
+                                unordered,
+                            )
+                        },
+                    );
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        (Value::Array(stored_arr), Value::Array(expected_arr)) => {
+            Value::Array(unordered.get(path).map_or_else(
+                || {
+                    expected_arr
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, expected_elem)| {
+                            stored_arr.get(idx).map_or_else(
+                                || expected_elem.clone(),
+                                |stored_elem| {
+                                    reconcile_at(
+                                        &format!("{path}[{idx}]"),
+                                        stored_elem,
+                                        expected_elem,
+                                        unordered,
+                                    )
+                                },
+                            )
+                        })
+                        .collect()
+                },
+                |match_key| {
+                    reconcile_unordered(
+                        path,
+                        stored_arr,
+                        expected_arr,
+                        match_key.as_deref(),
+                        unordered,
+                    )
+                },
+            ))
+        }
+        _ if values_equal(stored, expected) => stored.clone(),
+        _ => expected.clone(),
+    }
+}
+
+/// Keep stored elements that have a counterpart (paired by `match_key`, or
+/// equal when there is none), in stored order, then append the others
+fn reconcile_unordered(
+    path: &str,
+    stored: &[Value],
+    expected: &[Value],
+    match_key: Option<&str>,
+    unordered: &UnorderedArrays,
+) -> Vec<Value> {
+    let mut unmatched: Vec<Option<&Value>> = expected.iter().map(Some).collect();
+    let mut result = Vec::with_capacity(expected.len());
+
+    for stored_elem in stored {
+        let stored_key = match_key.and_then(|k| stored_elem.get(k));
+        let counterpart = unmatched.iter().position(|candidate| {
+            candidate.is_some_and(|candidate| match (stored_key, match_key) {
+                (Some(key), Some(k)) => candidate.get(k).is_some_and(|c| values_equal(c, key)),
+                _ => values_equal(stored_elem, candidate),
+            })
+        });
+        if let Some(pos) = counterpart {
+            if let Some(expected_elem) = unmatched[pos].take() {
+                let child = match (stored_key, match_key) {
+                    (Some(key), Some(k)) => format!("{path}[{k}={key}]"),
+                    _ => format!("{path}[*]"),
+                };
+                result.push(reconcile_at(&child, stored_elem, expected_elem, unordered));
+            }
+        }
+    }
+
+    result.extend(unmatched.into_iter().flatten().cloned());
+    result
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paths(diff: &[Difference]) -> Vec<&str> {
+        diff.iter().map(|d| d.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_identical_documents() {
+        let doc = json!({"id": 1, "tags": ["a", "b"], "author": {"name": "Alice"}});
+        assert!(structural_diff(&doc, &doc, &UnorderedArrays::new()).is_empty());
+    }
+
+    #[test]
+    fn test_object_differences() {
+        let stored = json!({"id": 1, "name": "Old", "stale": true, "author": {"name": "A"}});
+        let expected = json!({"id": 1, "name": "New", "author": {"name": "B"}, "extra": 2});
+
+        let diff = structural_diff(&stored, &expected, &UnorderedArrays::new());
+        assert_eq!(paths(&diff), vec!["author.name", "name", "stale", "extra"]);
+        assert_eq!(diff[2].expected, None);
+        assert_eq!(diff[3].stored, None);
+        assert_eq!(
+            diff[1].to_value(),
+            json!({"path": "name", "stored": "Old", "expected": "New"})
+        );
+    }
+
+    #[test]
+    fn test_numbers_compare_by_value() {
+        assert!(values_equal(&json!(1), &json!(1.0)));
+        assert!(structural_diff(
+            &json!({"n": 1}),
+            &json!({"n": 1.0}),
+            &UnorderedArrays::new()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_numbers_compare_exactly() {
+        // 2^53 + 1 has no exact `f64`
+        assert!(!values_equal(
+            &json!(9_007_199_254_740_993_u64),
+            &json!(9_007_199_254_740_992_u64)
+        ));
+        assert!(!values_equal(
+            &json!(9_007_199_254_740_993_u64),
+            &json!(9_007_199_254_740_992.0)
+        ));
+        assert!(!values_equal(&json!(u64::MAX), &json!(u64::MAX - 1)));
+        assert!(values_equal(&json!(-0.0), &json!(0)));
+        assert!(values_equal(
+            &json!(1e19),
+            &json!(10_000_000_000_000_000_000_u64)
+        ));
+    }
+
+    #[test]
+    fn test_keyed_numbers_pair_by_value() {
+        let unordered = UnorderedArrays::from([("posts".to_string(), Some("id".to_string()))]);
+        assert!(structural_diff(
+            &json!({"posts": [{"id": 1, "n": 2}]}),
+            &json!({"posts": [{"id": 1.0, "n": 2}]}),
+            &unordered
+        )
+        .is_empty());
+
+        let diff = structural_diff(
+            &json!({"posts": [{"id": 9_007_199_254_740_993_u64, "n": 1}]}),
+            &json!({"posts": [{"id": 9_007_199_254_740_992_u64, "n": 1}]}),
+            &unordered,
+        );
+        assert_eq!(paths(&diff), vec!["posts[*]", "posts[*]"]);
+    }
+
+    #[test]
+    fn test_positional_arrays() {
+        let diff = structural_diff(
+            &json!({"tags": ["a", "b"]}),
+            &json!({"tags": ["b", "a", "c"]}),
+            &UnorderedArrays::new(),
+        );
+        assert_eq!(paths(&diff), vec!["tags[0]", "tags[1]", "tags[2]"]);
+    }
+
+    #[test]
+    fn test_unordered_multiset() {
+        let unordered = UnorderedArrays::from([("tags".to_string(), None)]);
+        assert!(structural_diff(
+            &json!({"tags": ["a", "b", "a"]}),
+            &json!({"tags": ["a", "a", "b"]}),
+            &unordered
+        )
+        .is_empty());
+
+        let diff = structural_diff(
+            &json!({"tags": ["a", "b"]}),
+            &json!({"tags": ["b", "c"]}),
+            &unordered,
+        );
+        assert_eq!(
+            diff,
+            vec![
+                Difference::new("tags[*]", Some(&json!("a")), None),
+                Difference::new("tags[*]", None, Some(&json!("c"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unordered_keyed() {
+        let unordered = UnorderedArrays::from([("posts".to_string(), Some("id".to_string()))]);
+        let stored = json!({"posts": [{"id": 3}, {"id": 1, "title": "A"}, {"id": 9}]});
+        let expected = json!({"posts": [{"id": 1, "title": "B"}, {"id": 3}, {"id": 4}]});
+
+        let diff = structural_diff(&stored, &expected, &unordered);
+        assert_eq!(
+            diff,
+            vec![
+                Difference::new("posts[id=1].title", Some(&json!("A")), Some(&json!("B"))),
+                Difference::new("posts[*]", Some(&json!({"id": 9})), None),
+                Difference::new("posts[*]", None, Some(&json!({"id": 4}))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_change() {
+        let diff = structural_diff(
+            &json!({"author": null}),
+            &json!({"author": {"id": 1}}),
+            &UnorderedArrays::new(),
+        );
+        assert_eq!(
+            diff,
+            vec![Difference::new(
+                "author",
+                Some(&json!(null)),
+                Some(&json!({"id": 1}))
+            )]
+        );
+    }
+
+    #[test]
+    fn test_reconcile_matches_expected() {
+        let stored = json!({"id": 1, "name": "Old", "stale": true, "tags": ["a", "b", "c"]});
+        let expected = json!({"id": 1, "name": "New", "tags": ["a", "x"], "extra": {"n": 2}});
+
+        let repaired = reconcile(&stored, &expected, &UnorderedArrays::new());
+        assert_eq!(repaired, expected);
+    }
+
+    #[test]
+    fn test_reconcile_keeps_matching_values() {
+        let stored = json!({"n": 1.0, "m": 2});
+        let repaired = reconcile(&stored, &json!({"n": 1, "m": 3}), &UnorderedArrays::new());
+        assert_eq!(repaired.to_string(), r#"{"m":3,"n":1.0}"#);
+    }
+
+    #[test]
+    fn test_reconcile_replaces_large_integers() {
+        let stored = json!({"id": 9_007_199_254_740_993_u64, "n": 1.0});
+        let expected = json!({"id": 9_007_199_254_740_992_u64, "n": 1});
+        let repaired = reconcile(&stored, &expected, &UnorderedArrays::new());
+        assert_eq!(repaired.to_string(), r#"{"id":9007199254740992,"n":1.0}"#);
+    }
+
+    #[test]
+    fn test_reconcile_unordered_keeps_stored_order() {
+        let unordered = UnorderedArrays::from([
+            ("posts".to_string(), Some("id".to_string())),
+            ("tags".to_string(), None),
+        ]);
+        let stored = json!({"tags": ["b", "z", "a"], "posts": [{"id": 3}, {"id": 1, "t": "A"}]});
+        let expected = json!({"tags": ["a", "b", "c"], "posts": [{"id": 1, "t": "B"}, {"id": 3}]});
+
+        let repaired = reconcile(&stored, &expected, &unordered);
+        assert_eq!(
+            repaired,
+            json!({"tags": ["b", "a", "c"], "posts": [{"id": 3}, {"id": 1, "t": "B"}]})
+        );
+        assert!(structural_diff(&repaired, &expected, &unordered).is_empty());
+    }
+}
@@ -28,11 +28,30 @@ pub mod pg_test {
 }
 
 // Module declarations (Phase 0: Modularization)
+mod aggregates;
 mod array_ops;
+mod cascade;
+mod counters;
+mod decoding;
 mod depth;
+pub mod diff; // Public for doc tests
 mod merge;
+pub mod missing; // Public for doc tests
 pub mod path; // Public for doc tests
+mod queue;
+mod rebuild;
+pub mod registry; // Public for doc tests
 mod search;
+pub mod sort; // Public for doc tests
+mod triggers;
+mod verify;
+mod worker;
+
+/// Library load hook: registers the GUCs and the optional queue worker
+#[pg_guard]
+pub extern "C-unwind" fn _PG_init() {
+    worker::init();
+}
 
 // Property-based testing infrastructure (Phase 4)
 #[cfg(test)]
@@ -40,10 +59,13 @@ mod property_tests;
 
 // Re-exports for public API (maintains backward compatibility)
 pub use array_ops::*;
+pub use counters::*;
 pub use depth::validate_depth;
 pub use depth::MAX_JSONB_DEPTH;
 pub use merge::*;
+pub use missing::{find_array, find_array_mut, resolve_array, OnMissing};
 pub use path::*;
+pub use sort::{parse_order_spec, OrderSpec, SortTerm, SortType};
 
 /// Extract ID value from JSONB document
 ///
@@ -117,6 +139,8 @@ fn jsonb_extract_id(data: JsonB, key: default!(&str, "'id'")) -> Option<String>
 /// * `array_path` - Path to array field (e.g., 'posts')
 /// * `id_key` - Key to match on (e.g., 'id')
 /// * `id_value` - Value to search for
+/// * `on_missing` - `'error'` raises when the array is missing; `'create'`
+///   (default) and `'ignore'` return false
 ///
 /// # Returns
 ///
@@ -172,12 +196,17 @@ fn jsonb_extract_id(data: JsonB, key: default!(&str, "'id'")) -> Option<String>
 /// ```
 #[allow(clippy::needless_pass_by_value)]
 #[pg_extern(immutable, parallel_safe, strict)]
-fn jsonb_array_contains_id(data: JsonB, array_path: &str, id_key: &str, id_value: JsonB) -> bool {
-    let Some(obj) = data.0.as_object() else {
-        return false;
-    };
+fn jsonb_array_contains_id(
+    data: JsonB,
+    array_path: &str,
+    id_key: &str,
+    id_value: JsonB,
+    on_missing: default!(&str, "'create'"),
+) -> bool {
+    let policy = OnMissing::parse(on_missing).unwrap_or_else(|e| error!("{}", e));
 
-    let Some(array) = obj.get(array_path).and_then(|v| v.as_array()) else {
+    let Some(array) = find_array(&data.0, array_path, policy).unwrap_or_else(|e| error!("{}", e))
+    else {
         return false;
     };
 
@@ -210,6 +239,7 @@ fn find_element_by_match(array: &[Value], match_key: &str, match_value: &Value)
 /// * `match_value` - Value to match
 /// * `update_path` - NESTED PATH to the field to update (e.g., "profile.name")
 /// * `update_value` - New value for the field
+/// * `on_missing` - `'create'` (default), `'ignore'` or `'error'` when the array is missing
 ///
 /// # Returns
 /// Updated JSONB document
@@ -234,25 +264,22 @@ fn jsonb_ivm_array_update_where_path(
     match_value: JsonB,
     update_path: &str,
     update_value: JsonB,
+    on_missing: default!(&str, "'create'"),
 ) -> JsonB {
     let mut target_value: Value = target.0;
 
+    let policy = OnMissing::parse(on_missing).unwrap_or_else(|e| error!("{}", e));
+
     // Parse the update path
     let update_segments = parse_path(update_path)
         .unwrap_or_else(|e| error!("Invalid update path '{}': {}", update_path, e));
 
     // Navigate to array location (single level for now)
-    let Some(array) = target_value.get_mut(array_key) else {
-        error!("Array path '{}' does not exist in document", array_key);
-    };
-
-    let Some(array_items) = array.as_array_mut() else {
-        error!(
-            "Path '{}' does not point to an array, found: {}",
-            array_key,
-            value_type_name(array)
-        );
-    };
+    let Some(array_items) =
+        find_array_mut(&mut target_value, array_key, policy).unwrap_or_else(|e| error!("{}", e))
+    else {
+        return JsonB(target_value);
+    }; // Missing array ignored, return unchanged
 
     // Extract match value
     let match_val = match_value.0;
@@ -353,9 +380,8 @@ fn jsonb_ivm_set_path(target: JsonB, path: &str, value: JsonB) -> JsonB {
     JsonB(target_value)
 }
 
-/// Helper function to get human-readable type name for error messages
-#[allow(dead_code)]
-const fn value_type_name(value: &Value) -> &'static str {
+/// Human-readable JSON type name for error messages
+pub(crate) const fn value_type_name(value: &Value) -> &'static str {
     match value {
         Value::Null => "null",
         Value::Bool(_) => "boolean",
@@ -10,7 +10,9 @@ use pgrx::JsonB;
 use serde_json::Value;
 
 // Import from other modules
+use crate::missing::{find_array_mut, OnMissing};
 use crate::search::find_by_int_id_optimized;
+use crate::value_type_name;
 
 /// Merge top-level keys from source JSONB into target JSONB
 ///
@@ -81,6 +83,8 @@ pub fn jsonb_merge_shallow(target: Option<JsonB>, source: Option<JsonB>) -> Opti
 /// * `target` - Base JSONB document
 /// * `source` - JSONB object to merge
 /// * `path` - Path where to merge (empty array = root level)
+/// * `on_missing` - `'create'` (default) creates missing objects along the path,
+///   `'ignore'` returns the document unchanged, `'error'` raises
 ///
 /// # Returns
 /// Updated JSONB with source merged at path
@@ -97,10 +101,17 @@ pub fn jsonb_merge_shallow(target: Option<JsonB>, source: Option<JsonB>) -> Opti
 /// ```
 #[allow(clippy::needless_pass_by_value)]
 #[pg_extern(immutable, parallel_safe, strict)]
-pub fn jsonb_merge_at_path(target: JsonB, source: JsonB, path: pgrx::Array<&str>) -> JsonB {
+pub fn jsonb_merge_at_path(
+    target: JsonB,
+    source: JsonB,
+    path: pgrx::Array<&str>,
+    on_missing: default!(&str, "'create'"),
+) -> JsonB {
     // No Option unwrapping needed - strict guarantees non-NULL
     let mut target_value: Value = target.0;
 
+    let policy = OnMissing::parse(on_missing).unwrap_or_else(|e| error!("{}", e));
+
     // Validate source is an object
     let Some(source_obj) = source.0.as_object() else {
         error!(
@@ -129,6 +140,20 @@ pub fn jsonb_merge_at_path(target: JsonB, source: JsonB, path: pgrx::Array<&str>
         return JsonB(target_value);
     }
 
+    // Apply the on_missing policy before any intermediate object is created
+    let existing = path_vec
+        .iter()
+        .try_fold(&target_value, |current, key| current.get(key));
+    match policy {
+        OnMissing::Error if existing.is_none() => {
+            error!("Path {:?} does not exist in document", path_vec);
+        }
+        OnMissing::Ignore if !existing.is_some_and(Value::is_object) => {
+            return JsonB(target_value);
+        }
+        _ => {}
+    }
+
     // Navigate to parent of target path
     let mut current = &mut target_value;
     for (i, key) in path_vec.iter().enumerate() {
@@ -231,6 +256,7 @@ pub fn jsonb_smart_patch_scalar(target: JsonB, source: JsonB) -> JsonB {
 /// * `target` - Current JSONB document
 /// * `source` - JSONB object to merge
 /// * `path` - Path to nested object (e.g., ARRAY['user', 'company'])
+/// * `on_missing` - `'create'` (default), `'ignore'` or `'error'` when the path is missing
 ///
 /// # Returns
 ///
@@ -254,8 +280,13 @@ pub fn jsonb_smart_patch_scalar(target: JsonB, source: JsonB) -> JsonB {
 /// ```
 #[pg_extern(immutable, parallel_safe, strict)]
 #[must_use]
-pub fn jsonb_smart_patch_nested(target: JsonB, source: JsonB, path: pgrx::Array<&str>) -> JsonB {
-    jsonb_merge_at_path(target, source, path)
+pub fn jsonb_smart_patch_nested(
+    target: JsonB,
+    source: JsonB,
+    path: pgrx::Array<&str>,
+    on_missing: default!(&str, "'create'"),
+) -> JsonB {
+    jsonb_merge_at_path(target, source, path, on_missing)
 }
 
 /// Smart JSONB patch for array element updates
@@ -270,6 +301,8 @@ pub fn jsonb_smart_patch_nested(target: JsonB, source: JsonB, path: pgrx::Array<
 /// * `array_path` - Path to the array field (e.g., `"posts"`)
 /// * `match_key` - Key to match on (e.g., `"id"`)
 /// * `match_value` - Value to match (e.g., `'42'::jsonb`)
+/// * `mode` - `merge` (default), `deep-merge`, `replace`, or `merge-patch` (RFC 7396)
+/// * `on_missing` - `'create'` (default), `'ignore'` or `'error'` when the array is missing
 ///
 /// # Returns
 ///
@@ -307,24 +340,15 @@ pub fn jsonb_smart_patch_array(
     array_path: &str,
     match_key: &str,
     match_value: JsonB,
+    mode: default!(&str, "'merge'"),
+    on_missing: default!(&str, "'create'"),
 ) -> JsonB {
     // This will be moved to array_ops module, but for now we need to implement it here
     // since it depends on jsonb_array_update_where which we'll move later
     let mut target_value: Value = target.0;
 
-    // Navigate to array location (single level for now)
-    let Some(array) = target_value.get_mut(array_path) else {
-        error!("Path '{}' does not exist in document", array_path);
-    };
-
-    // Validate it's an array
-    let Some(array_items) = array.as_array_mut() else {
-        error!(
-            "Path '{}' does not point to an array, found: {}",
-            array_path,
-            value_type_name(array)
-        );
-    };
+    let update_mode = UpdateMode::parse(mode).unwrap_or_else(|e| error!("{}", e));
+    let policy = OnMissing::parse(on_missing).unwrap_or_else(|e| error!("{}", e));
 
     // Extract match value as serde_json::Value
     let match_val = match_value.0;
@@ -337,16 +361,19 @@ pub fn jsonb_smart_patch_array(
         );
     };
 
+    // Navigate to array location (single level for now)
+    let Some(array_items) =
+        find_array_mut(&mut target_value, array_path, policy).unwrap_or_else(|e| error!("{}", e))
+    else {
+        return JsonB(target_value);
+    }; // Missing array ignored, return unchanged
+
     // Find matching element using optimized search
     let match_idx = find_element_by_match(array_items, match_key, &match_val);
 
     // Apply update if match found
     if let Some(idx) = match_idx {
-        if let Some(elem_obj) = array_items[idx].as_object_mut() {
-            for (key, value) in updates_obj {
-                elem_obj.insert(key.clone(), value.clone());
-            }
-        }
+        update_mode.apply(&mut array_items[idx], updates_obj);
     }
 
     JsonB(target_value)
@@ -427,15 +454,92 @@ pub fn deep_merge_recursive(target: Value, source: Value) -> Value {
     }
 }
 
-// Helper function - will be moved to a common utils module later
-const fn value_type_name(value: &Value) -> &'static str {
-    match value {
-        Value::Null => "null",
-        Value::Bool(_) => "boolean",
-        Value::Number(_) => "number",
-        Value::String(_) => "string",
-        Value::Array(_) => "array",
-        Value::Object(_) => "object",
+/// How updates are applied to a matched array element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateMode {
+    /// Shallow merge: top-level keys of the update overwrite the element's
+    Merge,
+    /// Recursive merge of nested objects (see `deep_merge_recursive`)
+    DeepMerge,
+    /// Replace the element wholesale, dropping keys absent from the update
+    Replace,
+    /// RFC 7396 JSON Merge Patch: recursive merge where `null` deletes a key
+    MergePatch,
+}
+
+impl UpdateMode {
+    /// Parse a `mode` argument: `merge`, `deep-merge`, `replace` or `merge-patch`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for any other mode name.
+    pub fn parse(mode: &str) -> Result<Self, String> {
+        match mode.to_ascii_lowercase().replace('_', "-").as_str() {
+            "merge" => Ok(Self::Merge),
+            "deep-merge" => Ok(Self::DeepMerge),
+            "replace" => Ok(Self::Replace),
+            "merge-patch" => Ok(Self::MergePatch),
+            _ => Err(format!(
+                "Invalid update mode '{mode}': expected merge, deep-merge, replace or merge-patch"
+            )),
+        }
+    }
+
+    /// Apply `updates` (a JSON object) to an array element
+    ///
+    /// In `Merge` mode, elements that are not objects are left unchanged.
+    pub fn apply(self, element: &mut Value, updates: &serde_json::Map<String, Value>) {
+        match self {
+            Self::Merge => {
+                if let Some(elem_obj) = element.as_object_mut() {
+                    for (key, value) in updates {
+                        elem_obj.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            Self::DeepMerge => {
+                *element =
+                    deep_merge_recursive(std::mem::take(element), Value::Object(updates.clone()));
+            }
+            Self::Replace => *element = Value::Object(updates.clone()),
+            Self::MergePatch => merge_patch(element, Value::Object(updates.clone())),
+        }
+    }
+}
+
+/// Apply an RFC 7396 JSON Merge Patch in place
+///
+/// Objects are merged recursively, `null` members delete the key, and any
+/// other patch value replaces the target.
+///
+/// # Examples
+/// ```
+/// use jsonb_ivm::merge_patch;
+/// use serde_json::json;
+///
+/// let mut doc = json!({"a": 1, "b": {"c": 2, "d": 3}});
+/// merge_patch(&mut doc, json!({"a": null, "b": {"c": null, "e": 4}}));
+/// assert_eq!(doc, json!({"b": {"d": 3, "e": 4}}));
+/// ```
+pub fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch_obj) = patch else {
+        *target = patch;
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(serde_json::Map::new());
+    }
+    let Some(target_obj) = target.as_object_mut() else {
+        unreachable!("target was just made an object");
+    };
+
+    for (key, value) in patch_obj {
+        if value.is_null() {
+            target_obj.remove(&key);
+        } else {
+            merge_patch(target_obj.entry(key).or_insert(Value::Null), value);
+        }
     }
 }
 
@@ -0,0 +1,202 @@
+// jsonb_ivm - Missing Path Policy Module
+//
+// Shared `on_missing` handling for functions that operate on an array or
+// object at a path that may be absent from the document.
+
+use serde_json::Value;
+
+use crate::value_type_name;
+
+/// What to do when the array (or object) a function operates on is missing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnMissing {
+    /// Raise an error
+    Error,
+    /// Return the document unchanged
+    Ignore,
+    /// Treat the missing array as empty (default): functions that add elements
+    /// create it, the others have nothing to do
+    Create,
+}
+
+impl OnMissing {
+    /// Parse an `on_missing` argument: `error`, `ignore` or `create`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for any other policy name.
+    pub fn parse(policy: &str) -> Result<Self, String> {
+        match policy.to_ascii_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "ignore" => Ok(Self::Ignore),
+            "create" => Ok(Self::Create),
+            _ => Err(format!(
+                "Invalid on_missing policy '{policy}': expected error, ignore or create"
+            )),
+        }
+    }
+}
+
+/// Get or create the array at `array_path`, for functions that add elements
+///
+/// Under `Create`, a missing array is added to the document as `[]`. Other
+/// cases behave like `find_array_mut`; `Create` never overwrites a value that
+/// is not an array.
+///
+/// # Errors
+///
+/// Returns the errors of `find_array_mut`, or an error if `Create` needs to
+/// add the array to a target that is not an object.
+///
+/// # Examples
+/// ```
+/// use jsonb_ivm::{resolve_array, OnMissing};
+/// use serde_json::json;
+///
+/// let mut doc = json!({"id": 1});
+/// assert!(resolve_array(&mut doc, "posts", OnMissing::Ignore).unwrap().is_none());
+///
+/// resolve_array(&mut doc, "posts", OnMissing::Create).unwrap();
+/// assert_eq!(doc, json!({"id": 1, "posts": []}));
+/// ```
+pub fn resolve_array<'a>(
+    target: &'a mut Value,
+    array_path: &str,
+    on_missing: OnMissing,
+) -> Result<Option<&'a mut Vec<Value>>, String> {
+    if on_missing == OnMissing::Create && target.get(array_path).is_none() {
+        let target_type = value_type_name(target);
+        let Some(target_obj) = target.as_object_mut() else {
+            return Err(format!("target must be a JSONB object, got: {target_type}"));
+        };
+        target_obj.insert(array_path.to_string(), Value::Array(vec![]));
+    }
+
+    find_array_mut(target, array_path, on_missing)
+}
+
+/// Find the array at `array_path`, for functions that work on existing elements
+///
+/// Returns `Ok(None)` when there is nothing to do: the array is missing (a
+/// missing array has no elements, so `Create` has nothing to create), or the
+/// path holds another value under `Ignore`.
+///
+/// # Errors
+///
+/// Returns an error if the array is missing under `Error`, or if the path
+/// holds a value that is not an array under `Error` or `Create`.
+pub fn find_array_mut<'a>(
+    target: &'a mut Value,
+    array_path: &str,
+    on_missing: OnMissing,
+) -> Result<Option<&'a mut Vec<Value>>, String> {
+    match target.get_mut(array_path) {
+        Some(Value::Array(array_items)) => Ok(Some(array_items)),
+        Some(_) if on_missing == OnMissing::Ignore => Ok(None),
+        Some(other) => Err(format!(
+            "Path '{array_path}' does not point to an array, found: {}",
+            value_type_name(other)
+        )),
+        None if on_missing == OnMissing::Error => {
+            Err(format!("Path '{array_path}' does not exist in document"))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Read-only counterpart of `find_array_mut` for predicates
+///
+/// # Errors
+///
+/// Same as `find_array_mut`.
+pub fn find_array<'a>(
+    target: &'a Value,
+    array_path: &str,
+    on_missing: OnMissing,
+) -> Result<Option<&'a Vec<Value>>, String> {
+    match target.get(array_path) {
+        Some(Value::Array(array_items)) => Ok(Some(array_items)),
+        Some(_) if on_missing == OnMissing::Ignore => Ok(None),
+        Some(other) => Err(format!(
+            "Path '{array_path}' does not point to an array, found: {}",
+            value_type_name(other)
+        )),
+        None if on_missing == OnMissing::Error => {
+            Err(format!("Path '{array_path}' does not exist in document"))
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!(OnMissing::parse("create"), Ok(OnMissing::Create));
+        assert_eq!(OnMissing::parse("IGNORE"), Ok(OnMissing::Ignore));
+        assert_eq!(OnMissing::parse("error"), Ok(OnMissing::Error));
+        assert!(OnMissing::parse("skip").is_err());
+    }
+
+    #[test]
+    fn test_resolve_missing_array() {
+        let mut doc = json!({"id": 1});
+        assert!(resolve_array(&mut doc, "posts", OnMissing::Error).is_err());
+        assert_eq!(
+            resolve_array(&mut doc, "posts", OnMissing::Ignore),
+            Ok(None)
+        );
+        assert_eq!(doc, json!({"id": 1}));
+
+        let created = resolve_array(&mut doc, "posts", OnMissing::Create).unwrap();
+        assert_eq!(created.map(|items| items.len()), Some(0));
+        assert_eq!(doc, json!({"id": 1, "posts": []}));
+    }
+
+    #[test]
+    fn test_resolve_non_array() {
+        let mut doc = json!({"posts": {"id": 1}});
+        assert!(resolve_array(&mut doc, "posts", OnMissing::Error).is_err());
+        assert!(resolve_array(&mut doc, "posts", OnMissing::Create).is_err());
+        assert_eq!(
+            resolve_array(&mut doc, "posts", OnMissing::Ignore),
+            Ok(None)
+        );
+
+        let mut scalar = json!(42);
+        assert!(resolve_array(&mut scalar, "posts", OnMissing::Create).is_err());
+        assert_eq!(
+            resolve_array(&mut scalar, "posts", OnMissing::Ignore),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_find_array_mut_never_creates() {
+        let mut doc = json!({"id": 1});
+        assert_eq!(
+            find_array_mut(&mut doc, "posts", OnMissing::Create),
+            Ok(None)
+        );
+        assert_eq!(doc, json!({"id": 1}));
+        assert!(find_array_mut(&mut doc, "posts", OnMissing::Error).is_err());
+    }
+
+    #[test]
+    fn test_find_array() {
+        let doc = json!({"posts": [{"id": 1}], "meta": {}});
+        assert_eq!(
+            find_array(&doc, "posts", OnMissing::Error)
+                .unwrap()
+                .map(Vec::len),
+            Some(1)
+        );
+        assert_eq!(find_array(&doc, "other", OnMissing::Create), Ok(None));
+        assert!(find_array(&doc, "other", OnMissing::Error).is_err());
+        assert!(find_array(&doc, "meta", OnMissing::Create).is_err());
+        assert_eq!(find_array(&doc, "meta", OnMissing::Ignore), Ok(None));
+    }
+}
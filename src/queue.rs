@@ -0,0 +1,328 @@
+// jsonb_ivm - Deferred Change Queue Module
+//
+// Deferred cascades: triggers enqueue source row changes, and a flush applies
+// them later, coalesced, so hot rows cost one write per projection row.
+
+use pgrx::prelude::*;
+use pgrx::JsonB;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::cascade::propagate;
+
+extension_sql!(
+    r"
+-- Source row changes waiting for jsonb_ivm_flush (deferred cascade triggers)
+CREATE TABLE jsonb_ivm.change_queue (
+    id bigserial PRIMARY KEY,
+    source_table regclass NOT NULL,
+    row_key text NOT NULL,
+    old_row jsonb,
+    new_row jsonb,
+    enqueued_at timestamptz NOT NULL DEFAULT clock_timestamp(),
+    CHECK (old_row IS NOT NULL OR new_row IS NOT NULL)
+);
+
+CREATE INDEX change_queue_row_idx ON jsonb_ivm.change_queue (source_table, row_key, id);
+
+COMMENT ON TABLE jsonb_ivm.change_queue IS
+    'Pending source row changes (row images before/after), applied by jsonb_ivm_flush';
+",
+    name = "change_queue",
+    requires = ["projection_registry"]
+);
+
+/// A dequeued source row change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedChange {
+    pub source_table: String,
+    pub row_key: String,
+    pub old_row: Option<Value>,
+    pub new_row: Option<Value>,
+}
+
+/// Apply queued source row changes, coalesced per row
+///
+/// Takes the queued changes of up to `max_batch` source rows, oldest first,
+/// merges the changes of each row into one (its first old image and its last
+/// new image) and runs the cascade once per source table. A row inserted then
+/// deleted, or changed back to its original image, is dropped; every
+/// projection row is written once per cascade step, however many of its
+/// embedded rows changed.
+///
+/// # Arguments
+///
+/// * `max_batch` - Maximum number of source rows to apply (default 1000)
+///
+/// # Returns
+///
+/// The number of queued changes consumed; 0 when the queue is empty
+///
+/// # Examples
+///
+/// ```sql
+/// -- Defer the cascade of a hot table
+/// CREATE TRIGGER tv_user_cascade_update AFTER UPDATE ON tv_user
+/// REFERENCING OLD TABLE AS old_rows NEW TABLE AS new_rows
+/// FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger('deferred');
+///
+/// -- Later (e.g., every second from a scheduler)
+/// SELECT jsonb_ivm_flush(5000);
+/// ```
+///
+/// # Notes
+/// - Concurrent flushes (including the background worker) take disjoint rows;
+///   all queued changes of a row are applied together, in order
+/// - Queued changes are consumed in the flushing transaction; a failed flush
+///   leaves them queued
+#[pg_extern]
+pub fn jsonb_ivm_flush(max_batch: default!(i32, 1000)) -> i64 {
+    if max_batch < 1 {
+        error!("max_batch must be at least 1, got {}", max_batch);
+    }
+
+    flush_batch(max_batch).changes
+}
+
+/// Outcome of one `flush_batch`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlushedBatch {
+    /// Source rows taken from the queue
+    pub rows: i64,
+    /// Queued changes consumed
+    pub changes: i64,
+}
+
+/// Dequeue and apply the changes of up to `max_batch` source rows
+pub fn flush_batch(max_batch: i32) -> FlushedBatch {
+    // A row is taken through its oldest queued change: a row whose oldest
+    // change is locked by another flush is skipped whole, so the changes of
+    // a row are never split between concurrent flushes
+    let changes = Spi::connect_mut(|client| {
+        client
+            .update(
+                "WITH heads AS (
+                     SELECT q.source_table, q.row_key
+                     FROM jsonb_ivm.change_queue q
+                     WHERE NOT EXISTS (
+                         SELECT 1 FROM jsonb_ivm.change_queue e
+                         WHERE e.source_table = q.source_table
+                           AND e.row_key = q.row_key
+                           AND e.id < q.id
+                     )
+                     ORDER BY q.id
+                     LIMIT $1
+                     FOR UPDATE SKIP LOCKED
+                 ),
+                 batch AS (
+                     DELETE FROM jsonb_ivm.change_queue c
+                     USING heads h
+                     WHERE c.source_table = h.source_table AND c.row_key = h.row_key
+                     RETURNING c.*
+                 )
+                 SELECT source_table::text, row_key, old_row, new_row
+                 FROM batch
+                 ORDER BY id",
+                None,
+                &[max_batch.into()],
+            )?
+            .map(|row| {
+                Ok(QueuedChange {
+                    source_table: row.get::<String>(1)?.unwrap_or_default(),
+                    row_key: row.get::<String>(2)?.unwrap_or_default(),
+                    old_row: row.get::<JsonB>(3)?.map(|image| image.0),
+                    new_row: row.get::<JsonB>(4)?.map(|image| image.0),
+                })
+            })
+            .collect::<Result<Vec<_>, spi::Error>>()
+    })
+    .unwrap_or_else(|e| error!("{}", e));
+
+    let rows: BTreeSet<(&str, &str)> = changes
+        .iter()
+        .map(|change| (change.source_table.as_str(), change.row_key.as_str()))
+        .collect();
+    let flushed = FlushedBatch {
+        rows: i64::try_from(rows.len()).unwrap_or(i64::MAX),
+        changes: i64::try_from(changes.len()).unwrap_or(i64::MAX),
+    };
+
+    for (source_table, old_rows, new_rows) in coalesce(changes) {
+        propagate(&source_table, &old_rows, &new_rows);
+    }
+
+    flushed
+}
+
+/// Queue the changes of a statement on `source_table` for `jsonb_ivm_flush`
+///
+/// Old and new images are paired by the registered key of the source table.
+/// Returns the number of queued changes.
+pub fn enqueue(source_table: &str, old_rows: Vec<Value>, new_rows: Vec<Value>) -> i64 {
+    Spi::get_one_with_args::<i64>(
+        "WITH source AS (
+             SELECT key_column::text AS key_column
+             FROM jsonb_ivm.projection WHERE table_name = $1::regclass
+         ),
+         old_rows AS (
+             SELECT r ->> s.key_column AS row_key, r
+             FROM source s, jsonb_array_elements($2) r
+         ),
+         new_rows AS (
+             SELECT r ->> s.key_column AS row_key, r
+             FROM source s, jsonb_array_elements($3) r
+         ),
+         queued AS (
+             INSERT INTO jsonb_ivm.change_queue (source_table, row_key, old_row, new_row)
+             SELECT $1::regclass, COALESCE(o.row_key, n.row_key), o.r, n.r
+             FROM old_rows o FULL JOIN new_rows n ON n.row_key = o.row_key
+             RETURNING 1
+         )
+         SELECT count(*) FROM queued",
+        &[
+            source_table.into(),
+            JsonB(Value::Array(old_rows)).into(),
+            JsonB(Value::Array(new_rows)).into(),
+        ],
+    )
+    .unwrap_or_else(|e| error!("{}", e))
+    .unwrap_or(0)
+}
+
+/// Merge queued changes per source row, in queue order
+///
+/// Each row keeps its first old image and its last new image; rows whose
+/// merged change is a no-op (inserted then deleted, or back to the original
+/// image) are dropped. Returns `(source_table, old_rows, new_rows)` per
+/// source table, in order of first change.
+pub fn coalesce(changes: Vec<QueuedChange>) -> Vec<(String, Vec<Value>, Vec<Value>)> {
+    let mut tables: Vec<String> = Vec::new();
+    let mut merged: BTreeMap<(String, String), (Option<Value>, Option<Value>)> = BTreeMap::new();
+    // Row keys per table, in order of first change
+    let mut rows: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for change in changes {
+        let id = (change.source_table.clone(), change.row_key.clone());
+        if let Some((_, new_row)) = merged.get_mut(&id) {
+            *new_row = change.new_row;
+        } else {
+            if !rows.contains_key(&change.source_table) {
+                tables.push(change.source_table.clone());
+            }
+            rows.entry(change.source_table)
+                .or_default()
+                .push(change.row_key);
+            merged.insert(id, (change.old_row, change.new_row));
+        }
+    }
+
+    tables
+        .into_iter()
+        .filter_map(|table| {
+            let mut old_rows = Vec::new();
+            let mut new_rows = Vec::new();
+            for row_key in rows.remove(&table).unwrap_or_default() {
+                let Some((old_row, new_row)) = merged.remove(&(table.clone(), row_key)) else {
+                    continue;
+                };
+                if old_row == new_row {
+                    continue;
+                }
+                old_rows.extend(old_row);
+                new_rows.extend(new_row);
+            }
+            (!old_rows.is_empty() || !new_rows.is_empty()).then_some((table, old_rows, new_rows))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn change(
+        table: &str,
+        key: &str,
+        old_row: Option<Value>,
+        new_row: Option<Value>,
+    ) -> QueuedChange {
+        QueuedChange {
+            source_table: table.to_string(),
+            row_key: key.to_string(),
+            old_row,
+            new_row,
+        }
+    }
+
+    #[test]
+    fn test_coalesce_merges_updates() {
+        let changes = vec![
+            change("tv_user", "1", Some(json!({"v": 0})), Some(json!({"v": 1}))),
+            change("tv_user", "1", Some(json!({"v": 1})), Some(json!({"v": 2}))),
+            change("tv_user", "1", Some(json!({"v": 2})), Some(json!({"v": 3}))),
+        ];
+        assert_eq!(
+            coalesce(changes),
+            vec![(
+                "tv_user".to_string(),
+                vec![json!({"v": 0})],
+                vec![json!({"v": 3})]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_coalesce_cancels_insert_delete() {
+        let changes = vec![
+            change("tv_user", "1", None, Some(json!({"v": 1}))),
+            change("tv_user", "1", Some(json!({"v": 1})), Some(json!({"v": 2}))),
+            change("tv_user", "1", Some(json!({"v": 2})), None),
+        ];
+        assert!(coalesce(changes).is_empty());
+    }
+
+    #[test]
+    fn test_coalesce_drops_reverted_rows() {
+        let changes = vec![
+            change("tv_user", "1", Some(json!({"v": 0})), Some(json!({"v": 1}))),
+            change("tv_user", "1", Some(json!({"v": 1})), Some(json!({"v": 0}))),
+        ];
+        assert!(coalesce(changes).is_empty());
+    }
+
+    #[test]
+    fn test_coalesce_groups_by_table_in_queue_order() {
+        let changes = vec![
+            change(
+                "v_company",
+                "7",
+                Some(json!({"c": 0})),
+                Some(json!({"c": 1})),
+            ),
+            change("tv_user", "2", Some(json!({"u": 2})), None),
+            change("tv_user", "1", None, Some(json!({"u": 1}))),
+            change(
+                "v_company",
+                "7",
+                Some(json!({"c": 1})),
+                Some(json!({"c": 2})),
+            ),
+        ];
+        assert_eq!(
+            coalesce(changes),
+            vec![
+                (
+                    "v_company".to_string(),
+                    vec![json!({"c": 0})],
+                    vec![json!({"c": 2})]
+                ),
+                (
+                    "tv_user".to_string(),
+                    vec![json!({"u": 2})],
+                    vec![json!({"u": 1})]
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,287 @@
+// jsonb_ivm - Projection Rebuild Module
+//
+// Regenerates projection documents from their registered definition in keyed,
+// committed batches, for initial population and schema migrations.
+
+use pgrx::prelude::*;
+
+use crate::verify::{projection_source, ProjectionSource};
+
+extension_sql!(
+    r"
+-- Position of the current or last jsonb_ivm_rebuild of each projection
+CREATE TABLE jsonb_ivm.rebuild_progress (
+    target_table regclass PRIMARY KEY REFERENCES jsonb_ivm.projection ON DELETE CASCADE,
+    where_clause text,
+    last_key text,
+    batches bigint NOT NULL DEFAULT 0,
+    rows_checked bigint NOT NULL DEFAULT 0,
+    rows_written bigint NOT NULL DEFAULT 0,
+    started_at timestamptz NOT NULL DEFAULT now(),
+    updated_at timestamptz NOT NULL DEFAULT now(),
+    finished_at timestamptz
+);
+
+COMMENT ON TABLE jsonb_ivm.rebuild_progress IS
+    'Progress of jsonb_ivm_rebuild by projection: last rebuilt key, totals, completion';
+",
+    name = "rebuild_progress",
+    requires = ["projection_registry"]
+);
+
+extension_sql!(
+    r"
+CREATE PROCEDURE jsonb_ivm_rebuild(
+    target_table text,
+    batch_size integer DEFAULT 1000,
+    where_clause text DEFAULT NULL
+)
+LANGUAGE plpgsql AS $jsonb_ivm$
+DECLARE
+    batch record;
+    checked bigint := 0;
+    written bigint := 0;
+BEGIN
+    LOOP
+        SELECT * INTO batch
+        FROM @extschema@.jsonb_ivm_rebuild_batch(target_table, batch_size, where_clause);
+        COMMIT;
+
+        checked := checked + batch.rows_checked;
+        written := written + batch.rows_written;
+        RAISE NOTICE 'jsonb_ivm_rebuild(%): % rows checked, % rewritten, up to key %',
+            target_table, checked, written, batch.last_key;
+        EXIT WHEN batch.done;
+    END LOOP;
+END
+$jsonb_ivm$;
+
+COMMENT ON PROCEDURE jsonb_ivm_rebuild(text, integer, text) IS
+    'Regenerate projection documents from the registered definition, one committed batch at a time';
+",
+    name = "rebuild_procedure",
+    requires = ["rebuild_progress", jsonb_ivm_rebuild_batch]
+);
+
+/// Regenerate the next batch of a projection from its registered definition
+///
+/// Takes the next `batch_size` keys of the canonical query in key order,
+/// after the last key recorded in `jsonb_ivm.rebuild_progress`, and writes
+/// their documents: missing rows are inserted, drifted documents replaced and
+/// identical ones left untouched. `CALL jsonb_ivm_rebuild(...)` runs batches
+/// until the end, committing after each one.
+///
+/// # Arguments
+///
+/// * `target_table` - Projection registered with a `definition`
+/// * `batch_size` - Canonical keys per batch (default 1000)
+/// * `where_clause` - Optional filter on the canonical rows, over their `key`
+///   and `doc` columns (e.g., `'key BETWEEN 1 AND 50000'`)
+///
+/// # Returns
+///
+/// One row: keys `rows_checked` and documents `rows_written` by this batch,
+/// the `last_key` rebuilt so far and whether the rebuild is `done`
+///
+/// # Examples
+///
+/// ```sql
+/// CALL jsonb_ivm_rebuild('tv_feed', 5000);
+/// -- NOTICE:  jsonb_ivm_rebuild(tv_feed): 5000 rows checked, 312 rewritten, up to key 5000
+/// -- ...
+///
+/// -- Inside a transaction block, drive the batches yourself
+/// SELECT * FROM jsonb_ivm_rebuild_batch('tv_feed', 5000);
+/// ```
+///
+/// # Notes
+/// - An interrupted rebuild resumes after its last committed batch when run
+///   again with the same `where_clause`; a finished one starts over
+/// - The target rows of a batch are locked before its documents are computed,
+///   so cascades running meanwhile wait and then patch the rebuilt documents:
+///   no concurrent update is lost (at the default READ COMMITTED isolation)
+/// - Rows are inserted with the key and document columns only; rows absent
+///   from the canonical query are left for `jsonb_ivm_verify` to report
+/// - The key column must be unique (it is the `ON CONFLICT` target)
+#[pg_extern]
+pub fn jsonb_ivm_rebuild_batch(
+    target_table: &str,
+    batch_size: default!(i32, 1000),
+    where_clause: default!(Option<&str>, "NULL"),
+) -> TableIterator<
+    'static,
+    (
+        name!(rows_checked, i64),
+        name!(rows_written, i64),
+        name!(last_key, Option<String>),
+        name!(done, bool),
+    ),
+> {
+    if batch_size < 1 {
+        error!("batch_size must be at least 1, got {}", batch_size);
+    }
+
+    let ProjectionSource {
+        table,
+        key,
+        key_type,
+        data,
+        definition,
+    } = projection_source(target_table, None, None);
+    let Some(query) = definition else {
+        error!(
+            "No definition registered for '{}'; register one with jsonb_ivm_register_projection",
+            target_table
+        );
+    };
+    let filter = where_clause.unwrap_or("true");
+
+    let after = resume_point(target_table, where_clause);
+
+    let keys = batch_keys(&query, &key_type, filter, after.as_deref(), batch_size);
+
+    let done = keys.len() < usize::try_from(batch_size).unwrap_or(usize::MAX);
+    let checked = i64::try_from(keys.len()).unwrap_or(i64::MAX);
+    let last_key = keys.last().cloned().or_else(|| after.clone());
+
+    let written = keys.last().map_or(0, |last| {
+        // Keys after `$1` (the resume point) up to `$2` (the batch's last key)
+        let range = |column: &str| {
+            format!(
+                "($1::text IS NULL OR {column} > $1::text::{key_type}) AND {column} <= $2::text::{key_type}"
+            )
+        };
+        let args = || [after.clone().into(), last.as_str().into()];
+
+        Spi::connect_mut(|client| {
+            // Lock first: cascades already patching these rows commit before
+            // the canonical query runs (in a new snapshot), later ones wait
+            // for this transaction and patch the rebuilt documents
+            client.update(
+                &format!(
+                    "SELECT 1 FROM {table} WHERE {} ORDER BY {key} FOR UPDATE",
+                    range(&key)
+                ),
+                None,
+                &args(),
+            )?;
+
+            client
+                .update(
+                    &format!(
+                        "WITH canonical AS (
+                             SELECT q.key, q.doc::jsonb AS doc
+                             FROM ({query}) AS q(key, doc)
+                             WHERE {} AND ({filter})
+                         ),
+                         written AS (
+                             INSERT INTO {table} AS t ({key}, {data})
+                             SELECT key, doc FROM canonical
+                             ON CONFLICT ({key}) DO UPDATE SET {data} = EXCLUDED.{data}
+                             WHERE t.{data} IS DISTINCT FROM EXCLUDED.{data}
+                             RETURNING 1
+                         )
+                         SELECT count(*) FROM written",
+                        range("q.key")
+                    ),
+                    None,
+                    &args(),
+                )?
+                .next()
+                .and_then(|row| row.get::<i64>(1).transpose())
+                .transpose()
+        })
+        .unwrap_or_else(|e| error!("{}", e))
+        .unwrap_or(0)
+    });
+
+    Spi::run_with_args(
+        "UPDATE jsonb_ivm.rebuild_progress
+         SET last_key = $2,
+             batches = batches + 1,
+             rows_checked = rows_checked + $3,
+             rows_written = rows_written + $4,
+             updated_at = now(),
+             finished_at = CASE WHEN $5 THEN now() END
+         WHERE target_table = $1::regclass",
+        &[
+            target_table.into(),
+            last_key.clone().into(),
+            checked.into(),
+            written.into(),
+            done.into(),
+        ],
+    )
+    .unwrap_or_else(|e| error!("{}", e));
+
+    TableIterator::new(std::iter::once((checked, written, last_key, done)))
+}
+
+/// Keys of the next `batch_size` canonical rows after `after` that match `filter`
+fn batch_keys(
+    query: &str,
+    key_type: &str,
+    filter: &str,
+    after: Option<&str>,
+    batch_size: i32,
+) -> Vec<String> {
+    Spi::connect(|client| {
+        client
+            .select(
+                &format!(
+                    "SELECT q.key::text
+                     FROM ({query}) AS q(key, doc)
+                     WHERE ($1::text IS NULL OR q.key > $1::text::{key_type}) AND ({filter})
+                     ORDER BY q.key
+                     LIMIT $2"
+                ),
+                None,
+                &[after.into(), batch_size.into()],
+            )?
+            .map(|row| Ok(row.get::<String>(1)?.unwrap_or_default()))
+            .collect::<Result<Vec<_>, spi::Error>>()
+    })
+    .unwrap_or_else(|e| error!("{}", e))
+}
+
+/// Last key rebuilt by the unfinished rebuild of `target_table` with the same
+/// filter; otherwise records a new rebuild and returns `None`
+///
+/// Locks the progress row, so rebuilds of the same projection run one batch
+/// at a time.
+fn resume_point(target_table: &str, where_clause: Option<&str>) -> Option<String> {
+    let progress = Spi::connect_mut(|client| {
+        client
+            .update(
+                "SELECT last_key,
+                        finished_at IS NULL AND where_clause IS NOT DISTINCT FROM $2 AS resumable
+                 FROM jsonb_ivm.rebuild_progress
+                 WHERE target_table = $1::regclass
+                 FOR UPDATE",
+                None,
+                &[target_table.into(), where_clause.into()],
+            )?
+            .next()
+            .map(|row| {
+                Ok::<_, spi::Error>((row.get::<String>(1)?, row.get::<bool>(2)?.unwrap_or(false)))
+            })
+            .transpose()
+    })
+    .unwrap_or_else(|e| error!("{}", e));
+
+    if let Some((last_key, true)) = progress {
+        return last_key;
+    }
+
+    Spi::run_with_args(
+        "INSERT INTO jsonb_ivm.rebuild_progress (target_table, where_clause)
+         VALUES ($1::regclass, $2)
+         ON CONFLICT (target_table) DO UPDATE
+         SET where_clause = EXCLUDED.where_clause, last_key = NULL,
+             batches = 0, rows_checked = 0, rows_written = 0,
+             started_at = now(), updated_at = now(), finished_at = NULL",
+        &[target_table.into(), where_clause.into()],
+    )
+    .unwrap_or_else(|e| error!("{}", e));
+    None
+}
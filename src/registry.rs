@@ -0,0 +1,482 @@
+// jsonb_ivm - Projection Registry Module
+//
+// Extension-owned catalog of projection tables and of the source rows embedded
+// in their JSONB documents: the foundation for automated maintenance.
+
+use pgrx::prelude::*;
+
+use crate::cascade::find_cycle;
+use crate::path::{parse_path, PathSegment};
+
+extension_sql!(
+    r"
+CREATE SCHEMA jsonb_ivm;
+
+-- Tables taking part in projections, as sources or as projection (tv_*) tables
+CREATE TABLE jsonb_ivm.projection (
+    table_name regclass PRIMARY KEY,
+    key_column name NOT NULL DEFAULT 'pk',
+    data_column name NOT NULL DEFAULT 'data',
+    definition text,
+    registered_at timestamptz NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE jsonb_ivm.projection IS
+    'Tables registered with jsonb_ivm: key column, JSONB document column and optional canonical query';
+
+-- Which source table feeds which location of a projection document
+CREATE TABLE jsonb_ivm.dependency (
+    id bigserial PRIMARY KEY,
+    source_table regclass NOT NULL REFERENCES jsonb_ivm.projection ON DELETE CASCADE,
+    target_table regclass NOT NULL REFERENCES jsonb_ivm.projection ON DELETE CASCADE,
+    embed_path text[] NOT NULL,
+    kind text NOT NULL CHECK (kind IN ('object', 'array')),
+    match_key text,
+    fk_column name NOT NULL,
+    registered_at timestamptz NOT NULL DEFAULT now(),
+    UNIQUE (target_table, embed_path),
+    CHECK ((kind = 'array') = (match_key IS NOT NULL))
+);
+
+COMMENT ON TABLE jsonb_ivm.dependency IS
+    'Source rows embedded in projection documents (object: target.fk_column -> source key; array: source.fk_column -> target key)';
+
+CREATE INDEX dependency_source_idx ON jsonb_ivm.dependency (source_table);
+
+-- Registrations are user data: keep them in pg_dump output
+SELECT pg_catalog.pg_extension_config_dump('jsonb_ivm.projection', '');
+SELECT pg_catalog.pg_extension_config_dump('jsonb_ivm.dependency', '');
+SELECT pg_catalog.pg_extension_config_dump('jsonb_ivm.dependency_id_seq', '');
+",
+    name = "projection_registry"
+);
+
+/// Key column assumed for tables registered implicitly by a dependency
+pub const DEFAULT_KEY_COLUMN: &str = "pk";
+
+/// Document column assumed for tables registered implicitly by a dependency
+pub const DEFAULT_DATA_COLUMN: &str = "data";
+
+/// How a source row is embedded in a projection document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyKind {
+    /// The source document is an object at `embed_path` (e.g., `user.company`);
+    /// the projection row references the source row through `fk_column`
+    Object,
+    /// The source document is an element of the array at `embed_path` (e.g.,
+    /// `feed.posts`); the source row references the projection row through
+    /// `fk_column` and elements are identified by `match_key`
+    Array,
+}
+
+impl DependencyKind {
+    /// Parse a dependency kind: `object` or `array`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for any other kind.
+    pub fn parse(kind: &str) -> Result<Self, String> {
+        match kind.to_ascii_lowercase().as_str() {
+            "object" => Ok(Self::Object),
+            "array" => Ok(Self::Array),
+            _ => Err(format!(
+                "Invalid dependency kind '{kind}': expected object or array"
+            )),
+        }
+    }
+
+    /// Name stored in `jsonb_ivm.dependency.kind`
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Object => "object",
+            Self::Array => "array",
+        }
+    }
+}
+
+/// Validate the shape of a dependency and return its kind and path keys
+///
+/// `embed_path` uses dot notation (e.g., `"author.company"`); array indexes
+/// are not allowed since an embedded location must not depend on element
+/// positions. Array dependencies need a single-key path, as the array
+/// functions take a top-level `array_path`, and a `match_key`; object
+/// dependencies take no `match_key`.
+///
+/// # Errors
+///
+/// Returns an error describing the first invalid argument.
+///
+/// # Examples
+/// ```
+/// use jsonb_ivm::registry::{validate_dependency, DependencyKind};
+///
+/// assert_eq!(
+///     validate_dependency("object", "author.company", None),
+///     Ok((DependencyKind::Object, vec!["author".to_string(), "company".to_string()]))
+/// );
+/// assert!(validate_dependency("array", "posts", None).is_err());
+/// ```
+pub fn validate_dependency(
+    kind: &str,
+    embed_path: &str,
+    match_key: Option<&str>,
+) -> Result<(DependencyKind, Vec<String>), String> {
+    let kind = DependencyKind::parse(kind)?;
+
+    let path_keys = parse_path(embed_path)
+        .map_err(|e| format!("Invalid embed path '{embed_path}': {e}"))?
+        .into_iter()
+        .map(|segment| match segment {
+            PathSegment::Key(key) => Ok(key),
+            PathSegment::Index(idx) => Err(format!(
+                "Invalid embed path '{embed_path}': array index [{idx}] is not allowed"
+            )),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match (kind, match_key) {
+        (DependencyKind::Array, None) => Err("array dependencies require a match_key".to_string()),
+        (DependencyKind::Array, Some(_)) if path_keys.len() > 1 => Err(format!(
+            "Invalid embed path '{embed_path}': array dependencies take a top-level key"
+        )),
+        (DependencyKind::Object, Some(_)) => {
+            Err("match_key is only used by array dependencies".to_string())
+        }
+        _ => Ok((kind, path_keys)),
+    }
+}
+
+/// Register a table with `jsonb_ivm`, or update its registration
+///
+/// Tables referenced by `jsonb_ivm_register_dependency` are registered
+/// implicitly with a `pk` key column and a `data` document column; call this
+/// first for tables that use other names.
+///
+/// # Arguments
+///
+/// * `table_name` - Table to register (e.g., `"tv_user"`, optionally schema-qualified)
+/// * `key_column` - Primary key column (default `'pk'`)
+/// * `data_column` - JSONB document column (default `'data'`)
+/// * `definition` - Optional canonical query producing `(key, document)` rows,
+///   for verification and rebuilds
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT jsonb_ivm_register_projection('v_company', key_column => 'id', data_column => 'doc');
+/// ```
+#[pg_extern]
+pub fn jsonb_ivm_register_projection(
+    table_name: &str,
+    key_column: default!(&str, "'pk'"),
+    data_column: default!(&str, "'data'"),
+    definition: default!(Option<&str>, "NULL"),
+) {
+    for column in [key_column, data_column] {
+        if !column_exists(table_name, column) {
+            error!("Table '{}' has no column '{}'", table_name, column);
+        }
+    }
+
+    Spi::run_with_args(
+        "INSERT INTO jsonb_ivm.projection (table_name, key_column, data_column, definition)
+         VALUES ($1::regclass, $2, $3, $4)
+         ON CONFLICT (table_name) DO UPDATE
+         SET key_column = EXCLUDED.key_column,
+             data_column = EXCLUDED.data_column,
+             definition = EXCLUDED.definition",
+        &[
+            table_name.into(),
+            key_column.into(),
+            data_column.into(),
+            definition.into(),
+        ],
+    )
+    .unwrap_or_else(|e| error!("{}", e));
+}
+
+/// Declare that rows of `source_table` are embedded in documents of `target_table`
+///
+/// Records where each source document lives in the projection so that
+/// maintenance can be derived from the catalog instead of hand-written
+/// triggers. Registering the same `(target_table, embed_path)` again replaces
+/// the previous declaration.
+///
+/// # Arguments
+///
+/// * `source_table` - Table whose documents are embedded (e.g., `"v_company"`)
+/// * `target_table` - Projection table (e.g., `"tv_user"`)
+/// * `embed_path` - Location in the target document, dot notation (e.g., `"company"`)
+/// * `kind` - `'object'` (embedded object) or `'array'` (element of an embedded array)
+/// * `match_key` - Array dependencies: key identifying elements (e.g., `"id"`);
+///   must be NULL for object dependencies
+/// * `fk_column` - Foreign key column: on the target for `'object'`
+///   (`tv_user.fk_company`), on the source for `'array'` (`tv_post.fk_feed`)
+///
+/// # Returns
+///
+/// The id of the dependency in `jsonb_ivm.dependency`
+///
+/// # Examples
+///
+/// ```sql
+/// -- tv_user.data.company is the document of v_company row tv_user.fk_company
+/// SELECT jsonb_ivm_register_dependency('v_company', 'tv_user', 'company', 'object', NULL, 'fk_company');
+///
+/// -- tv_feed.data.posts holds the documents of the tv_post rows with fk_feed = tv_feed.pk
+/// SELECT jsonb_ivm_register_dependency('tv_post', 'tv_feed', 'posts', 'array', 'id', 'fk_feed');
+/// ```
+///
+/// # Notes
+/// - Source and target tables are registered implicitly (see `jsonb_ivm_register_projection`)
+/// - Declarations that would make a projection embed itself, directly or
+///   through other projections, are rejected
+/// - Remove a declaration with `DELETE FROM jsonb_ivm.dependency WHERE id = ...`
+#[pg_extern]
+#[must_use]
+pub fn jsonb_ivm_register_dependency(
+    source_table: &str,
+    target_table: &str,
+    embed_path: &str,
+    kind: &str,
+    match_key: Option<&str>,
+    fk_column: &str,
+) -> i64 {
+    let (kind, path_keys) =
+        validate_dependency(kind, embed_path, match_key).unwrap_or_else(|e| error!("{}", e));
+
+    ensure_projection(source_table);
+    ensure_projection(target_table);
+
+    let fk_table = match kind {
+        DependencyKind::Object => target_table,
+        DependencyKind::Array => source_table,
+    };
+    if !column_exists(fk_table, fk_column) {
+        error!(
+            "Table '{}' has no column '{}' ({} dependencies need the foreign key on the {} table)",
+            fk_table,
+            fk_column,
+            kind.as_str(),
+            if kind == DependencyKind::Object {
+                "target"
+            } else {
+                "source"
+            }
+        );
+    }
+
+    reject_cycle(source_table, target_table, &path_keys);
+
+    Spi::get_one_with_args::<i64>(
+        "INSERT INTO jsonb_ivm.dependency
+             (source_table, target_table, embed_path, kind, match_key, fk_column)
+         VALUES ($1::regclass, $2::regclass, $3, $4, $5, $6)
+         ON CONFLICT (target_table, embed_path) DO UPDATE
+         SET source_table = EXCLUDED.source_table,
+             kind = EXCLUDED.kind,
+             match_key = EXCLUDED.match_key,
+             fk_column = EXCLUDED.fk_column
+         RETURNING id",
+        &[
+            source_table.into(),
+            target_table.into(),
+            path_keys.into(),
+            kind.as_str().into(),
+            match_key.into(),
+            fk_column.into(),
+        ],
+    )
+    .unwrap_or_else(|e| error!("{}", e))
+    .unwrap_or_else(|| {
+        error!(
+            "Failed to register dependency of '{}' on '{}'",
+            target_table, source_table
+        )
+    })
+}
+
+/// Raise if embedding `source_table` in `target_table` would close a cycle
+///
+/// The declaration being replaced, if any, is left out of the graph.
+fn reject_cycle(source_table: &str, target_table: &str, path_keys: &[String]) {
+    let edges: Vec<(String, String)> = Spi::connect(|client| {
+        client
+            .select(
+                "SELECT source_table::text, target_table::text
+                 FROM jsonb_ivm.dependency
+                 WHERE NOT (target_table = $2::regclass AND embed_path = $3)
+                 UNION ALL
+                 SELECT $1::regclass::text, $2::regclass::text",
+                None,
+                &[
+                    source_table.into(),
+                    target_table.into(),
+                    path_keys.to_vec().into(),
+                ],
+            )?
+            .map(|row| {
+                Ok((
+                    row.get::<String>(1)?.unwrap_or_default(),
+                    row.get::<String>(2)?.unwrap_or_default(),
+                ))
+            })
+            .collect::<Result<Vec<_>, spi::Error>>()
+    })
+    .unwrap_or_else(|e| error!("{}", e));
+
+    let edges: Vec<(&str, &str)> = edges
+        .iter()
+        .map(|(source, target)| (source.as_str(), target.as_str()))
+        .collect();
+    if let Some(cycle) = find_cycle(&edges) {
+        error!(
+            "Dependency of '{}' on '{}' would create a cycle: {}",
+            target_table,
+            source_table,
+            cycle.join(" -> ")
+        );
+    }
+}
+
+/// A registered dependency with every identifier and literal quoted for SQL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RegisteredDependency {
+    pub(crate) id: i64,
+    pub(crate) kind: DependencyKind,
+    /// Schema holding the `jsonb_ivm` functions, for schema-qualified calls
+    pub(crate) function_schema: String,
+    pub(crate) source_table: String,
+    pub(crate) source_key: String,
+    pub(crate) source_data: String,
+    pub(crate) target_table: String,
+    pub(crate) target_key: String,
+    pub(crate) target_data: String,
+    pub(crate) fk_column: String,
+    /// `text[]` literal for object dependencies, `text` literal of the array
+    /// key for array dependencies
+    pub(crate) embed_path: String,
+    /// `text` literal (array dependencies only)
+    pub(crate) match_key: Option<String>,
+}
+
+/// Read registered dependencies, all or those of `target_table`, ordered by id
+pub(crate) fn load_dependencies(target_table: Option<&str>) -> Vec<RegisteredDependency> {
+    Spi::connect(|client| {
+        client
+            .select(
+                "SELECT d.id, d.kind,
+                        (SELECT quote_ident(n.nspname)
+                         FROM pg_catalog.pg_extension e
+                         JOIN pg_catalog.pg_namespace n ON n.oid = e.extnamespace
+                         WHERE e.extname = 'jsonb_ivm') AS function_schema,
+                        d.source_table::text,
+                        quote_ident(s.key_column) AS source_key,
+                        quote_ident(s.data_column) AS source_data,
+                        d.target_table::text,
+                        quote_ident(t.key_column) AS target_key,
+                        quote_ident(t.data_column) AS target_data,
+                        quote_ident(d.fk_column) AS fk_column,
+                        CASE d.kind
+                            WHEN 'array' THEN quote_literal(d.embed_path[1])
+                            ELSE quote_literal(d.embed_path::text) || '::text[]'
+                        END AS embed_path,
+                        quote_literal(d.match_key) AS match_key
+                 FROM jsonb_ivm.dependency d
+                 JOIN jsonb_ivm.projection s ON s.table_name = d.source_table
+                 JOIN jsonb_ivm.projection t ON t.table_name = d.target_table
+                 WHERE $1::regclass IS NULL OR d.target_table = $1::regclass
+                 ORDER BY d.id",
+                None,
+                &[target_table.into()],
+            )?
+            .map(|row| {
+                let text = |name: &str| -> Result<String, spi::Error> {
+                    Ok(row.get_by_name::<String, _>(name)?.unwrap_or_default())
+                };
+                let kind =
+                    DependencyKind::parse(&text("kind")?).unwrap_or_else(|e| error!("{}", e));
+
+                Ok(RegisteredDependency {
+                    id: row.get_by_name::<i64, _>("id")?.unwrap_or_default(),
+                    kind,
+                    function_schema: text("function_schema")?,
+                    source_table: text("source_table")?,
+                    source_key: text("source_key")?,
+                    source_data: text("source_data")?,
+                    target_table: text("target_table")?,
+                    target_key: text("target_key")?,
+                    target_data: text("target_data")?,
+                    fk_column: text("fk_column")?,
+                    embed_path: text("embed_path")?,
+                    match_key: row.get_by_name::<String, _>("match_key")?,
+                })
+            })
+            .collect::<Result<Vec<_>, spi::Error>>()
+    })
+    .unwrap_or_else(|e| error!("{}", e))
+}
+
+/// Register `table_name` with the default columns unless it is already registered
+fn ensure_projection(table_name: &str) {
+    let registered = Spi::get_one_with_args::<bool>(
+        "SELECT EXISTS (SELECT 1 FROM jsonb_ivm.projection WHERE table_name = $1::regclass)",
+        &[table_name.into()],
+    )
+    .unwrap_or_else(|e| error!("{}", e))
+    .unwrap_or(false);
+
+    if !registered {
+        jsonb_ivm_register_projection(table_name, DEFAULT_KEY_COLUMN, DEFAULT_DATA_COLUMN, None);
+    }
+}
+
+/// Whether `table_name` has a (non-dropped) column named `column`
+fn column_exists(table_name: &str, column: &str) -> bool {
+    Spi::get_one_with_args::<bool>(
+        "SELECT EXISTS (
+             SELECT 1 FROM pg_catalog.pg_attribute
+             WHERE attrelid = $1::regclass AND attname = $2 AND attnum > 0 AND NOT attisdropped
+         )",
+        &[table_name.into(), column.into()],
+    )
+    .unwrap_or_else(|e| error!("{}", e))
+    .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_kind() {
+        assert_eq!(DependencyKind::parse("object"), Ok(DependencyKind::Object));
+        assert_eq!(DependencyKind::parse("ARRAY"), Ok(DependencyKind::Array));
+        assert!(DependencyKind::parse("scalar").is_err());
+    }
+
+    #[test]
+    fn test_validate_array_dependency() {
+        assert_eq!(
+            validate_dependency("array", "posts", Some("id")),
+            Ok((DependencyKind::Array, vec!["posts".to_string()]))
+        );
+        assert!(validate_dependency("array", "posts", None).is_err());
+        assert!(validate_dependency("array", "feed.posts", Some("id")).is_err());
+    }
+
+    #[test]
+    fn test_validate_object_dependency() {
+        assert_eq!(
+            validate_dependency("object", "author.company", None),
+            Ok((
+                DependencyKind::Object,
+                vec!["author".to_string(), "company".to_string()]
+            ))
+        );
+        assert!(validate_dependency("object", "company", Some("id")).is_err());
+        assert!(validate_dependency("object", "posts[0].author", None).is_err());
+        assert!(validate_dependency("object", "", None).is_err());
+    }
+}
@@ -62,3 +62,14 @@ pub fn find_by_int_id_scalar(array: &[Value], match_key: &str, match_value: i64)
         elem.get(match_key).and_then(serde_json::Value::as_i64) == Some(match_value)
     })
 }
+
+/// Canonical hashable representation of a match value
+///
+/// `serde_json::Value` does not implement `Hash`, so batch operations key their
+/// lookup tables on the serialized form. For the values used as IDs (integers,
+/// strings, UUIDs) two values produce the same key exactly when they compare equal.
+#[inline]
+#[must_use]
+pub fn value_key(value: &Value) -> String {
+    value.to_string()
+}
@@ -0,0 +1,737 @@
+// jsonb_ivm - Sort Specification Module
+//
+// ORDER BY-style sort specifications for sorted array operations.
+// Parses specs like `priority DESC, created_at::timestamptz ASC NULLS LAST, id`
+// once and compares array elements according to them, using PostgreSQL types and
+// collations where requested.
+
+use pgrx::prelude::*;
+use pgrx::{direct_function_call, Uuid};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::ffi::CString;
+use std::str::FromStr;
+
+use crate::array_ops::compare_values;
+
+/// A single `key[::type] [COLLATE name] [ASC|DESC] [NULLS FIRST|LAST]` term of a
+/// sort specification
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SortTerm {
+    /// Element key to sort by
+    pub key: String,
+    /// Type the value is cast to before comparison
+    pub cast: SortType,
+    /// Collation for string comparison (`COLLATE name`)
+    pub collation: Option<String>,
+    /// Sort in descending order
+    pub descending: bool,
+    /// Place NULL (or missing) values before non-NULL values
+    pub nulls_first: bool,
+    /// Resolved collation, set by `OrderSpec::resolve_collations`
+    collation_oid: Option<pg_sys::Oid>,
+}
+
+/// Type a sort value is cast to (`key::type`) before comparison
+///
+/// Typed values are parsed with the database's input functions and compared
+/// with its comparison functions, so the array order matches `ORDER BY
+/// (elem->>'key')::type` on the source table.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SortType {
+    /// No cast: JSON ordering of `compare_values`
+    Json,
+    /// `text`, compared with the term's collation (database default if none)
+    Text,
+    /// `numeric`, exact for numbers stored as strings
+    Numeric,
+    /// `timestamptz`, so different UTC offsets compare correctly
+    Timestamptz,
+    /// `timestamp`
+    Timestamp,
+    /// `date`
+    Date,
+    /// `uuid`, compared bytewise like SQL `uuid` values
+    Uuid,
+}
+
+impl SortType {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" => Some(Self::Text),
+            "numeric" | "decimal" => Some(Self::Numeric),
+            "timestamptz" => Some(Self::Timestamptz),
+            "timestamp" => Some(Self::Timestamp),
+            "date" => Some(Self::Date),
+            "uuid" => Some(Self::Uuid),
+            _ => None,
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Json => "jsonb",
+            Self::Text => "text",
+            Self::Numeric => "numeric",
+            Self::Timestamptz => "timestamptz",
+            Self::Timestamp => "timestamp",
+            Self::Date => "date",
+            Self::Uuid => "uuid",
+        }
+    }
+}
+
+/// A sort value cast according to its term's `SortType`
+///
+/// Values of one term always share a variant.
+pub enum SortValue<'a> {
+    Json(&'a Value),
+    Text(String),
+    Numeric(AnyNumeric),
+    Timestamptz(TimestampWithTimeZone),
+    Timestamp(Timestamp),
+    Date(Date),
+    Uuid(Uuid),
+}
+
+impl SortValue<'_> {
+    fn cmp(&self, other: &Self, collation: Option<pg_sys::Oid>) -> Ordering {
+        match (self, other) {
+            // Without a cast, only string-to-string comparisons use the collation
+            (Self::Json(a), Self::Json(b)) => match (a, b, collation) {
+                (Value::String(a), Value::String(b), Some(collation)) => {
+                    compare_text(a, b, collation)
+                }
+                _ => compare_values(a, b),
+            },
+            (Self::Text(a), Self::Text(b)) => {
+                compare_text(a, b, collation.unwrap_or(pg_sys::DEFAULT_COLLATION_OID))
+            }
+            (Self::Numeric(a), Self::Numeric(b)) => a.cmp(b),
+            (Self::Timestamptz(a), Self::Timestamptz(b)) => a.cmp(b),
+            (Self::Timestamp(a), Self::Timestamp(b)) => a.cmp(b),
+            (Self::Date(a), Self::Date(b)) => a.cmp(b),
+            (Self::Uuid(a), Self::Uuid(b)) => a.as_bytes().cmp(b.as_bytes()),
+            _ => unreachable!("sort values of one term share a type"),
+        }
+    }
+}
+
+/// Precomputed sort values of an element, one per term
+pub type SortKey<'a> = Vec<Option<SortValue<'a>>>;
+
+/// A parsed ORDER BY-style sort specification
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OrderSpec {
+    pub terms: Vec<SortTerm>,
+}
+
+impl OrderSpec {
+    /// Look up the collations used by the specification
+    ///
+    /// Terms without a `COLLATE` clause use `default_collation`; if that is
+    /// `None` too, strings keep their JSON (bytewise) order, and `::text` terms
+    /// use the database default collation.
+    ///
+    /// # Panics
+    ///
+    /// Raises an error if a collation does not exist.
+    pub fn resolve_collations(&mut self, default_collation: Option<&str>) {
+        for term in &mut self.terms {
+            term.collation_oid = term
+                .collation
+                .as_deref()
+                .or(default_collation)
+                .map(lookup_collation);
+        }
+    }
+
+    /// Compare two array elements according to the specification
+    ///
+    /// Terms are compared in order; the first non-equal term decides. A missing
+    /// key and a JSON `null` are both treated as SQL NULL.
+    #[must_use]
+    pub fn compare(&self, a: &Value, b: &Value) -> Ordering {
+        for term in &self.terms {
+            let ord = term.compare(a, b);
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// Cast the sort values of an element once, for repeated comparisons
+    #[must_use]
+    pub fn sort_key<'a>(&self, elem: &'a Value) -> SortKey<'a> {
+        self.terms
+            .iter()
+            .map(|term| term.sort_value(elem))
+            .collect()
+    }
+
+    /// Compare a precomputed sort key against an array element
+    #[must_use]
+    pub fn compare_key(&self, key: &SortKey<'_>, elem: &Value) -> Ordering {
+        for (term, key_val) in self.terms.iter().zip(key) {
+            let ord = term.compare_sort_values(key_val.as_ref(), term.sort_value(elem).as_ref());
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// Compare two precomputed sort keys
+    #[must_use]
+    pub fn compare_keys(&self, a: &SortKey<'_>, b: &SortKey<'_>) -> Ordering {
+        for ((term, a_val), b_val) in self.terms.iter().zip(a).zip(b) {
+            let ord = term.compare_sort_values(a_val.as_ref(), b_val.as_ref());
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// Stable sort of an array according to the specification
+    ///
+    /// Sort values are cast once per element rather than once per comparison.
+    pub fn sort(&self, array: &mut Vec<Value>) {
+        let mut order: Vec<usize> = (0..array.len()).collect();
+        {
+            let keys: Vec<SortKey<'_>> = array.iter().map(|elem| self.sort_key(elem)).collect();
+            order.sort_by(|&a, &b| self.compare_keys(&keys[a], &keys[b]));
+        }
+
+        let mut slots: Vec<Option<Value>> = std::mem::take(array).into_iter().map(Some).collect();
+        *array = order
+            .into_iter()
+            .filter_map(|idx| slots[idx].take())
+            .collect();
+    }
+
+    /// Whether the array is ordered according to the specification (ties allowed)
+    #[must_use]
+    pub fn is_sorted(&self, array: &[Value]) -> bool {
+        array
+            .windows(2)
+            .all(|pair| self.compare(&pair[0], &pair[1]) != Ordering::Greater)
+    }
+
+    /// Whether the first term sorts in descending order
+    #[must_use]
+    pub fn is_descending(&self) -> bool {
+        self.terms.first().is_some_and(|term| term.descending)
+    }
+}
+
+impl SortTerm {
+    /// Compare two array elements on this term only
+    #[must_use]
+    pub fn compare(&self, a: &Value, b: &Value) -> Ordering {
+        self.compare_sort_values(self.sort_value(a).as_ref(), self.sort_value(b).as_ref())
+    }
+
+    /// Extract and cast the sort value of an element (`None` for NULL)
+    ///
+    /// # Panics
+    ///
+    /// Raises an error if the value is not valid input for the cast type.
+    #[must_use]
+    pub fn sort_value<'a>(&self, elem: &'a Value) -> Option<SortValue<'a>> {
+        let value = elem.get(&self.key).filter(|v| !v.is_null())?;
+
+        if self.cast == SortType::Json {
+            return Some(SortValue::Json(value));
+        }
+
+        // Same text as `elem->>'key'`
+        let text = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+
+        let sort_value = match self.cast {
+            SortType::Json => unreachable!(),
+            SortType::Text => return Some(SortValue::Text(text)),
+            SortType::Numeric => AnyNumeric::from_str(&text)
+                .map(SortValue::Numeric)
+                .map_err(|e| e.to_string()),
+            SortType::Timestamptz => TimestampWithTimeZone::from_str(&text)
+                .map(SortValue::Timestamptz)
+                .map_err(|e| e.to_string()),
+            SortType::Timestamp => Timestamp::from_str(&text)
+                .map(SortValue::Timestamp)
+                .map_err(|e| e.to_string()),
+            SortType::Date => Date::from_str(&text)
+                .map(SortValue::Date)
+                .map_err(|e| e.to_string()),
+            SortType::Uuid => Ok(SortValue::Uuid(parse_uuid(&text))),
+        };
+
+        match sort_value {
+            Ok(sort_value) => Some(sort_value),
+            Err(e) => error!(
+                "invalid {} value '{}' for sort key '{}': {}",
+                self.cast.name(),
+                text,
+                self.key,
+                e
+            ),
+        }
+    }
+
+    /// Order two sort values (NULL placement and direction applied)
+    fn compare_sort_values(
+        &self,
+        a: Option<&SortValue<'_>>,
+        b: Option<&SortValue<'_>>,
+    ) -> Ordering {
+        match (a, b) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) if self.nulls_first => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) if self.nulls_first => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a_val), Some(b_val)) => {
+                let ord = a_val.cmp(b_val, self.collation_oid);
+                if self.descending {
+                    ord.reverse()
+                } else {
+                    ord
+                }
+            }
+        }
+    }
+}
+
+/// Compare strings like SQL `text` values in the given collation (libc or ICU)
+fn compare_text(a: &str, b: &str, collation: pg_sys::Oid) -> Ordering {
+    let len = |s: &str| {
+        i32::try_from(s.len())
+            .unwrap_or_else(|_| error!("sort string of {} bytes is too long to compare", s.len()))
+    };
+    // SAFETY: both pointers are valid for the given lengths
+    let cmp = unsafe {
+        pg_sys::varstr_cmp(
+            a.as_ptr().cast(),
+            len(a),
+            b.as_ptr().cast(),
+            len(b),
+            collation,
+        )
+    };
+    cmp.cmp(&0)
+}
+
+/// Look up a collation by name (raises an error if it does not exist)
+///
+/// The name is matched as written, like a quoted identifier: `fr_FR`,
+/// `de-DE-x-icu`, `C`.
+fn lookup_collation(name: &str) -> pg_sys::Oid {
+    let quoted = format!("\"{}\"", name.replace('"', "\"\""));
+    let Ok(cstr) = CString::new(quoted) else {
+        error!("invalid collation name '{}': contains a NUL byte", name);
+    };
+
+    // SAFETY: `cstr` is a valid NUL-terminated cstring that outlives the call;
+    // `regcollationin` raises an error for unknown or invalid names
+    unsafe {
+        direct_function_call::<pg_sys::Oid>(pg_sys::regcollationin, &[cstr.as_c_str().into_datum()])
+    }
+    .unwrap_or_else(|| error!("collation \"{}\" does not exist", name))
+}
+
+/// Parse a UUID with the `uuid_in` input function (raises an error on invalid input)
+fn parse_uuid(text: &str) -> Uuid {
+    let Ok(cstr) = CString::new(text) else {
+        error!("invalid uuid value '{}': contains a NUL byte", text);
+    };
+
+    // SAFETY: `cstr` is a valid NUL-terminated cstring that outlives the call;
+    // `uuid_in` raises an error on invalid input
+    unsafe { direct_function_call::<Uuid>(pg_sys::uuid_in, &[cstr.as_c_str().into_datum()]) }
+        .unwrap_or_else(|| error!("uuid_in returned NULL for '{}'", text))
+}
+
+/// Parse an ORDER BY-style sort specification
+///
+/// # Supported Syntax
+/// - Comma-separated terms: `priority DESC, created_at, id`
+/// - Typed comparison per term: `created_at::timestamptz` (also `timestamp`,
+///   `date`, `numeric`, `uuid`, `text`)
+/// - Collation per term: `name COLLATE "fr_FR"` (see `OrderSpec::resolve_collations`)
+/// - Direction per term: `ASC` or `DESC` (case-insensitive)
+/// - NULL placement per term: `NULLS FIRST` or `NULLS LAST`
+/// - Quoted keys for names with spaces or commas: `"created at" DESC`
+///
+/// Terms without a direction use `default_order` (`ASC` or `DESC`). As in
+/// SQL `ORDER BY`, `NULL` values sort last in ascending order and first in
+/// descending order unless `NULLS FIRST`/`NULLS LAST` is given. Missing keys
+/// are `NULL`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The specification or one of its terms is empty
+/// - A direction other than `ASC`/`DESC` is given (in a term or as `default_order`)
+/// - `NULLS` is not followed by `FIRST` or `LAST`
+/// - A cast type is not supported
+/// - `COLLATE` is not followed by a collation name
+/// - A quoted key is not terminated
+///
+/// # Examples
+/// ```
+/// use jsonb_ivm::{parse_order_spec, SortType};
+///
+/// let spec = parse_order_spec(
+///     r#"priority DESC, created_at::timestamptz ASC NULLS FIRST, name COLLATE "fr_FR""#,
+///     "ASC",
+/// )
+/// .unwrap();
+/// assert!(spec.terms[0].descending);
+/// assert_eq!(spec.terms[1].cast, SortType::Timestamptz);
+/// assert!(spec.terms[1].nulls_first);
+/// assert_eq!(spec.terms[2].collation.as_deref(), Some("fr_FR"));
+///
+/// assert!(parse_order_spec("created_at DOWN", "ASC").is_err());
+/// ```
+pub fn parse_order_spec(spec: &str, default_order: &str) -> Result<OrderSpec, String> {
+    let default_descending = parse_direction(default_order)
+        .ok_or_else(|| format!("Invalid sort order '{default_order}': expected ASC or DESC"))?;
+
+    let mut terms = Vec::new();
+    for term_tokens in tokenize(spec)? {
+        terms.push(parse_term(&term_tokens, default_descending)?);
+    }
+
+    if terms.is_empty() {
+        return Err("Invalid sort specification: empty".into());
+    }
+
+    Ok(OrderSpec { terms })
+}
+
+/// Parse a single term from its tokens
+fn parse_term(tokens: &[String], default_descending: bool) -> Result<SortTerm, String> {
+    let Some((key, mut rest)) = tokens.split_first() else {
+        return Err("Invalid sort specification: empty term".into());
+    };
+
+    let mut cast = SortType::Json;
+    let mut collation = None;
+    let mut descending = default_descending;
+    let mut nulls_first = None;
+
+    if let Some((type_name, tail)) = rest
+        .split_first()
+        .and_then(|(first, tail)| Some((first.strip_prefix("::")?, tail)))
+    {
+        cast = SortType::from_name(type_name)
+            .ok_or_else(|| format!("Unsupported sort type '{type_name}' for key '{key}'"))?;
+        rest = tail;
+    }
+
+    if let Some((_, tail)) = rest
+        .split_first()
+        .filter(|(first, _)| first.eq_ignore_ascii_case("COLLATE"))
+    {
+        let Some((name, tail)) = tail.split_first() else {
+            return Err(format!(
+                "Missing collation name after COLLATE for key '{key}'"
+            ));
+        };
+        collation = Some(name.clone());
+        rest = tail;
+    }
+
+    if let Some((direction, tail)) = rest.split_first() {
+        if !direction.eq_ignore_ascii_case("NULLS") {
+            descending = parse_direction(direction)
+                .ok_or_else(|| format!("Unknown sort direction '{direction}' for key '{key}'"))?;
+            rest = tail;
+        }
+    }
+
+    match rest {
+        [] => {}
+        [nulls, placement] if nulls.eq_ignore_ascii_case("NULLS") => {
+            if placement.eq_ignore_ascii_case("FIRST") {
+                nulls_first = Some(true);
+            } else if placement.eq_ignore_ascii_case("LAST") {
+                nulls_first = Some(false);
+            } else {
+                return Err(format!(
+                    "Invalid NULLS placement '{placement}' for key '{key}': expected FIRST or LAST"
+                ));
+            }
+        }
+        [other, ..] => {
+            return Err(format!("Unexpected '{other}' in sort term for key '{key}'"));
+        }
+    }
+
+    Ok(SortTerm {
+        key: key.clone(),
+        cast,
+        collation,
+        descending,
+        // PostgreSQL default: NULLS LAST for ASC, NULLS FIRST for DESC
+        nulls_first: nulls_first.unwrap_or(descending),
+        collation_oid: None,
+    })
+}
+
+/// Parse `ASC`/`DESC`, returning whether the direction is descending
+const fn parse_direction(direction: &str) -> Option<bool> {
+    if direction.eq_ignore_ascii_case("ASC") {
+        Some(false)
+    } else if direction.eq_ignore_ascii_case("DESC") {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Split a specification into terms, and each term into whitespace-separated tokens
+///
+/// An unquoted `::` starts a new token, so `key::type` yields `key` and `::type`.
+fn tokenize(spec: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut terms = Vec::new();
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut chars = spec.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                // Quoted key: read until the closing quote ("" escapes a quote)
+                loop {
+                    match chars.next() {
+                        Some('"') => {
+                            if chars.as_str().starts_with('"') {
+                                chars.next();
+                                current.push('"');
+                            } else {
+                                break;
+                            }
+                        }
+                        Some(c) => current.push(c),
+                        None => return Err("Invalid sort specification: unterminated quote".into()),
+                    }
+                }
+            }
+            ',' => {
+                flush_token(&mut current, &mut tokens);
+                if tokens.is_empty() {
+                    return Err("Invalid sort specification: empty term".into());
+                }
+                terms.push(std::mem::take(&mut tokens));
+            }
+            ':' if chars.as_str().starts_with(':') => {
+                chars.next();
+                flush_token(&mut current, &mut tokens);
+                current.push_str("::");
+                // Allow `key :: type`
+                while chars.as_str().starts_with(char::is_whitespace) {
+                    chars.next();
+                }
+            }
+            c if c.is_whitespace() => flush_token(&mut current, &mut tokens),
+            c => current.push(c),
+        }
+    }
+
+    flush_token(&mut current, &mut tokens);
+    if !tokens.is_empty() {
+        terms.push(tokens);
+    } else if !terms.is_empty() {
+        return Err("Invalid sort specification: empty term".into());
+    }
+
+    Ok(terms)
+}
+
+fn flush_token(current: &mut String, tokens: &mut Vec<String>) {
+    if !current.is_empty() {
+        tokens.push(std::mem::take(current));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn term(key: &str, descending: bool, nulls_first: bool) -> SortTerm {
+        SortTerm {
+            key: key.into(),
+            cast: SortType::Json,
+            collation: None,
+            descending,
+            nulls_first,
+            collation_oid: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_single_key() {
+        assert_eq!(
+            parse_order_spec("created_at", "ASC").unwrap().terms,
+            vec![term("created_at", false, false)]
+        );
+    }
+
+    #[test]
+    fn test_parse_default_order() {
+        assert_eq!(
+            parse_order_spec("created_at", "desc").unwrap().terms,
+            vec![term("created_at", true, true)]
+        );
+    }
+
+    #[test]
+    fn test_parse_multi_key() {
+        assert_eq!(
+            parse_order_spec("priority DESC, created_at ASC NULLS LAST, id", "ASC")
+                .unwrap()
+                .terms,
+            vec![
+                term("priority", true, true),
+                term("created_at", false, false),
+                term("id", false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_nulls_without_direction() {
+        assert_eq!(
+            parse_order_spec("score NULLS FIRST", "ASC").unwrap().terms,
+            vec![term("score", false, true)]
+        );
+    }
+
+    #[test]
+    fn test_parse_quoted_key() {
+        assert_eq!(
+            parse_order_spec(r#""created at" DESC, "a""b""#, "ASC")
+                .unwrap()
+                .terms,
+            vec![term("created at", true, true), term("a\"b", false, false)]
+        );
+    }
+
+    #[test]
+    fn test_parse_cast() {
+        let spec = parse_order_spec(
+            r#"created_at::timestamptz DESC, "due date"::DATE, amount :: numeric NULLS FIRST"#,
+            "ASC",
+        )
+        .unwrap();
+        assert_eq!(
+            spec.terms
+                .iter()
+                .map(|t| (t.key.as_str(), t.cast))
+                .collect::<Vec<_>>(),
+            vec![
+                ("created_at", SortType::Timestamptz),
+                ("due date", SortType::Date),
+                ("amount", SortType::Numeric),
+            ]
+        );
+        assert!(spec.terms[0].descending);
+        assert!(spec.terms[2].nulls_first);
+        assert_eq!(
+            parse_order_spec(r#""a::b""#, "ASC").unwrap().terms[0].key,
+            "a::b"
+        );
+    }
+
+    #[test]
+    fn test_parse_collate() {
+        let spec = parse_order_spec(
+            r#"name COLLATE "fr_FR" DESC, title::text collate "und-x-icu" NULLS FIRST"#,
+            "ASC",
+        )
+        .unwrap();
+        assert_eq!(spec.terms[0].collation.as_deref(), Some("fr_FR"));
+        assert!(spec.terms[0].descending);
+        assert_eq!(spec.terms[1].cast, SortType::Text);
+        assert_eq!(spec.terms[1].collation.as_deref(), Some("und-x-icu"));
+        assert!(spec.terms[1].nulls_first);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse_order_spec("", "ASC").is_err());
+        assert!(parse_order_spec("a,,b", "ASC").is_err());
+        assert!(parse_order_spec("a,", "ASC").is_err());
+        assert!(parse_order_spec("a SIDEWAYS", "ASC").is_err());
+        assert!(parse_order_spec("a NULLS", "ASC").is_err());
+        assert!(parse_order_spec("a NULLS MIDDLE", "ASC").is_err());
+        assert!(parse_order_spec("a DESC extra", "ASC").is_err());
+        assert!(parse_order_spec("\"a", "ASC").is_err());
+        assert!(parse_order_spec("a", "DOWN").is_err());
+        assert!(parse_order_spec("a::point", "ASC").is_err());
+        assert!(parse_order_spec("a::", "ASC").is_err());
+        assert!(parse_order_spec("a COLLATE", "ASC").is_err());
+        assert!(parse_order_spec("a DESC COLLATE \"C\"", "ASC").is_err());
+    }
+
+    #[test]
+    fn test_compare_multi_key() {
+        let spec = parse_order_spec("priority DESC, id", "ASC").unwrap();
+        let a = json!({"priority": 2, "id": 5});
+        let b = json!({"priority": 1, "id": 1});
+        let c = json!({"priority": 2, "id": 7});
+        assert_eq!(spec.compare(&a, &b), Ordering::Less);
+        assert_eq!(spec.compare(&a, &c), Ordering::Less);
+        assert_eq!(spec.compare(&a, &a), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_compare_nulls() {
+        let asc = parse_order_spec("k", "ASC").unwrap();
+        let desc = parse_order_spec("k DESC", "ASC").unwrap();
+        let asc_first = parse_order_spec("k ASC NULLS FIRST", "ASC").unwrap();
+        let missing = json!({});
+        let null = json!({"k": null});
+        let one = json!({"k": 1});
+
+        assert_eq!(asc.compare(&missing, &one), Ordering::Greater);
+        assert_eq!(asc.compare(&missing, &null), Ordering::Equal);
+        assert_eq!(desc.compare(&missing, &one), Ordering::Less);
+        assert_eq!(asc_first.compare(&null, &one), Ordering::Less);
+    }
+
+    #[test]
+    fn test_sort_stable() {
+        let spec = parse_order_spec("k DESC", "ASC").unwrap();
+        let mut array = vec![
+            json!({"k": 1, "i": 0}),
+            json!({"i": 1}),
+            json!({"k": 2, "i": 2}),
+            json!({"k": 1, "i": 3}),
+        ];
+        spec.sort(&mut array);
+        assert_eq!(
+            array,
+            vec![
+                json!({"i": 1}),
+                json!({"k": 2, "i": 2}),
+                json!({"k": 1, "i": 0}),
+                json!({"k": 1, "i": 3}),
+            ]
+        );
+        assert!(spec.is_sorted(&array));
+    }
+
+    #[test]
+    fn test_is_sorted() {
+        let spec = parse_order_spec("k DESC", "ASC").unwrap();
+        assert!(spec.is_sorted(&[json!({}), json!({"k": 2}), json!({"k": 2}), json!({"k": 1})]));
+        assert!(!spec.is_sorted(&[json!({"k": 1}), json!({"k": 2})]));
+    }
+}
@@ -0,0 +1,360 @@
+// jsonb_ivm - Trigger Generation Module
+//
+// Generates the cascade triggers of a projection from its registered
+// dependencies, so maintenance code never drifts from the declarations.
+
+use pgrx::prelude::*;
+
+use crate::registry::{load_dependencies, DependencyKind, RegisteredDependency};
+
+extension_sql!(
+    r"
+-- Triggers generated by jsonb_ivm_install_triggers, for clean removal
+CREATE TABLE jsonb_ivm.installed_trigger (
+    dependency_id bigint PRIMARY KEY,
+    target_table regclass NOT NULL,
+    source_table regclass NOT NULL,
+    trigger_name name NOT NULL,
+    function_name name NOT NULL,
+    installed_at timestamptz NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE jsonb_ivm.installed_trigger IS
+    'Cascade triggers generated from jsonb_ivm.dependency, by projection table';
+
+CREATE INDEX installed_trigger_target_idx ON jsonb_ivm.installed_trigger (target_table);
+
+SELECT pg_catalog.pg_extension_config_dump('jsonb_ivm.installed_trigger', '');
+",
+    name = "installed_triggers",
+    requires = ["projection_registry"]
+);
+
+impl RegisteredDependency {
+    /// Name of the trigger created on the source table
+    fn trigger_name(&self) -> String {
+        format!("jsonb_ivm_dependency_{}", self.id)
+    }
+
+    /// Name of the trigger function, created in the `jsonb_ivm` schema
+    fn function_name(&self) -> String {
+        format!("dependency_{}_trigger", self.id)
+    }
+
+    /// `CREATE FUNCTION` statement of the trigger function
+    fn function_sql(&self) -> String {
+        let body = match self.kind {
+            DependencyKind::Object => self.object_body(),
+            DependencyKind::Array => self.array_body(),
+        };
+
+        format!(
+            "CREATE OR REPLACE FUNCTION jsonb_ivm.{name}() RETURNS trigger
+LANGUAGE plpgsql AS $jsonb_ivm$
+BEGIN
+{body}
+    RETURN NULL;
+END
+$jsonb_ivm$",
+            name = self.function_name()
+        )
+    }
+
+    /// `CREATE TRIGGER` statement attaching the function to the source table
+    fn trigger_sql(&self) -> String {
+        format!(
+            "CREATE TRIGGER {trigger} AFTER INSERT OR UPDATE OR DELETE ON {source}
+FOR EACH ROW EXECUTE FUNCTION jsonb_ivm.{function}()",
+            trigger = self.trigger_name(),
+            source = self.source_table,
+            function = self.function_name()
+        )
+    }
+
+    /// Patch the embedded object into every projection row referencing the
+    /// source row; a deleted source row leaves `null` behind
+    fn object_body(&self) -> String {
+        let Self {
+            function_schema: schema,
+            source_key,
+            source_data,
+            target_table,
+            target_data,
+            fk_column,
+            embed_path,
+            ..
+        } = self;
+
+        format!(
+            "    IF TG_OP = 'DELETE' THEN
+        UPDATE {target_table}
+        SET {target_data} = jsonb_set({target_data}, {embed_path}, 'null'::jsonb, false)
+        WHERE {fk_column} = OLD.{source_key};
+    ELSIF NEW.{source_data} IS NOT NULL THEN
+        UPDATE {target_table}
+        SET {target_data} = {schema}.jsonb_smart_patch_nested({target_data}, NEW.{source_data}, {embed_path})
+        WHERE {fk_column} = NEW.{source_key};
+    END IF;"
+        )
+    }
+
+    /// Keep the source document in the array of the projection row it
+    /// references; moving a row to another parent deletes and re-inserts it
+    fn array_body(&self) -> String {
+        let Self {
+            function_schema: schema,
+            source_data,
+            target_table,
+            target_key,
+            target_data,
+            fk_column,
+            embed_path,
+            ..
+        } = self;
+        let match_key = self.match_key.as_deref().unwrap_or("NULL");
+
+        let insert = format!(
+            "UPDATE {target_table}
+        SET {target_data} = {schema}.jsonb_array_insert_where({target_data}, {embed_path}, NEW.{source_data}, NULL, NULL)
+        WHERE {target_key} = NEW.{fk_column} AND NEW.{source_data} IS NOT NULL;"
+        );
+        let delete = format!(
+            "UPDATE {target_table}
+        SET {target_data} = {schema}.jsonb_array_delete_where({target_data}, {embed_path}, {match_key}, OLD.{source_data} -> {match_key})
+        WHERE {target_key} = OLD.{fk_column} AND OLD.{source_data} -> {match_key} IS NOT NULL;"
+        );
+        let update = format!(
+            "UPDATE {target_table}
+        SET {target_data} = {schema}.jsonb_array_update_where({target_data}, {embed_path}, {match_key}, NEW.{source_data} -> {match_key}, NEW.{source_data}, 'replace')
+        WHERE {target_key} = NEW.{fk_column} AND NEW.{source_data} -> {match_key} IS NOT NULL;"
+        );
+
+        format!(
+            "    IF TG_OP = 'INSERT' THEN
+        {insert}
+    ELSIF TG_OP = 'DELETE' THEN
+        {delete}
+    ELSIF OLD.{fk_column} IS DISTINCT FROM NEW.{fk_column} THEN
+        {delete}
+        {insert}
+    ELSE
+        {update}
+    END IF;"
+        )
+    }
+}
+
+/// Generate the cascade triggers maintaining a projection table
+///
+/// For every dependency registered for `target_table`, creates a trigger
+/// function `jsonb_ivm.dependency_<id>_trigger()` and an `AFTER INSERT OR
+/// UPDATE OR DELETE` row trigger `jsonb_ivm_dependency_<id>` on the source
+/// table. Installing again regenerates the triggers from the current
+/// declarations.
+///
+/// # Arguments
+///
+/// * `target_table` - Projection table whose dependencies to maintain (e.g., `"tv_feed"`)
+///
+/// # Returns
+///
+/// Number of triggers installed
+///
+/// # Generated maintenance
+///
+/// - `object` dependencies: inserting or updating a source row patches its
+///   document into the referencing projection rows with
+///   `jsonb_smart_patch_nested`; deleting it sets the embedded object to `null`
+/// - `array` dependencies: source rows are added with `jsonb_array_insert_where`,
+///   replaced with `jsonb_array_update_where` and removed with
+///   `jsonb_array_delete_where`; changing the foreign key moves the element
+///   to the new parent
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT jsonb_ivm_register_dependency('tv_post', 'tv_feed', 'posts', 'array', 'id', 'fk_feed');
+/// SELECT jsonb_ivm_install_triggers('tv_feed');
+/// -- Result: 1
+///
+/// INSERT INTO tv_post VALUES (1, 10, '{"id": 1, "title": "Hello"}');
+/// -- tv_feed row 10: {"posts": [{"id": 1, "title": "Hello"}]}
+/// ```
+///
+/// # Notes
+/// - Elements are appended; arrays kept in a sort order still need a custom trigger
+/// - Updates to the projection rows fire their own triggers, so projections
+///   embedded in other projections cascade
+/// - Generated functions live in the `jsonb_ivm` schema: uninstall them before
+///   `DROP EXTENSION`, or drop it with `CASCADE`
+#[pg_extern]
+pub fn jsonb_ivm_install_triggers(target_table: &str) -> i32 {
+    let dependencies = load_dependencies(Some(target_table));
+    if dependencies.is_empty() {
+        error!("Table '{}' has no registered dependencies", target_table);
+    }
+
+    jsonb_ivm_uninstall_triggers(target_table);
+
+    for dependency in &dependencies {
+        Spi::run(&dependency.function_sql()).unwrap_or_else(|e| error!("{}", e));
+        Spi::run(&dependency.trigger_sql()).unwrap_or_else(|e| error!("{}", e));
+
+        Spi::run_with_args(
+            "INSERT INTO jsonb_ivm.installed_trigger
+                 (dependency_id, target_table, source_table, trigger_name, function_name)
+             VALUES ($1, $2::regclass, $3::regclass, $4, $5)",
+            &[
+                dependency.id.into(),
+                dependency.target_table.as_str().into(),
+                dependency.source_table.as_str().into(),
+                dependency.trigger_name().into(),
+                dependency.function_name().into(),
+            ],
+        )
+        .unwrap_or_else(|e| error!("{}", e));
+    }
+
+    i32::try_from(dependencies.len()).unwrap_or(i32::MAX)
+}
+
+/// Remove the cascade triggers generated for a projection table
+///
+/// Drops the triggers and trigger functions recorded by
+/// `jsonb_ivm_install_triggers`, including those of dependencies deleted
+/// since installation. Registrations are kept.
+///
+/// # Arguments
+///
+/// * `target_table` - Projection table (e.g., `"tv_feed"`)
+///
+/// # Returns
+///
+/// Number of triggers removed (0 if none were installed)
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT jsonb_ivm_uninstall_triggers('tv_feed');
+/// ```
+#[pg_extern]
+pub fn jsonb_ivm_uninstall_triggers(target_table: &str) -> i32 {
+    let installed: Vec<(String, String, String)> = Spi::connect(|client| {
+        client
+            .select(
+                "SELECT quote_ident(trigger_name), source_table::text, quote_ident(function_name)
+                 FROM jsonb_ivm.installed_trigger
+                 WHERE target_table = $1::regclass",
+                None,
+                &[target_table.into()],
+            )?
+            .map(|row| {
+                Ok((
+                    row.get::<String>(1)?.unwrap_or_default(),
+                    row.get::<String>(2)?.unwrap_or_default(),
+                    row.get::<String>(3)?.unwrap_or_default(),
+                ))
+            })
+            .collect::<Result<Vec<_>, spi::Error>>()
+    })
+    .unwrap_or_else(|e| error!("{}", e));
+
+    for (trigger_name, source_table, function_name) in &installed {
+        Spi::run(&format!(
+            "DROP TRIGGER IF EXISTS {trigger_name} ON {source_table}"
+        ))
+        .unwrap_or_else(|e| error!("{}", e));
+        Spi::run(&format!(
+            "DROP FUNCTION IF EXISTS jsonb_ivm.{function_name}()"
+        ))
+        .unwrap_or_else(|e| error!("{}", e));
+    }
+
+    Spi::run_with_args(
+        "DELETE FROM jsonb_ivm.installed_trigger WHERE target_table = $1::regclass",
+        &[target_table.into()],
+    )
+    .unwrap_or_else(|e| error!("{}", e));
+
+    i32::try_from(installed.len()).unwrap_or(i32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post_dependency() -> RegisteredDependency {
+        RegisteredDependency {
+            id: 7,
+            kind: DependencyKind::Array,
+            function_schema: "public".to_string(),
+            source_table: "tv_post".to_string(),
+            source_key: "pk".to_string(),
+            source_data: "data".to_string(),
+            target_table: "tv_feed".to_string(),
+            target_key: "id".to_string(),
+            target_data: "doc".to_string(),
+            fk_column: "fk_feed".to_string(),
+            embed_path: "'posts'".to_string(),
+            match_key: Some("'id'".to_string()),
+        }
+    }
+
+    fn company_dependency() -> RegisteredDependency {
+        RegisteredDependency {
+            id: 3,
+            kind: DependencyKind::Object,
+            source_table: "v_company".to_string(),
+            target_table: "tv_user".to_string(),
+            target_key: "pk".to_string(),
+            target_data: "data".to_string(),
+            fk_column: "fk_company".to_string(),
+            embed_path: "'{company}'::text[]".to_string(),
+            match_key: None,
+            ..post_dependency()
+        }
+    }
+
+    #[test]
+    fn test_generated_names() {
+        let dependency = post_dependency();
+        assert_eq!(dependency.trigger_name(), "jsonb_ivm_dependency_7");
+        assert_eq!(dependency.function_name(), "dependency_7_trigger");
+        assert_eq!(
+            dependency.trigger_sql(),
+            "CREATE TRIGGER jsonb_ivm_dependency_7 AFTER INSERT OR UPDATE OR DELETE ON tv_post
+FOR EACH ROW EXECUTE FUNCTION jsonb_ivm.dependency_7_trigger()"
+        );
+    }
+
+    #[test]
+    fn test_object_trigger_function() {
+        let sql = company_dependency().function_sql();
+        assert!(sql.starts_with("CREATE OR REPLACE FUNCTION jsonb_ivm.dependency_3_trigger()"));
+        assert!(sql.contains(
+            "SET data = public.jsonb_smart_patch_nested(data, NEW.data, '{company}'::text[])
+        WHERE fk_company = NEW.pk;"
+        ));
+        assert!(sql.contains(
+            "SET data = jsonb_set(data, '{company}'::text[], 'null'::jsonb, false)
+        WHERE fk_company = OLD.pk;"
+        ));
+    }
+
+    #[test]
+    fn test_array_trigger_function() {
+        let sql = post_dependency().function_sql();
+        assert!(sql.contains(
+            "public.jsonb_array_insert_where(doc, 'posts', NEW.data, NULL, NULL)
+        WHERE id = NEW.fk_feed"
+        ));
+        assert!(sql.contains(
+            "public.jsonb_array_update_where(doc, 'posts', 'id', NEW.data -> 'id', NEW.data, 'replace')"
+        ));
+        assert!(sql.contains(
+            "public.jsonb_array_delete_where(doc, 'posts', 'id', OLD.data -> 'id')
+        WHERE id = OLD.fk_feed"
+        ));
+        assert!(sql.contains("ELSIF OLD.fk_feed IS DISTINCT FROM NEW.fk_feed THEN"));
+    }
+}
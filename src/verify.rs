@@ -0,0 +1,363 @@
+// jsonb_ivm - Projection Verification Module
+//
+// Detects drift between stored projection documents and the documents their
+// canonical query produces.
+
+use pgrx::prelude::*;
+use pgrx::JsonB;
+use serde_json::Value;
+
+use crate::diff::{reconcile, structural_diff, Difference, UnorderedArrays};
+
+/// Compare stored projection documents with their canonical definition
+///
+/// Runs `rebuild_query`, matches its rows to the rows of `target_table` by
+/// key and reports every document that differs, with a path-level diff.
+/// Arrays maintained through registered `array` dependencies are compared
+/// regardless of order, their elements paired by `match_key`: incremental
+/// maintenance appends elements, so their order may legitimately differ from
+/// the canonical query's.
+///
+/// # Arguments
+///
+/// * `target_table` - Projection table to check (e.g., `"tv_user"`)
+/// * `rebuild_query` - Query returning `(key, document)` rows; defaults to the
+///   `definition` registered with `jsonb_ivm_register_projection`
+/// * `key_column` - Key column of `target_table`; defaults to the registered one (or `pk`)
+/// * `sample_pct` - Percentage of keys to check (default 100); the same keys
+///   are sampled on both sides, so missing and extra rows are still reported
+///
+/// # Returns
+///
+/// One row per drifted document: its `key` and a `diff` array of
+/// `{"path", "stored", "expected"}` entries, where a missing side is omitted.
+/// A row missing from `target_table` has a single entry with path `""` and no
+/// `stored` value; a row absent from the canonical query has no `expected` value.
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT * FROM jsonb_ivm_verify('tv_user',
+///     'SELECT u.id, jsonb_build_object(''id'', u.id, ''company'', c.data)
+///      FROM users u LEFT JOIN v_company c ON c.pk = u.fk_company');
+/// --  key |                                   diff
+/// -- -----+--------------------------------------------------------------------------
+/// --  42  | [{"path": "company.name", "stored": "ACME", "expected": "ACME Corp"}]
+///
+/// -- Spot-check 5% of a large projection against its registered definition
+/// SELECT count(*) FROM jsonb_ivm_verify('tv_feed', sample_pct => 5);
+/// ```
+///
+/// # Notes
+/// - Numbers compare by value (`1` equals `1.0`), as in `jsonb`; integers
+///   keep every digit, decimals compare at `float8` precision
+/// - Sampling hashes the key; the canonical query still runs in full
+#[pg_extern]
+pub fn jsonb_ivm_verify(
+    target_table: &str,
+    rebuild_query: default!(Option<&str>, "NULL"),
+    key_column: default!(Option<&str>, "NULL"),
+    sample_pct: default!(f64, 100.0),
+) -> TableIterator<'static, (name!(key, String), name!(diff, JsonB))> {
+    if !(sample_pct > 0.0 && sample_pct <= 100.0) {
+        error!(
+            "sample_pct must be greater than 0 and at most 100, got {}",
+            sample_pct
+        );
+    }
+
+    let ProjectionSource {
+        table,
+        key,
+        data,
+        definition,
+        ..
+    } = projection_source(target_table, rebuild_query, key_column);
+    let Some(query) = definition else {
+        error!(
+            "No rebuild query given and no definition registered for '{}'",
+            target_table
+        );
+    };
+    let unordered = unordered_arrays(target_table);
+
+    let sql = format!(
+        "SELECT COALESCE(s.key, r.key) AS key,
+                s.key IS NOT NULL AS in_stored, s.doc AS stored,
+                r.key IS NOT NULL AS in_canonical, r.doc AS expected
+         FROM (SELECT {key}::text AS key, {data} AS doc FROM {table}) s
+         FULL JOIN (SELECT q.key::text AS key, q.doc::jsonb AS doc
+                    FROM ({query}) AS q(key, doc)) r ON r.key = s.key
+         WHERE ($1 >= 100 OR abs((hashtext(COALESCE(s.key, r.key)))::bigint) % 10000 < $1 * 100)
+           AND (s.key IS NULL OR r.key IS NULL OR s.doc IS DISTINCT FROM r.doc)
+         ORDER BY 1"
+    );
+
+    let candidates = Spi::connect(|client| {
+        client
+            .select(&sql, None, &[sample_pct.into()])?
+            .map(|row| {
+                let document = |present: &str, doc: &str| -> Result<Option<Value>, spi::Error> {
+                    Ok(row
+                        .get_by_name::<bool, _>(present)?
+                        .unwrap_or(false)
+                        .then(|| row.get_by_name::<JsonB, _>(doc))
+                        .transpose()?
+                        .map(|doc| doc.map_or(Value::Null, |doc| doc.0)))
+                };
+                Ok((
+                    row.get_by_name::<String, _>("key")?.unwrap_or_default(),
+                    document("in_stored", "stored")?,
+                    document("in_canonical", "expected")?,
+                ))
+            })
+            .collect::<Result<Vec<_>, spi::Error>>()
+    })
+    .unwrap_or_else(|e| error!("{}", e));
+
+    TableIterator::new(
+        candidates
+            .into_iter()
+            .filter_map(move |(key, stored, expected)| {
+                let diff = row_diff(stored.as_ref(), expected.as_ref(), &unordered);
+                (!diff.is_empty()).then(|| {
+                    let entries = diff.iter().map(Difference::to_value).collect();
+                    (key, JsonB(Value::Array(entries)))
+                })
+            }),
+    )
+}
+
+/// Repair one drifted projection document with a minimal patch
+///
+/// Compares the stored document of `key` with `canonical` and writes only
+/// when they differ. The written document keeps every stored value that
+/// already matches, including the stored order of arrays maintained by
+/// registered `array` dependencies, so a repair does not reshuffle them.
+///
+/// # Arguments
+///
+/// * `target_table` - Projection table (e.g., `"tv_user"`)
+/// * `key` - Key of the row to repair, as text (e.g., `'42'`)
+/// * `canonical` - The document the row should hold
+///
+/// # Returns
+///
+/// The differences that were repaired, in the `jsonb_ivm_verify` diff format;
+/// `[]` when the row was already consistent and left untouched
+///
+/// # Examples
+///
+/// ```sql
+/// SELECT jsonb_ivm_repair('tv_user', '42', '{"id": 42, "company": {"id": 1, "name": "ACME Corp"}}');
+/// -- [{"path": "company.name", "stored": "ACME", "expected": "ACME Corp"}]
+///
+/// -- Repair everything jsonb_ivm_verify reports
+/// SELECT v.key, jsonb_ivm_repair('tv_user', v.key, c.doc)
+/// FROM jsonb_ivm_verify('tv_user') v
+/// JOIN canonical_users c ON c.id::text = v.key;
+/// ```
+///
+/// # Notes
+/// - Consistent rows are not updated, so they fire no triggers or cascades
+/// - The row is locked while compared; missing rows raise an error
+#[allow(clippy::needless_pass_by_value)]
+#[pg_extern]
+pub fn jsonb_ivm_repair(target_table: &str, key: &str, canonical: JsonB) -> JsonB {
+    let ProjectionSource {
+        table,
+        key: key_column,
+        key_type,
+        data,
+        ..
+    } = projection_source(target_table, None, None);
+
+    // FOR UPDATE needs a read-write client
+    let stored = Spi::connect_mut(|client| {
+        client
+            .update(
+                &format!(
+                    "SELECT {data} AS doc FROM {table}
+                     WHERE {key_column} = $1::text::{key_type}
+                     FOR UPDATE"
+                ),
+                None,
+                &[key.into()],
+            )?
+            .next()
+            .map(|row| row.get_by_name::<JsonB, _>("doc"))
+            .transpose()
+    })
+    .unwrap_or_else(|e| error!("{}", e))
+    .unwrap_or_else(|| error!("Row '{}' not found in '{}'", key, target_table))
+    .map_or(Value::Null, |doc| doc.0);
+
+    let unordered = unordered_arrays(target_table);
+    let diff = structural_diff(&stored, &canonical.0, &unordered);
+
+    if !diff.is_empty() {
+        let repaired = reconcile(&stored, &canonical.0, &unordered);
+        Spi::run_with_args(
+            &format!(
+                "UPDATE {table} SET {data} = $1
+                 WHERE {key_column} = $2::text::{key_type}"
+            ),
+            &[JsonB(repaired).into(), key.into()],
+        )
+        .unwrap_or_else(|e| error!("{}", e));
+    }
+
+    JsonB(Value::Array(
+        diff.iter().map(Difference::to_value).collect(),
+    ))
+}
+
+/// Differences between the stored and canonical versions of one row
+///
+/// `None` means the row is missing on that side.
+fn row_diff(
+    stored: Option<&Value>,
+    expected: Option<&Value>,
+    unordered: &UnorderedArrays,
+) -> Vec<Difference> {
+    match (stored, expected) {
+        (Some(stored), Some(expected)) => structural_diff(stored, expected, unordered),
+        (None, None) => Vec::new(),
+        (stored, expected) => vec![Difference {
+            path: String::new(),
+            stored: stored.cloned(),
+            expected: expected.cloned(),
+        }],
+    }
+}
+
+/// Where the documents of a projection table live
+pub struct ProjectionSource {
+    /// Table name, quoted as needed
+    pub table: String,
+    /// Quoted key column
+    pub key: String,
+    /// SQL type of the key column
+    pub key_type: String,
+    /// Quoted document column
+    pub data: String,
+    /// Canonical `(key, document)` query
+    pub definition: Option<String>,
+}
+
+/// Read the registration of `target_table`
+///
+/// Arguments take precedence over the registration; unregistered tables use
+/// the default `pk` and `data` columns.
+pub fn projection_source(
+    target_table: &str,
+    rebuild_query: Option<&str>,
+    key_column: Option<&str>,
+) -> ProjectionSource {
+    let source = Spi::connect(|client| {
+        client
+            .select(
+                "SELECT x.t::text AS table_name,
+                        quote_ident(k.name) AS key_column,
+                        format_type(a.atttypid, a.atttypmod) AS key_type,
+                        quote_ident(COALESCE(p.data_column, 'data')) AS data_column,
+                        COALESCE($3, p.definition) AS definition
+                 FROM (SELECT $1::regclass AS t) x
+                 LEFT JOIN jsonb_ivm.projection p ON p.table_name = x.t
+                 CROSS JOIN LATERAL (SELECT COALESCE($2, p.key_column, 'pk')::name AS name) k
+                 LEFT JOIN pg_catalog.pg_attribute a
+                        ON a.attrelid = x.t AND a.attname = k.name
+                       AND a.attnum > 0 AND NOT a.attisdropped",
+                None,
+                &[target_table.into(), key_column.into(), rebuild_query.into()],
+            )?
+            .next()
+            .map(|row| {
+                let text = |name: &str| row.get_by_name::<String, _>(name);
+                Ok::<_, spi::Error>((
+                    text("table_name")?.unwrap_or_default(),
+                    text("key_column")?.unwrap_or_default(),
+                    text("key_type")?,
+                    text("data_column")?.unwrap_or_default(),
+                    text("definition")?,
+                ))
+            })
+            .transpose()
+    })
+    .unwrap_or_else(|e| error!("{}", e))
+    .unwrap_or_else(|| error!("Table '{}' not found", target_table));
+
+    let (table, key, key_type, data, definition) = source;
+    let Some(key_type) = key_type else {
+        error!("Table '{}' has no column {}", target_table, key);
+    };
+
+    ProjectionSource {
+        table,
+        key,
+        key_type,
+        data,
+        // Allow a trailing semicolon: the query is embedded as a subquery
+        definition: definition.map(|query| query.trim().trim_end_matches(';').to_string()),
+    }
+}
+
+/// Arrays of `target_table` maintained by registered array dependencies
+fn unordered_arrays(target_table: &str) -> UnorderedArrays {
+    Spi::connect(|client| {
+        client
+            .select(
+                "SELECT array_to_string(embed_path, '.') AS path, match_key
+                 FROM jsonb_ivm.dependency
+                 WHERE target_table = $1::regclass AND kind = 'array'",
+                None,
+                &[target_table.into()],
+            )?
+            .map(|row| {
+                Ok((
+                    row.get_by_name::<String, _>("path")?.unwrap_or_default(),
+                    row.get_by_name::<String, _>("match_key")?,
+                ))
+            })
+            .collect::<Result<UnorderedArrays, spi::Error>>()
+    })
+    .unwrap_or_else(|e| error!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_row_diff_missing_rows() {
+        let doc = json!({"id": 1});
+        let unordered = UnorderedArrays::new();
+
+        let missing = row_diff(None, Some(&doc), &unordered);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(
+            missing[0].to_value(),
+            json!({"path": "", "expected": {"id": 1}})
+        );
+
+        let extra = row_diff(Some(&doc), None, &unordered);
+        assert_eq!(
+            extra[0].to_value(),
+            json!({"path": "", "stored": {"id": 1}})
+        );
+    }
+
+    #[test]
+    fn test_row_diff_ignores_unordered_arrays() {
+        let unordered = UnorderedArrays::from([("posts".to_string(), Some("id".to_string()))]);
+        let stored = json!({"posts": [{"id": 2}, {"id": 1}]});
+        let expected = json!({"posts": [{"id": 1}, {"id": 2}]});
+
+        assert!(row_diff(Some(&stored), Some(&expected), &unordered).is_empty());
+        assert_eq!(
+            row_diff(Some(&stored), Some(&expected), &UnorderedArrays::new()).len(),
+            2
+        );
+    }
+}
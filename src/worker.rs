@@ -0,0 +1,216 @@
+// jsonb_ivm - Queue Worker Module
+//
+// Background worker draining the deferred change queue, so deferred cascades
+// are applied continuously without an external scheduler.
+
+use pgrx::bgworkers::{BackgroundWorker, BackgroundWorkerBuilder, SignalWakeFlags};
+use pgrx::prelude::*;
+use pgrx::{GucContext, GucFlags, GucRegistry, GucSetting};
+use std::ffi::CString;
+use std::time::Duration;
+
+use crate::decoding::consume;
+use crate::queue::flush_batch;
+
+extension_sql!(
+    r"
+-- Progress of the queue worker of this database (a single row)
+CREATE TABLE jsonb_ivm.worker_state (
+    singleton boolean PRIMARY KEY DEFAULT true CHECK (singleton),
+    pid integer NOT NULL,
+    started_at timestamptz NOT NULL,
+    last_flush_at timestamptz,
+    batches bigint NOT NULL DEFAULT 0,
+    rows_applied bigint NOT NULL DEFAULT 0,
+    changes_applied bigint NOT NULL DEFAULT 0
+);
+
+CREATE VIEW jsonb_ivm.queue_stats AS
+SELECT q.pending_changes,
+       q.pending_rows,
+       q.oldest_enqueued_at,
+       clock_timestamp() - q.oldest_enqueued_at AS lag,
+       a.pid IS NOT NULL AS worker_running,
+       w.pid AS worker_pid,
+       w.started_at AS worker_started_at,
+       w.last_flush_at,
+       w.batches,
+       w.rows_applied,
+       w.changes_applied
+FROM (
+    SELECT count(*) AS pending_changes,
+           count(DISTINCT (source_table, row_key)) AS pending_rows,
+           min(enqueued_at) AS oldest_enqueued_at
+    FROM jsonb_ivm.change_queue
+) q
+LEFT JOIN jsonb_ivm.worker_state w ON true
+LEFT JOIN pg_catalog.pg_stat_activity a
+       ON a.pid = w.pid AND a.backend_type = 'jsonb_ivm queue worker';
+
+COMMENT ON VIEW jsonb_ivm.queue_stats IS
+    'Deferred change queue backlog and progress of the jsonb_ivm queue worker';
+",
+    name = "queue_worker",
+    requires = ["change_queue"]
+);
+
+/// Database whose queue the worker drains; no worker is started when unset
+static WORKER_DATABASE: GucSetting<Option<CString>> = GucSetting::<Option<CString>>::new(None);
+
+/// Pause between polls once the queue is drained, in milliseconds
+static WORKER_NAPTIME: GucSetting<i32> = GucSetting::<i32>::new(1000);
+
+/// Source rows applied per worker transaction
+static WORKER_BATCH_SIZE: GucSetting<i32> = GucSetting::<i32>::new(1000);
+
+/// Logical replication slot the worker also consumes; none when unset
+static WORKER_SLOT: GucSetting<Option<CString>> = GucSetting::<Option<CString>>::new(None);
+
+/// Register the worker GUCs and, when preloaded, the queue worker
+///
+/// The worker only starts when `jsonb_ivm` is listed in
+/// `shared_preload_libraries` and `jsonb_ivm.worker_database` is set.
+pub fn init() {
+    GucRegistry::define_string_guc(
+        c"jsonb_ivm.worker_database",
+        c"Database whose deferred change queue the jsonb_ivm worker drains.",
+        c"The worker starts only when jsonb_ivm is in shared_preload_libraries and this is set.",
+        &WORKER_DATABASE,
+        GucContext::Postmaster,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        c"jsonb_ivm.worker_naptime",
+        c"Pause between polls of an empty deferred change queue.",
+        c"Full batches are followed by the next batch without pausing.",
+        &WORKER_NAPTIME,
+        10,
+        3_600_000,
+        GucContext::Sighup,
+        GucFlags::UNIT_MS,
+    );
+    GucRegistry::define_int_guc(
+        c"jsonb_ivm.worker_batch_size",
+        c"Source rows applied per jsonb_ivm worker transaction.",
+        c"All queued changes of a row are applied together, coalesced into one.",
+        &WORKER_BATCH_SIZE,
+        1,
+        1_000_000,
+        GucContext::Sighup,
+        GucFlags::default(),
+    );
+    GucRegistry::define_string_guc(
+        c"jsonb_ivm.worker_slot",
+        c"test_decoding replication slot whose changes the jsonb_ivm worker applies.",
+        c"Each worker transaction reads up to jsonb_ivm.worker_batch_size decoded changes.",
+        &WORKER_SLOT,
+        GucContext::Sighup,
+        GucFlags::default(),
+    );
+
+    // SAFETY: set by PostgreSQL for the duration of library preloading
+    let preloading = unsafe { pg_sys::process_shared_preload_libraries_in_progress };
+    if preloading && WORKER_DATABASE.get().is_some() {
+        BackgroundWorkerBuilder::new("jsonb_ivm queue worker")
+            .set_type("jsonb_ivm queue worker")
+            .set_library("jsonb_ivm")
+            .set_function("jsonb_ivm_queue_worker_main")
+            .enable_spi_access()
+            .set_restart_time(Some(Duration::from_secs(10)))
+            .load();
+    }
+}
+
+/// Entry point of the queue worker
+///
+/// Drains the queue (and the `jsonb_ivm.worker_slot` slot, when set) one batch
+/// per transaction, without pausing while batches come back full, then polls
+/// every `jsonb_ivm.worker_naptime`. A failing batch stops the worker, which
+/// the postmaster restarts after 10 seconds; the changes of the failed batch
+/// stay queued.
+#[pg_guard]
+#[unsafe(no_mangle)]
+pub extern "C-unwind" fn jsonb_ivm_queue_worker_main(_arg: pg_sys::Datum) {
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+
+    let database = WORKER_DATABASE
+        .get()
+        .and_then(|name| name.into_string().ok())
+        .unwrap_or_default();
+    BackgroundWorker::connect_worker_to_spi(Some(&database), None);
+    log!(
+        "jsonb_ivm queue worker started on database \"{}\"",
+        database
+    );
+
+    let mut recorded = false;
+    let mut naptime = Duration::ZERO;
+    while BackgroundWorker::wait_latch(Some(naptime)) {
+        if BackgroundWorker::sighup_received() {
+            // SAFETY: the main loop runs outside any transaction, as configuration reloads require
+            unsafe {
+                pg_sys::ConfigReloadPending = 0;
+                pg_sys::ProcessConfigFile(pg_sys::GucContext::PGC_SIGHUP);
+            }
+        }
+
+        let batch_size = WORKER_BATCH_SIZE.get();
+        let slot = WORKER_SLOT.get().and_then(|name| name.into_string().ok());
+        let record_start = !recorded;
+        let drained = BackgroundWorker::transaction(move || drain(batch_size, slot, record_start));
+        recorded |= drained.is_some();
+
+        naptime = if drained == Some(true) {
+            Duration::ZERO
+        } else {
+            Duration::from_millis(u64::try_from(WORKER_NAPTIME.get()).unwrap_or(1000))
+        };
+    }
+
+    log!("jsonb_ivm queue worker stopped");
+}
+
+/// One worker transaction: apply a batch and record the progress
+///
+/// Returns whether a batch came back full, or `None` while the extension is
+/// not installed in the worker's database (the worker idles until it is).
+fn drain(batch_size: i32, slot: Option<String>, record_start: bool) -> Option<bool> {
+    let installed =
+        Spi::get_one::<bool>("SELECT to_regclass('jsonb_ivm.change_queue') IS NOT NULL")
+            .unwrap_or_else(|e| error!("{}", e))
+            .unwrap_or(false);
+    if !installed {
+        return None;
+    }
+
+    // Decoding first, before this transaction writes anything
+    let consumed = slot.map_or(0, |slot| consume(&slot, batch_size));
+
+    if record_start {
+        let pid = i32::try_from(std::process::id()).unwrap_or_default();
+        Spi::run_with_args(
+            "INSERT INTO jsonb_ivm.worker_state (pid, started_at) VALUES ($1, now())
+             ON CONFLICT (singleton) DO UPDATE
+             SET pid = EXCLUDED.pid, started_at = EXCLUDED.started_at, last_flush_at = NULL,
+                 batches = 0, rows_applied = 0, changes_applied = 0",
+            &[pid.into()],
+        )
+        .unwrap_or_else(|e| error!("{}", e));
+    }
+
+    let flushed = flush_batch(batch_size);
+    if flushed.changes > 0 {
+        Spi::run_with_args(
+            "UPDATE jsonb_ivm.worker_state
+             SET last_flush_at = now(),
+                 batches = batches + 1,
+                 rows_applied = rows_applied + $1,
+                 changes_applied = changes_applied + $2",
+            &[flushed.rows.into(), flushed.changes.into()],
+        )
+        .unwrap_or_else(|e| error!("{}", e));
+    }
+
+    let batch_size = i64::from(batch_size);
+    Some(flushed.rows >= batch_size || consumed >= batch_size)
+}
@@ -229,6 +229,65 @@ BEGIN
 END $$;
 \timing off
 
+-- ============================================================================
+-- Benchmark 4: Sorted insertion into a 10k-element array
+-- ============================================================================
+
+\echo ''
+\echo '=== Benchmark 4: Sorted insert into 10,000-element array (1000 inserts) ==='
+\echo ''
+
+CREATE TEMP TABLE bench_sorted_array AS
+SELECT jsonb_build_object(
+    'events',
+    jsonb_agg(jsonb_build_object('id', i, 'seq', i * 2) ORDER BY i)
+) AS data
+FROM generate_series(1, 10000) i;
+
+-- Sanity check: binary search requires the sort invariant
+SELECT jsonb_array_is_sorted(data, 'events', 'seq', 'ASC') AS events_sorted
+FROM bench_sorted_array;
+
+\echo '--- Linear scan (assume_sorted => false) ---'
+\timing on
+SELECT count(*)
+FROM bench_sorted_array, generate_series(1, 1000) n,
+LATERAL jsonb_array_insert_where(
+    data,
+    'events',
+    jsonb_build_object('id', 10000 + n, 'seq', (n * 37) % 20000 + 1),
+    'seq',
+    'ASC',
+    NULL,
+    false
+) AS result;
+\timing off
+
+\echo ''
+\echo '--- Binary search (assume_sorted => true) ---'
+\timing on
+SELECT count(*)
+FROM bench_sorted_array, generate_series(1, 1000) n,
+LATERAL jsonb_array_insert_where(
+    data,
+    'events',
+    jsonb_build_object('id', 10000 + n, 'seq', (n * 37) % 20000 + 1),
+    'seq',
+    'ASC',
+    NULL,
+    true
+) AS result;
+\timing off
+
+-- Both strategies must produce identical documents
+SELECT bool_and(
+    jsonb_array_insert_where(data, 'events', jsonb_build_object('id', -n, 'seq', n * 7), 'seq', 'ASC', NULL, false)
+    = jsonb_array_insert_where(data, 'events', jsonb_build_object('id', -n, 'seq', n * 7), 'seq', 'ASC', NULL, true)
+) AS binary_matches_linear
+FROM bench_sorted_array, generate_series(1, 100) n;
+
+DROP TABLE bench_sorted_array;
+
 \echo ''
 \echo '========================================'
 \echo 'Benchmark Complete'
@@ -238,5 +297,6 @@ END $$;
 \echo '  - Benchmark 1 (single update): Rust 2-3x faster'
 \echo '  - Benchmark 2 (cascade): Rust 3-5x faster'
 \echo '  - Benchmark 3 (stress): Rust 5-10x faster'
+\echo '  - Benchmark 4 (sorted insert): binary search dominated by (de)serialization, comparisons O(log n)'
 \echo ''
 \echo 'If Rust is <1.5x faster, reconsider approach.'
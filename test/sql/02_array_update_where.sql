@@ -73,4 +73,72 @@ SELECT jsonb_array_update_where(
 ) IS NULL
 AS test_null_handling;
 
+-- Test 7: Replace mode drops keys absent from updates
+SELECT jsonb_array_update_where(
+    '{"dns_servers": [{"id": 1, "ip": "1.1.1.1", "port": 53}]}'::jsonb,
+    'dns_servers',
+    'id',
+    '1'::jsonb,
+    '{"id": 1, "ip": "8.8.8.8"}'::jsonb,
+    'replace'
+)->'dns_servers'->0 = '{"id": 1, "ip": "8.8.8.8"}'::jsonb
+AS test_replace_mode;
+
+-- Test 8: Deep-merge mode merges nested objects
+SELECT jsonb_array_update_where(
+    '{"dns_servers": [{"id": 1, "config": {"ttl": 60, "proto": "udp"}}]}'::jsonb,
+    'dns_servers',
+    'id',
+    '1'::jsonb,
+    '{"config": {"ttl": 300}}'::jsonb,
+    'deep-merge'
+)->'dns_servers'->0 = '{"id": 1, "config": {"ttl": 300, "proto": "udp"}}'::jsonb
+AS test_deep_merge_mode;
+
+-- Test 9: Merge-patch mode deletes keys set to null
+SELECT jsonb_array_update_where(
+    '{"dns_servers": [{"id": 1, "ip": "1.1.1.1", "config": {"ttl": 60, "proto": "udp"}}]}'::jsonb,
+    'dns_servers',
+    'id',
+    '1'::jsonb,
+    '{"ip": null, "config": {"proto": null}}'::jsonb,
+    'merge-patch'
+)->'dns_servers'->0 = '{"id": 1, "config": {"ttl": 60}}'::jsonb
+AS test_merge_patch_mode;
+
+-- Test 10: Batch update with replace mode
+SELECT jsonb_array_update_where_batch(
+    '{"dns_servers": [{"id": 1, "ip": "1.1.1.1", "port": 53}, {"id": 2, "ip": "2.2.2.2"}]}'::jsonb,
+    'dns_servers',
+    'id',
+    '[{"match_value": 1, "updates": {"id": 1, "ip": "8.8.8.8"}}]'::jsonb,
+    'replace'
+)->'dns_servers' = '[{"id": 1, "ip": "8.8.8.8"}, {"id": 2, "ip": "2.2.2.2"}]'::jsonb
+AS test_batch_replace_mode;
+
+-- Test 11: Batch update matches string and UUID ids
+SELECT jsonb_array_update_where_batch(
+    '{"tags": [{"id": "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11", "name": "x"}, {"id": "b", "name": "y"}, {"id": 1, "name": "z"}]}'::jsonb,
+    'tags',
+    'id',
+    '[{"match_value": "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11", "updates": {"name": "X"}},
+      {"match_value": "b", "updates": {"name": "Y"}},
+      {"match_value": "1", "updates": {"name": "not an integer match"}}]'::jsonb
+)->'tags' = '[{"id": "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11", "name": "X"}, {"id": "b", "name": "Y"}, {"id": 1, "name": "z"}]'::jsonb
+AS test_batch_string_ids;
+
+-- Test 12: Unknown mode is rejected
+DO $$
+BEGIN
+    PERFORM jsonb_array_update_where(
+        '{"dns_servers": [{"id": 1}]}'::jsonb,
+        'dns_servers', 'id', '1'::jsonb, '{"ip": "8.8.8.8"}'::jsonb, 'upsert'
+    );
+    RAISE EXCEPTION 'expected invalid mode error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Invalid update mode%' THEN
+        RAISE;
+    END IF;
+END $$;
+
 \echo 'All tests should return TRUE'
@@ -129,6 +129,24 @@ SELECT data->'posts'->0->>'title' = 'Updated Post 10' AS title_updated,
        data->'posts'->1->>'title' = 'Post 20' AS other_post_unchanged
 FROM test_tv_feed WHERE pk_feed = 1;
 
+-- Test 13: Replace mode removes stale keys from the matched element
+UPDATE test_tv_feed
+SET data = jsonb_smart_patch_array(data, '{"id": 20, "title": "Post 20 v2"}'::jsonb, 'posts', 'id', '20'::jsonb, 'replace')
+WHERE pk_feed = 1;
+
+SELECT data->'posts'->1 = '{"id": 20, "title": "Post 20 v2"}'::jsonb AS element_replaced,
+       data->'posts'->0->'featured' = 'true'::jsonb AS other_post_unchanged
+FROM test_tv_feed WHERE pk_feed = 1;
+
+-- Test 14: Merge-patch mode deletes fields set to null
+UPDATE test_tv_feed
+SET data = jsonb_smart_patch_array(data, '{"featured": null}'::jsonb, 'posts', 'id', '10'::jsonb, 'merge-patch')
+WHERE pk_feed = 1;
+
+SELECT NOT (data->'posts'->0 ? 'featured') AS featured_removed,
+       data->'posts'->0->>'title' = 'Updated Post 10' AS title_preserved
+FROM test_tv_feed WHERE pk_feed = 1;
+
 -- Display summary
 \echo '==================================='
 \echo 'All jsonb_smart_patch tests passed!'
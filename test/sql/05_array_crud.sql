@@ -239,6 +239,759 @@ SELECT jsonb_array_length(data->'posts') = 3 AS post_inserted,
        data->'posts'->1->>'title' = 'Middle' AS correct_data
 FROM test_feed_insert WHERE pk_feed = 1;
 
+-- ===== BATCH DELETE + INSERT TESTS =====
+
+-- Test 19: Batch delete by integer IDs
+SELECT jsonb_array_delete_where_batch(
+    '{"following": [{"id": 1}, {"id": 2}, {"id": 3}, {"id": 4}]}'::jsonb,
+    'following',
+    'id',
+    '[2, 3]'::jsonb
+) = '{"following": [{"id": 1}, {"id": 4}]}'::jsonb AS test_delete_batch_basic;
+
+-- Test 20: Batch delete with mixed ID types and unknown IDs
+SELECT jsonb_array_delete_where_batch(
+    '{"items": [{"id": 1}, {"id": "aaa"}, {"id": "bbb"}]}'::jsonb,
+    'items',
+    'id',
+    '["aaa", 999, "zzz"]'::jsonb
+) = '{"items": [{"id": 1}, {"id": "bbb"}]}'::jsonb AS test_delete_batch_mixed;
+
+-- Test 21: Batch delete from non-existent array (unchanged)
+SELECT jsonb_array_delete_where_batch(
+    '{"other": "data"}'::jsonb,
+    'posts',
+    'id',
+    '[1, 2]'::jsonb
+) = '{"other": "data"}'::jsonb AS test_delete_batch_missing_array;
+
+-- Test 22: Batch insert without sort (append in order)
+SELECT jsonb_array_insert_batch(
+    '{"posts": [{"id": 1}]}'::jsonb,
+    'posts',
+    '[{"id": 2}, {"id": 3}]'::jsonb,
+    NULL,
+    NULL
+) = '{"posts": [{"id": 1}, {"id": 2}, {"id": 3}]}'::jsonb AS test_insert_batch_append;
+
+-- Test 23: Batch insert merged into DESC-sorted array
+SELECT jsonb_array_insert_batch(
+    '{"posts": [
+        {"id": 4, "created_at": "2025-01-04"},
+        {"id": 1, "created_at": "2025-01-01"}
+    ]}'::jsonb,
+    'posts',
+    '[
+        {"id": 2, "created_at": "2025-01-02"},
+        {"id": 5, "created_at": "2025-01-05"},
+        {"id": 3, "created_at": "2025-01-03"}
+    ]'::jsonb,
+    'created_at',
+    'DESC'
+) = '{"posts": [
+    {"id": 5, "created_at": "2025-01-05"},
+    {"id": 4, "created_at": "2025-01-04"},
+    {"id": 3, "created_at": "2025-01-03"},
+    {"id": 2, "created_at": "2025-01-02"},
+    {"id": 1, "created_at": "2025-01-01"}
+]}'::jsonb AS test_insert_batch_sorted_desc;
+
+-- Test 24: Batch insert matches repeated single inserts (ties go after existing)
+SELECT jsonb_array_insert_batch(
+    '{"items": [{"id": 1, "score": 10}, {"id": 2, "score": 20}]}'::jsonb,
+    'items',
+    '[{"id": 3, "score": 20}, {"id": 4, "score": 5}]'::jsonb,
+    'score',
+    'ASC'
+) = jsonb_array_insert_where(
+    jsonb_array_insert_where(
+        '{"items": [{"id": 1, "score": 10}, {"id": 2, "score": 20}]}'::jsonb,
+        'items',
+        '{"id": 3, "score": 20}'::jsonb,
+        'score',
+        'ASC'
+    ),
+    'items',
+    '{"id": 4, "score": 5}'::jsonb,
+    'score',
+    'ASC'
+) AS test_insert_batch_matches_single;
+
+-- Test 25: Batch insert creates array if it doesn't exist
+SELECT jsonb_array_insert_batch(
+    '{}'::jsonb,
+    'posts',
+    '[{"id": 1}]'::jsonb,
+    NULL,
+    NULL
+) = '{"posts": [{"id": 1}]}'::jsonb AS test_insert_batch_create_array;
+
+-- ===== ARRAY SYNC TESTS =====
+
+-- Test 26: Sync deletes, updates in place and appends new elements
+SELECT jsonb_array_sync(
+    '{"members": [{"id": 1, "role": "admin"}, {"id": 2, "role": "dev"}, {"id": 3}]}'::jsonb,
+    'members',
+    'id',
+    '[{"id": 3}, {"id": 1, "role": "owner"}, {"id": 4}]'::jsonb
+) = '{"members": [{"id": 1, "role": "owner"}, {"id": 3}, {"id": 4}]}'::jsonb AS test_sync_basic;
+
+-- Test 27: Sync with identical set is a no-op
+SELECT jsonb_array_sync(
+    '{"members": [{"id": 2}, {"id": 1}]}'::jsonb,
+    'members',
+    'id',
+    '[{"id": 1}, {"id": 2}]'::jsonb
+) = '{"members": [{"id": 2}, {"id": 1}]}'::jsonb AS test_sync_noop;
+
+-- Test 28: Sync creates array if it doesn't exist, and a NULL desired set
+-- (jsonb_agg over no rows: the last child was deleted) empties it
+SELECT jsonb_array_sync(
+    '{}'::jsonb,
+    'members',
+    'id',
+    '[{"id": 1}]'::jsonb
+) = '{"members": [{"id": 1}]}'::jsonb AS test_sync_create_array,
+jsonb_array_sync(
+    '{"members": [{"id": 1}], "member_count": 1}'::jsonb,
+    'members',
+    'id',
+    (SELECT jsonb_agg(m) FROM jsonb_array_elements('[{"id": 1}]') m WHERE false),
+    aggregates => '{"member_count": "count"}'
+) = '{"members": [], "member_count": 0}'::jsonb AS test_sync_last_child_deleted;
+
+-- Test 29: Sync summary reports changed keys
+SELECT summary = '{"inserted": [3], "updated": [1], "deleted": [2]}'::jsonb AS test_sync_summary
+FROM jsonb_array_sync_summary(
+    '{"members": [{"id": 1, "role": "admin"}, {"id": 2}]}'::jsonb,
+    'members',
+    'id',
+    '[{"id": 1, "role": "owner"}, {"id": 3}]'::jsonb
+);
+
+-- ===== BOUNDED ARRAY TESTS =====
+
+-- Test 30: DESC insert with max_len evicts from the tail (oldest)
+SELECT jsonb_array_insert_where(
+    '{"posts": [
+        {"id": 3, "created_at": "2025-01-03"},
+        {"id": 2, "created_at": "2025-01-02"},
+        {"id": 1, "created_at": "2025-01-01"}
+    ]}'::jsonb,
+    'posts',
+    '{"id": 4, "created_at": "2025-01-04"}'::jsonb,
+    'created_at',
+    'DESC',
+    3
+) = '{"posts": [
+    {"id": 4, "created_at": "2025-01-04"},
+    {"id": 3, "created_at": "2025-01-03"},
+    {"id": 2, "created_at": "2025-01-02"}
+]}'::jsonb AS test_insert_max_len_desc;
+
+-- Test 31: ASC insert with max_len evicts from the head (oldest)
+SELECT jsonb_array_insert_where(
+    '{"items": [{"id": 1, "score": 10}, {"id": 2, "score": 20}]}'::jsonb,
+    'items',
+    '{"id": 3, "score": 30}'::jsonb,
+    'score',
+    'ASC',
+    2
+) = '{"items": [{"id": 2, "score": 20}, {"id": 3, "score": 30}]}'::jsonb AS test_insert_max_len_asc;
+
+-- Test 32: Unsorted append with max_len evicts from the head
+SELECT jsonb_array_insert_where(
+    '{"recent": [{"id": 1}, {"id": 2}]}'::jsonb,
+    'recent',
+    '{"id": 3}'::jsonb,
+    NULL,
+    NULL,
+    2
+) = '{"recent": [{"id": 2}, {"id": 3}]}'::jsonb AS test_insert_max_len_append;
+
+-- Test 33: Batch insert with max_len
+SELECT jsonb_array_insert_batch(
+    '{"posts": [{"id": 2, "created_at": "2025-01-02"}]}'::jsonb,
+    'posts',
+    '[{"id": 1, "created_at": "2025-01-01"}, {"id": 3, "created_at": "2025-01-03"}]'::jsonb,
+    'created_at',
+    'DESC',
+    2
+) = '{"posts": [{"id": 3, "created_at": "2025-01-03"}, {"id": 2, "created_at": "2025-01-02"}]}'::jsonb AS test_insert_batch_max_len;
+
+-- Test 34: Truncate from the end (default)
+SELECT jsonb_array_truncate(
+    '{"posts": [{"id": 5}, {"id": 4}, {"id": 3}]}'::jsonb,
+    'posts',
+    2
+) = '{"posts": [{"id": 5}, {"id": 4}]}'::jsonb AS test_truncate_from_end;
+
+-- Test 35: Truncate from the start
+SELECT jsonb_array_truncate(
+    '{"posts": [{"id": 1}, {"id": 2}, {"id": 3}]}'::jsonb,
+    'posts',
+    2,
+    false
+) = '{"posts": [{"id": 2}, {"id": 3}]}'::jsonb AS test_truncate_from_start;
+
+-- Test 36: Truncate shorter array (unchanged)
+SELECT jsonb_array_truncate(
+    '{"posts": [{"id": 1}]}'::jsonb,
+    'posts',
+    10
+) = '{"posts": [{"id": 1}]}'::jsonb AS test_truncate_short_array;
+
+-- ===== BINARY SEARCH INSERT TESTS =====
+
+-- Test 37: assume_sorted gives the same result as the linear scan
+SELECT jsonb_array_insert_where(
+    '{"items": [{"id": 1, "score": 10}, {"id": 2, "score": 20}, {"id": 3, "score": 20}, {"id": 4, "score": 30}]}'::jsonb,
+    'items',
+    '{"id": 5, "score": 20}'::jsonb,
+    'score',
+    'ASC',
+    NULL,
+    true
+) = '{"items": [
+    {"id": 1, "score": 10},
+    {"id": 2, "score": 20},
+    {"id": 3, "score": 20},
+    {"id": 5, "score": 20},
+    {"id": 4, "score": 30}
+]}'::jsonb AS test_insert_binary_ties_after;
+
+-- Test 38: assume_sorted on DESC array
+SELECT jsonb_array_insert_where(
+    '{"posts": [{"id": 3, "created_at": "2025-01-03"}, {"id": 1, "created_at": "2025-01-01"}]}'::jsonb,
+    'posts',
+    '{"id": 2, "created_at": "2025-01-02"}'::jsonb,
+    'created_at',
+    'DESC',
+    NULL,
+    true
+) = '{"posts": [
+    {"id": 3, "created_at": "2025-01-03"},
+    {"id": 2, "created_at": "2025-01-02"},
+    {"id": 1, "created_at": "2025-01-01"}
+]}'::jsonb AS test_insert_binary_desc;
+
+-- Test 39: Sort invariant check
+SELECT jsonb_array_is_sorted(
+    '{"posts": [{"seq": 1}, {"seq": 2}, {"seq": 2}, {"title": "no seq"}]}'::jsonb,
+    'posts',
+    'seq'
+) AS test_is_sorted_true,
+NOT jsonb_array_is_sorted(
+    '{"posts": [{"seq": 2}, {"seq": 1}]}'::jsonb,
+    'posts',
+    'seq',
+    'ASC'
+) AS test_is_sorted_false,
+jsonb_array_is_sorted(
+    '{"posts": [{"seq": 2}, {"seq": 1}]}'::jsonb,
+    'posts',
+    'seq',
+    'DESC'
+) AS test_is_sorted_desc;
+
+-- ===== MULTI-KEY SORT TESTS =====
+
+-- Test 40: ORDER BY-style specification with several keys
+SELECT jsonb_array_insert_where(
+    '{"tasks": [
+        {"id": 1, "priority": 2, "created_at": "2025-01-01"},
+        {"id": 2, "priority": 1, "created_at": "2025-01-01"}
+    ]}'::jsonb,
+    'tasks',
+    '{"id": 3, "priority": 2, "created_at": "2025-01-02"}'::jsonb,
+    'priority DESC, created_at ASC NULLS LAST, id'
+) = '{"tasks": [
+    {"id": 1, "priority": 2, "created_at": "2025-01-01"},
+    {"id": 3, "priority": 2, "created_at": "2025-01-02"},
+    {"id": 2, "priority": 1, "created_at": "2025-01-01"}
+]}'::jsonb AS test_insert_multi_key;
+
+-- Test 41: Missing keys sort as NULLs (first for DESC, unless NULLS LAST)
+SELECT jsonb_array_insert_where(
+    '{"posts": [{"id": 2, "seq": 2}, {"id": 1, "seq": 1}]}'::jsonb,
+    'posts',
+    '{"id": 9}'::jsonb,
+    'seq',
+    'DESC'
+) = '{"posts": [{"id": 9}, {"id": 2, "seq": 2}, {"id": 1, "seq": 1}]}'::jsonb AS test_insert_nulls_first_desc,
+jsonb_array_insert_where(
+    '{"posts": [{"id": 2, "seq": 2}, {"id": 1, "seq": 1}]}'::jsonb,
+    'posts',
+    '{"id": 9, "seq": null}'::jsonb,
+    'seq DESC NULLS LAST'
+) = '{"posts": [{"id": 2, "seq": 2}, {"id": 1, "seq": 1}, {"id": 9, "seq": null}]}'::jsonb AS test_insert_nulls_last_desc;
+
+-- Test 42: Batch insert and sort check with a multi-key specification
+SELECT jsonb_array_insert_batch(
+    '{"tasks": [{"id": 1, "priority": 2}, {"id": 2, "priority": 1}]}'::jsonb,
+    'tasks',
+    '[{"id": 4, "priority": 1}, {"id": 3, "priority": 2}]'::jsonb,
+    'priority DESC, id'
+) = '{"tasks": [
+    {"id": 1, "priority": 2},
+    {"id": 3, "priority": 2},
+    {"id": 2, "priority": 1},
+    {"id": 4, "priority": 1}
+]}'::jsonb AS test_insert_batch_multi_key,
+jsonb_array_is_sorted(
+    '{"tasks": [{"id": 1, "priority": 2}, {"id": 3, "priority": 2}, {"id": 2, "priority": 1}]}'::jsonb,
+    'tasks',
+    'priority DESC, id'
+) AS test_is_sorted_multi_key;
+
+-- Test 43: Unknown sort direction raises an error
+DO $$
+BEGIN
+    PERFORM jsonb_array_insert_where('{}'::jsonb, 'tasks', '{"id": 1}'::jsonb, 'priority DOWN');
+    RAISE EXCEPTION 'expected an error for unknown sort direction';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Unknown sort direction%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+-- ===== TYPED SORT TESTS =====
+
+-- Test 44: timestamptz compares instants, not text (10:00+02 is before 09:00Z)
+SELECT jsonb_array_insert_where(
+    '{"events": [
+        {"id": 1, "at": "2025-01-01T10:00:00+02:00"},
+        {"id": 2, "at": "2025-01-01T09:00:00+00:00"}
+    ]}'::jsonb,
+    'events',
+    '{"id": 3, "at": "2025-01-01T08:30:00Z"}'::jsonb,
+    'at::timestamptz'
+) = '{"events": [
+    {"id": 1, "at": "2025-01-01T10:00:00+02:00"},
+    {"id": 3, "at": "2025-01-01T08:30:00Z"},
+    {"id": 2, "at": "2025-01-01T09:00:00+00:00"}
+]}'::jsonb AS test_insert_timestamptz;
+
+-- Test 45: date and numeric casts
+SELECT jsonb_array_insert_where(
+    '{"days": [{"d": "2025-1-9"}, {"d": "2025-10-01"}]}'::jsonb,
+    'days',
+    '{"d": "2025-2-1"}'::jsonb,
+    'd::date'
+) = '{"days": [{"d": "2025-1-9"}, {"d": "2025-2-1"}, {"d": "2025-10-01"}]}'::jsonb AS test_insert_date,
+jsonb_array_insert_where(
+    '{"lines": [{"amount": "10.5"}, {"amount": "9.75"}]}'::jsonb,
+    'lines',
+    '{"amount": "10"}'::jsonb,
+    'amount::numeric DESC'
+) = '{"lines": [{"amount": "10.5"}, {"amount": "10"}, {"amount": "9.75"}]}'::jsonb AS test_insert_numeric;
+
+-- Test 46: uuid compares the 128-bit value, not the text
+SELECT jsonb_array_is_sorted(
+    '{"items": [{"id": "a0000000-0000-0000-0000-000000000000"}, {"id": "B0000000-0000-0000-0000-000000000000"}]}'::jsonb,
+    'items',
+    'id::uuid'
+) AS test_is_sorted_uuid,
+NOT jsonb_array_is_sorted(
+    '{"items": [{"id": "a0000000-0000-0000-0000-000000000000"}, {"id": "B0000000-0000-0000-0000-000000000000"}]}'::jsonb,
+    'items',
+    'id'
+) AS test_is_sorted_uuid_as_json;
+
+-- Test 47: Values that are not valid input for the cast, and unsupported casts, raise an error
+DO $$
+BEGIN
+    PERFORM jsonb_array_insert_where(
+        '{"events": [{"at": "2025-01-01"}]}'::jsonb, 'events', '{"at": "yesterday-ish"}'::jsonb, 'at::timestamptz'
+    );
+    RAISE EXCEPTION 'expected an error for an invalid timestamptz';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'invalid timestamptz value ''yesterday-ish'' for sort key ''at''%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+DO $$
+BEGIN
+    PERFORM jsonb_array_insert_where(
+        '{"items": [{"price": 1}]}'::jsonb, 'items', '{"price": 2}'::jsonb, 'price::money'
+    );
+    RAISE EXCEPTION 'expected an error for an unsupported sort type';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Unsupported sort type ''money'' for key ''price''%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+-- ===== COLLATION TESTS =====
+
+-- Test 48: COLLATE "C" orders strings bytewise (uppercase first)
+SELECT jsonb_array_insert_where(
+    '{"members": [{"name": "Bob"}, {"name": "carol"}]}'::jsonb,
+    'members',
+    '{"name": "alice"}'::jsonb,
+    'name COLLATE "C"'
+) = '{"members": [{"name": "Bob"}, {"name": "alice"}, {"name": "carol"}]}'::jsonb AS test_insert_collate_c,
+jsonb_array_is_sorted(
+    '{"members": [{"name": "Bob"}, {"name": "alice"}]}'::jsonb,
+    'members',
+    'name',
+    'ASC',
+    'C'
+) AS test_is_sorted_collation,
+jsonb_array_is_sorted(
+    '{"members": [{"name": "Bob"}, {"name": "alice"}]}'::jsonb,
+    'members',
+    'name',
+    'ASC',
+    NULL
+) IS TRUE AS test_is_sorted_null_collation;
+
+-- Test 49: ICU collation places accented names where ORDER BY would (skipped without ICU)
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_collation WHERE collname = 'und-x-icu') THEN
+        RAISE NOTICE 'ICU not available, skipping collation test';
+        RETURN;
+    END IF;
+    IF jsonb_array_insert_where(
+        '{"members": [{"name": "Eve"}, {"name": "Zoé"}]}'::jsonb,
+        'members',
+        '{"name": "Élodie"}'::jsonb,
+        'name',
+        collation => 'und-x-icu'
+    ) <> '{"members": [{"name": "Élodie"}, {"name": "Eve"}, {"name": "Zoé"}]}'::jsonb THEN
+        RAISE EXCEPTION 'ICU collation order not applied';
+    END IF;
+END $$;
+
+-- Test 50: Unknown collation raises an error
+DO $$
+BEGIN
+    PERFORM jsonb_array_insert_where('{}'::jsonb, 'members', '{"name": "x"}'::jsonb, 'name COLLATE "no_such_collation"');
+    RAISE EXCEPTION 'expected an error for an unknown collation';
+EXCEPTION WHEN undefined_object THEN
+    NULL;
+END $$;
+
+-- ===== MOVE / REORDER TESTS =====
+
+-- Test 51: Move to a position (negative counts from the end, out of range clamps)
+SELECT jsonb_array_move_where(
+    '{"cards": [{"id": 1}, {"id": 2}, {"id": 3}]}'::jsonb,
+    'cards', 'id', '3'::jsonb, 0
+) = '{"cards": [{"id": 3}, {"id": 1}, {"id": 2}]}'::jsonb AS test_move_to_top,
+jsonb_array_move_where(
+    '{"cards": [{"id": 1}, {"id": 2}, {"id": 3}]}'::jsonb,
+    'cards', 'id', '1'::jsonb, -1
+) = '{"cards": [{"id": 2}, {"id": 3}, {"id": 1}]}'::jsonb AS test_move_to_last,
+jsonb_array_move_where(
+    '{"cards": [{"id": 1}, {"id": 2}, {"id": 3}]}'::jsonb,
+    'cards', 'id', '1'::jsonb, 99
+) = '{"cards": [{"id": 2}, {"id": 3}, {"id": 1}]}'::jsonb AS test_move_clamped,
+jsonb_array_move_where(
+    '{"cards": [{"id": 1}, {"id": 2}]}'::jsonb,
+    'cards', 'id', '9'::jsonb, 0
+) = '{"cards": [{"id": 1}, {"id": 2}]}'::jsonb AS test_move_no_match;
+
+-- Test 52: Move before / after another element
+SELECT jsonb_array_move_relative(
+    '{"cards": [{"id": 1}, {"id": 2}, {"id": 3}]}'::jsonb,
+    'cards', 'id', '1'::jsonb, after_value => '2'::jsonb
+) = '{"cards": [{"id": 2}, {"id": 1}, {"id": 3}]}'::jsonb AS test_move_after,
+jsonb_array_move_relative(
+    '{"cards": [{"id": 1}, {"id": 2}, {"id": 3}]}'::jsonb,
+    'cards', 'id', '3'::jsonb, before_value => '1'::jsonb
+) = '{"cards": [{"id": 3}, {"id": 1}, {"id": 2}]}'::jsonb AS test_move_before;
+
+-- Test 53: Move relative requires exactly one anchor
+DO $$
+BEGIN
+    PERFORM jsonb_array_move_relative('{"cards": [{"id": 1}]}'::jsonb, 'cards', 'id', '1'::jsonb);
+    RAISE EXCEPTION 'expected an error without an anchor';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'exactly one of%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+-- Test 54: Reorder by a list of ids (unlisted elements keep their order at the end)
+SELECT jsonb_array_reorder(
+    '{"tracks": [{"id": 1, "t": "a"}, {"id": 2, "t": "b"}, {"id": 3, "t": "c"}, {"id": 4, "t": "d"}]}'::jsonb,
+    'tracks',
+    'id',
+    '[3, 99, 1]'::jsonb
+) = '{"tracks": [{"id": 3, "t": "c"}, {"id": 1, "t": "a"}, {"id": 2, "t": "b"}, {"id": 4, "t": "d"}]}'::jsonb AS test_reorder;
+
+-- ===== SORT / DEDUPE TESTS =====
+
+-- Test 55: Stable full-array sort with a multi-key specification
+SELECT jsonb_array_sort(
+    '{"posts": [{"id": 1, "score": 5}, {"id": 2, "score": 9}, {"id": 3}, {"id": 4, "score": 5}]}'::jsonb,
+    'posts',
+    'score DESC NULLS LAST'
+) = '{"posts": [{"id": 2, "score": 9}, {"id": 1, "score": 5}, {"id": 4, "score": 5}, {"id": 3}]}'::jsonb AS test_sort_stable,
+jsonb_array_sort(NULL::jsonb, 'posts', 'id') IS NULL AS test_sort_null_target;
+
+-- Test 56: Sorted arrays agree with sorted inserts
+SELECT jsonb_array_is_sorted(
+    jsonb_array_insert_where(
+        jsonb_array_sort(
+            '{"events": [{"at": "2025-01-02"}, {"at": "2025-1-9"}, {"at": "2025-01-03"}]}'::jsonb,
+            'events',
+            'at::date DESC'
+        ),
+        'events',
+        '{"at": "2025-1-5"}'::jsonb,
+        'at::date DESC'
+    ),
+    'events',
+    'at::date DESC'
+) AS test_sort_then_insert_sorted;
+
+-- Test 57: Dedupe keeping the first or last occurrence
+SELECT jsonb_array_dedupe(
+    '{"members": [{"id": 1, "v": 1}, {"id": 2}, {"id": 1, "v": 2}, {"name": "no id"}]}'::jsonb,
+    'members',
+    'id'
+) = '{"members": [{"id": 1, "v": 1}, {"id": 2}, {"name": "no id"}]}'::jsonb AS test_dedupe_first,
+jsonb_array_dedupe(
+    '{"members": [{"id": 1, "v": 1}, {"id": 2}, {"id": 1, "v": 2}, {"name": "no id"}]}'::jsonb,
+    'members',
+    'id',
+    'last'
+) = '{"members": [{"id": 2}, {"id": 1, "v": 2}, {"name": "no id"}]}'::jsonb AS test_dedupe_last;
+
+-- ===== OPERATION STATS TESTS =====
+
+-- Test 58: Update stats distinguish missing, unchanged and changed elements
+SELECT (SELECT (matched, modified) FROM jsonb_array_update_where_stats(
+            '{"posts": [{"id": 1, "title": "A"}]}'::jsonb, 'posts', 'id', '2'::jsonb, '{"title": "B"}'::jsonb
+        )) = (0, false) AS test_update_stats_missing,
+       (SELECT (matched, modified) FROM jsonb_array_update_where_stats(
+            '{"posts": [{"id": 1, "title": "A"}]}'::jsonb, 'posts', 'id', '1'::jsonb, '{"title": "A"}'::jsonb
+        )) = (1, false) AS test_update_stats_unchanged,
+       (SELECT (matched, modified) FROM jsonb_array_update_where_batch_stats(
+            '{"posts": [{"id": 1}, {"id": 2}]}'::jsonb, 'posts', 'id',
+            '[{"match_value": 1, "updates": {"title": "A"}}, {"match_value": 3, "updates": {"title": "C"}}]'::jsonb
+        )) = (1, true) AS test_update_batch_stats;
+
+-- Test 59: Delete stats count removed elements
+SELECT (SELECT (matched, modified) FROM jsonb_array_delete_where_stats(
+            '{"posts": [{"id": 1}]}'::jsonb, 'posts', 'id', '999'::jsonb
+        )) = (0, false) AS test_delete_stats_missing,
+       (SELECT (result, matched, modified) FROM jsonb_array_delete_where_batch_stats(
+            '{"posts": [{"id": 1}, {"id": 2}, {"id": 3}]}'::jsonb, 'posts', 'id', '[1, 3, 7]'::jsonb
+        )) = ('{"posts": [{"id": 2}]}'::jsonb, 2, true) AS test_delete_batch_stats;
+
+-- Test 60: Insert stats report inserts undone by max_len eviction
+SELECT (SELECT (matched, modified) FROM jsonb_array_insert_where_stats(
+            '{"posts": [{"id": 3, "created_at": "2025-01-03"}]}'::jsonb, 'posts',
+            '{"id": 1, "created_at": "2025-01-01"}'::jsonb, 'created_at', 'DESC', 1
+        )) = (1, false) AS test_insert_stats_evicted,
+       (SELECT (matched, modified) FROM jsonb_array_insert_batch_stats(
+            '{"posts": []}'::jsonb, 'posts', '[{"id": 1}, {"id": 2}]'::jsonb, NULL, NULL
+        )) = (2, true) AS test_insert_batch_stats;
+
+-- ===== ON_MISSING POLICY TESTS =====
+
+-- Test 61: Default 'create' treats a missing array as empty
+SELECT jsonb_array_update_where(
+    '{"other": "data"}'::jsonb, 'posts', 'id', '1'::jsonb, '{"title": "A"}'::jsonb
+) = '{"other": "data"}'::jsonb AS test_update_missing_default,
+jsonb_array_sort('{"other": "data"}'::jsonb, 'posts', 'id') = '{"other": "data"}'::jsonb AS test_sort_missing_default,
+jsonb_array_insert_where(
+    '{"other": "data"}'::jsonb, 'posts', '{"id": 1}'::jsonb
+) = '{"other": "data", "posts": [{"id": 1}]}'::jsonb AS test_insert_missing_default;
+
+-- Test 62: 'ignore' leaves the document unchanged, even for inserts
+SELECT jsonb_array_insert_where(
+    '{"other": "data"}'::jsonb, 'posts', '{"id": 1}'::jsonb, on_missing => 'ignore'
+) = '{"other": "data"}'::jsonb AS test_insert_missing_ignore,
+jsonb_array_sync(
+    '{"other": "data"}'::jsonb, 'posts', 'id', '[{"id": 1}]'::jsonb, 'ignore'
+) = '{"other": "data"}'::jsonb AS test_sync_missing_ignore,
+jsonb_array_delete_where(
+    '{"posts": "not an array"}'::jsonb, 'posts', 'id', '1'::jsonb, 'ignore'
+) = '{"posts": "not an array"}'::jsonb AS test_delete_non_array_ignore,
+jsonb_merge_at_path(
+    '{"id": 1}'::jsonb, '{"name": "ACME"}'::jsonb, ARRAY['company'], 'ignore'
+) = '{"id": 1}'::jsonb AS test_merge_at_path_missing_ignore;
+
+-- Test 63: 'error' raises on a missing array
+DO $$
+BEGIN
+    PERFORM jsonb_array_delete_where('{"other": "data"}'::jsonb, 'posts', 'id', '1'::jsonb, 'error');
+    RAISE EXCEPTION 'expected missing array error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Path ''posts'' does not exist%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+DO $$
+BEGIN
+    PERFORM jsonb_array_contains_id('{"other": "data"}'::jsonb, 'posts', 'id', '1'::jsonb, 'error');
+    RAISE EXCEPTION 'expected missing array error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Path ''posts'' does not exist%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+-- Test 64: Non-array values are rejected unless ignored, and unknown policies fail
+DO $$
+BEGIN
+    PERFORM jsonb_array_insert_where('{"posts": {"id": 1}}'::jsonb, 'posts', '{"id": 2}'::jsonb);
+    RAISE EXCEPTION 'expected non-array error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Path ''posts'' does not point to an array%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+DO $$
+BEGIN
+    PERFORM jsonb_array_truncate('{"posts": []}'::jsonb, 'posts', 1, true, 'skip');
+    RAISE EXCEPTION 'expected invalid policy error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Invalid on_missing policy%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+-- ===== NESTED ARRAY TESTS =====
+
+-- Test 65: Sorted insert into a nested array of the matched element
+SELECT jsonb_array_insert_nested(
+    '{"posts": [{"id": 1}, {"id": 42, "comments": [{"id": 3, "at": 3}, {"id": 1, "at": 1}]}]}'::jsonb,
+    'posts', 'id', '42'::jsonb,
+    'comments',
+    '{"id": 2, "at": 2}'::jsonb,
+    'at', 'DESC'
+)->'posts'->1->'comments' = '[{"id": 3, "at": 3}, {"id": 2, "at": 2}, {"id": 1, "at": 1}]'::jsonb AS test_insert_nested_sorted,
+jsonb_array_insert_nested(
+    '{"posts": [{"id": 1}]}'::jsonb,
+    'posts', 'id', '1'::jsonb,
+    'comments',
+    '{"id": 9}'::jsonb
+) = '{"posts": [{"id": 1, "comments": [{"id": 9}]}]}'::jsonb AS test_insert_nested_creates;
+
+-- Test 66: Delete and update inside a nested array
+SELECT jsonb_array_delete_nested(
+    '{"posts": [{"id": 42, "reactions": [{"id": 1}, {"id": 2}]}]}'::jsonb,
+    'posts', 'id', '42'::jsonb,
+    'reactions', 'id', '1'::jsonb
+) = '{"posts": [{"id": 42, "reactions": [{"id": 2}]}]}'::jsonb AS test_delete_nested,
+jsonb_array_update_nested(
+    '{"posts": [{"id": 42, "comments": [{"id": 7, "body": "old", "draft": true}]}]}'::jsonb,
+    'posts', 'id', '42'::jsonb,
+    'comments', 'id', '7'::jsonb,
+    '{"body": "new", "draft": null}'::jsonb,
+    'merge-patch'
+) = '{"posts": [{"id": 42, "comments": [{"id": 7, "body": "new"}]}]}'::jsonb AS test_update_nested;
+
+-- Test 67: No matching outer element leaves the document unchanged
+SELECT jsonb_array_delete_nested(
+    '{"posts": [{"id": 42, "reactions": [{"id": 1}]}]}'::jsonb,
+    'posts', 'id', '7'::jsonb,
+    'reactions', 'id', '1'::jsonb
+) = '{"posts": [{"id": 42, "reactions": [{"id": 1}]}]}'::jsonb AS test_nested_no_outer_match;
+
+-- ===== COUNTER TESTS =====
+
+-- Test 68: Increment an existing counter and create a missing one at zero
+SELECT jsonb_ivm_increment(
+    '{"stats": {"post_count": 4}}'::jsonb,
+    'stats.post_count',
+    1
+) = '{"stats": {"post_count": 5}}'::jsonb AS test_increment_existing,
+jsonb_ivm_increment(
+    '{"id": 1}'::jsonb,
+    'stats.like_count',
+    -1
+) = '{"id": 1, "stats": {"like_count": -1}}'::jsonb AS test_increment_creates;
+
+-- Test 69: Integer counters stay integers, decimals stay decimals, large totals keep every digit
+SELECT jsonb_ivm_increment('{"n": 41}'::jsonb, 'n', 1)->>'n' = '42' AS test_increment_integer,
+       jsonb_ivm_increment('{"t": 10.0}'::jsonb, 't', 1)->>'t' = '11.0' AS test_increment_decimal,
+       jsonb_ivm_increment('{"t": 0.1}'::jsonb, 't', 0.2)->>'t' = '0.3' AS test_increment_exact,
+       jsonb_ivm_increment('{"t": 98765432109876543210.987654321}'::jsonb, 't', 0.000000001)->>'t'
+           = '98765432109876543210.987654322' AS test_increment_large_decimal,
+       jsonb_ivm_increment('{"id": 9007199254740993, "n": 1}'::jsonb, 'n', 1)
+           = '{"id": 9007199254740993, "n": 2}'::jsonb AS test_increment_keeps_other_numbers;
+
+-- Test 70: Increment a field of a matched array element, keeping every digit
+SELECT jsonb_array_increment_where(
+    '{"posts": [{"id": 1}, {"id": 42, "like_count": 7}]}'::jsonb,
+    'posts', 'id', '42'::jsonb,
+    'like_count', 1
+) = '{"posts": [{"id": 1}, {"id": 42, "like_count": 8}]}'::jsonb AS test_array_increment,
+jsonb_array_increment_where(
+    '{"posts": [{"id": 1}]}'::jsonb,
+    'posts', 'id', '7'::jsonb,
+    'like_count', 1
+) = '{"posts": [{"id": 1}]}'::jsonb AS test_array_increment_no_match,
+jsonb_array_increment_where(
+    '{"posts": [{"id": 9007199254740993, "total": 98765432109876543210.987654321}]}'::jsonb,
+    'posts', 'id', '9007199254740993'::jsonb,
+    'total', 0.000000001
+) = '{"posts": [{"id": 9007199254740993, "total": 98765432109876543210.987654322}]}'::jsonb
+    AS test_array_increment_large_decimal;
+
+-- Test 71: Incrementing a non-numeric field raises
+DO $$
+BEGIN
+    PERFORM jsonb_ivm_increment('{"count": "4"}'::jsonb, 'count', 1);
+    RAISE EXCEPTION 'expected non-numeric field error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Field ''count'' is not a number%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+-- ===== MAINTAINED AGGREGATE TESTS =====
+
+-- Test 72: Insert and delete keep count, sum and max consistent
+SELECT jsonb_array_insert_where(
+    '{"posts": [{"id": 1, "at": "2025-01-01", "likes": 2}]}'::jsonb,
+    'posts',
+    '{"id": 2, "at": "2025-01-02", "likes": 3}'::jsonb,
+    aggregates => '{"stats.post_count": "count", "stats.total_likes": "sum(likes)", "stats.latest_post_at": "max(at)"}'
+)->'stats' = '{"post_count": 2, "total_likes": 5, "latest_post_at": "2025-01-02"}'::jsonb AS test_aggregates_insert,
+jsonb_array_delete_where(
+    '{"posts": [{"id": 1, "at": "2025-01-01", "likes": 2}, {"id": 2, "at": "2025-01-02", "likes": 3}],
+      "stats": {"post_count": 2, "total_likes": 5, "latest_post_at": "2025-01-02"}}'::jsonb,
+    'posts', 'id', '2'::jsonb,
+    aggregates => '{"stats.post_count": "count", "stats.total_likes": "sum(likes)", "stats.latest_post_at": "max(at)"}'
+)->'stats' = '{"post_count": 1, "total_likes": 2, "latest_post_at": "2025-01-01"}'::jsonb AS test_aggregates_delete;
+
+-- Test 73: Stored aggregates are adjusted, not recomputed; updates change sums
+SELECT jsonb_array_insert_where(
+    '{"posts": [], "post_count": 10}'::jsonb,
+    'posts',
+    '{"id": 1}'::jsonb,
+    aggregates => '{"post_count": "count"}'
+)->'post_count' = '11'::jsonb AS test_aggregates_incremental,
+jsonb_array_update_where(
+    '{"posts": [{"id": 1, "likes": 2}, {"id": 2, "likes": 5}], "total": 7, "top": 5}'::jsonb,
+    'posts', 'id', '2'::jsonb,
+    '{"likes": 1}'::jsonb,
+    aggregates => '{"total": "sum(likes)", "top": "max(likes)"}'
+) - 'posts' = '{"total": 3, "top": 2}'::jsonb AS test_aggregates_update;
+
+-- Test 74: Invalid aggregate expressions raise
+DO $$
+BEGIN
+    PERFORM jsonb_array_delete_where('{"posts": []}'::jsonb, 'posts', 'id', '1'::jsonb,
+        aggregates => '{"n": "avg(likes)"}');
+    RAISE EXCEPTION 'expected invalid aggregate error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Invalid aggregate%' THEN
+        RAISE;
+    END IF;
+END $$;
+
 -- Display summary
 \echo '========================================='
 \echo 'All array CRUD tests passed!'
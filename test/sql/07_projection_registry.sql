@@ -0,0 +1,94 @@
+-- Test projection registry (jsonb_ivm.projection / jsonb_ivm.dependency)
+
+\set ECHO all
+\set ON_ERROR_STOP on
+
+-- Clean up from previous test runs
+DROP EXTENSION IF EXISTS jsonb_ivm CASCADE;
+DROP TABLE IF EXISTS tv_feed, tv_post, tv_user, v_company CASCADE;
+CREATE EXTENSION jsonb_ivm;
+
+CREATE TABLE v_company (pk integer PRIMARY KEY, data jsonb);
+CREATE TABLE tv_user (pk integer PRIMARY KEY, fk_company integer, data jsonb);
+CREATE TABLE tv_feed (id integer PRIMARY KEY, doc jsonb);
+CREATE TABLE tv_post (pk integer PRIMARY KEY, fk_feed integer, data jsonb);
+
+-- ===== REGISTRATION TESTS =====
+
+\echo '=== Test 1: Object dependency registers both tables with default columns ==='
+SELECT jsonb_ivm_register_dependency('v_company', 'tv_user', 'company', 'object', NULL, 'fk_company') > 0
+    AS test_object_registered;
+
+SELECT table_name::text, key_column::text, data_column::text
+FROM jsonb_ivm.projection
+ORDER BY 1;
+-- Expected: tv_user and v_company with pk / data
+
+\echo '=== Test 2: Array dependency on a table with custom columns ==='
+SELECT jsonb_ivm_register_projection('tv_feed', key_column => 'id', data_column => 'doc');
+SELECT jsonb_ivm_register_dependency('tv_post', 'tv_feed', 'posts', 'array', 'id', 'fk_feed') > 0
+    AS test_array_registered;
+
+SELECT source_table::text, target_table::text, embed_path, kind, match_key, fk_column::text
+FROM jsonb_ivm.dependency
+ORDER BY id;
+-- Expected: (v_company, tv_user, {company}, object, NULL, fk_company)
+--           (tv_post, tv_feed, {posts}, array, id, fk_feed)
+
+\echo '=== Test 3: Registering the same location again replaces the declaration ==='
+SELECT jsonb_ivm_register_dependency('v_company', 'tv_user', 'company', 'object', NULL, 'fk_company')
+     = (SELECT id FROM jsonb_ivm.dependency WHERE target_table = 'tv_user'::regclass)
+    AS test_reregister_same_id,
+    (SELECT count(*) FROM jsonb_ivm.dependency) = 2 AS test_no_duplicate;
+
+-- ===== VALIDATION TESTS =====
+
+\echo '=== Test 4: Invalid declarations raise ==='
+DO $$
+BEGIN
+    PERFORM jsonb_ivm_register_dependency('tv_post', 'tv_feed', 'posts', 'array', NULL, 'fk_feed');
+    RAISE EXCEPTION 'expected missing match_key error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'array dependencies require a match_key%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+DO $$
+BEGIN
+    PERFORM jsonb_ivm_register_dependency('v_company', 'tv_user', 'company', 'nested', NULL, 'fk_company');
+    RAISE EXCEPTION 'expected invalid kind error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Invalid dependency kind%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+DO $$
+BEGIN
+    -- Object dependencies need the foreign key on the target table
+    PERFORM jsonb_ivm_register_dependency('v_company', 'tv_user', 'employer', 'object', NULL, 'fk_employer');
+    RAISE EXCEPTION 'expected missing column error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Table ''tv_user'' has no column ''fk_employer''%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+DO $$
+BEGIN
+    PERFORM jsonb_ivm_register_projection('tv_feed');
+    RAISE EXCEPTION 'expected missing default column error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Table ''tv_feed'' has no column ''pk''%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+-- Cleanup
+DROP EXTENSION jsonb_ivm CASCADE;
+DROP TABLE tv_feed, tv_post, tv_user, v_company;
+
+\echo '========================================='
+\echo 'All projection registry tests passed!'
+\echo '========================================='
@@ -0,0 +1,124 @@
+-- Test trigger generation from registered dependencies
+
+\set ECHO all
+\set ON_ERROR_STOP on
+
+-- Clean up from previous test runs
+DROP EXTENSION IF EXISTS jsonb_ivm CASCADE;
+DROP TABLE IF EXISTS tv_feed, tv_post, tv_user, v_company CASCADE;
+CREATE EXTENSION jsonb_ivm;
+
+CREATE TABLE v_company (pk integer PRIMARY KEY, data jsonb);
+CREATE TABLE tv_user (pk integer PRIMARY KEY, fk_company integer, data jsonb);
+CREATE TABLE tv_feed (id integer PRIMARY KEY, doc jsonb);
+CREATE TABLE tv_post (pk integer PRIMARY KEY, fk_feed integer, fk_user integer, data jsonb);
+
+INSERT INTO v_company VALUES (1, '{"id": 1, "name": "ACME"}');
+INSERT INTO tv_user VALUES (10, 1, '{"id": 10, "name": "Alice", "company": {"id": 1, "name": "ACME"}}');
+INSERT INTO tv_feed VALUES (100, '{"posts": []}'), (200, '{"posts": []}');
+
+SELECT jsonb_ivm_register_projection('tv_feed', key_column => 'id', data_column => 'doc');
+SELECT jsonb_ivm_register_dependency('v_company', 'tv_user', 'company', 'object', NULL, 'fk_company');
+SELECT jsonb_ivm_register_dependency('tv_user', 'tv_post', 'author', 'object', NULL, 'fk_user');
+SELECT jsonb_ivm_register_dependency('tv_post', 'tv_feed', 'posts', 'array', 'id', 'fk_feed');
+
+-- ===== INSTALLATION TESTS =====
+
+\echo '=== Test 1: Install creates one trigger per dependency ==='
+SELECT jsonb_ivm_install_triggers('tv_user') = 1 AS test_user_installed,
+       jsonb_ivm_install_triggers('tv_post') = 1 AS test_post_installed,
+       jsonb_ivm_install_triggers('tv_feed') = 1 AS test_feed_installed;
+
+SELECT tgrelid::regclass::text AS source_table, tgname
+FROM pg_trigger
+WHERE tgname LIKE 'jsonb_ivm_dependency_%'
+ORDER BY 1;
+-- Expected: tv_post, tv_user and v_company each carry one trigger
+
+\echo '=== Test 2: Installing again regenerates instead of duplicating ==='
+SELECT jsonb_ivm_install_triggers('tv_feed') = 1 AS test_reinstall,
+       (SELECT count(*) FROM jsonb_ivm.installed_trigger) = 3 AS test_no_duplicate;
+
+-- ===== ARRAY DEPENDENCY TESTS =====
+
+\echo '=== Test 3: INSERT on the source appends the element ==='
+INSERT INTO tv_post VALUES (1, 100, 10, '{"id": 1, "title": "Hello", "author": {"id": 10, "name": "Alice"}}');
+INSERT INTO tv_post VALUES (2, 100, 10, '{"id": 2, "title": "World", "author": {"id": 10, "name": "Alice"}}');
+
+SELECT jsonb_array_length(doc -> 'posts') = 2 AS test_insert_appended
+FROM tv_feed WHERE id = 100;
+
+\echo '=== Test 4: UPDATE on the source replaces the element ==='
+UPDATE tv_post SET data = data || '{"title": "Hello again"}' WHERE pk = 1;
+
+SELECT doc -> 'posts' -> 0 ->> 'title' = 'Hello again' AS test_update_replaced
+FROM tv_feed WHERE id = 100;
+
+\echo '=== Test 5: Changing the foreign key moves the element ==='
+UPDATE tv_post SET fk_feed = 200 WHERE pk = 2;
+
+SELECT (SELECT jsonb_array_length(doc -> 'posts') FROM tv_feed WHERE id = 100) = 1 AS test_moved_out,
+       (SELECT doc -> 'posts' -> 0 ->> 'id' FROM tv_feed WHERE id = 200) = '2' AS test_moved_in;
+
+\echo '=== Test 6: DELETE on the source removes the element ==='
+DELETE FROM tv_post WHERE pk = 2;
+
+SELECT doc -> 'posts' = '[]'::jsonb AS test_delete_removed
+FROM tv_feed WHERE id = 200;
+
+-- ===== OBJECT DEPENDENCY TESTS =====
+
+\echo '=== Test 7: Source updates cascade through every level ==='
+UPDATE v_company SET data = jsonb_set(data, '{name}', '"ACME Corp"') WHERE pk = 1;
+
+SELECT data -> 'company' ->> 'name' = 'ACME Corp' AS test_user_patched
+FROM tv_user WHERE pk = 10;
+
+SELECT data -> 'author' -> 'company' ->> 'name' = 'ACME Corp' AS test_post_patched
+FROM tv_post WHERE pk = 1;
+
+SELECT doc -> 'posts' -> 0 -> 'author' -> 'company' ->> 'name' = 'ACME Corp' AS test_feed_patched
+FROM tv_feed WHERE id = 100;
+
+\echo '=== Test 8: DELETE on the source nulls the embedded object ==='
+DELETE FROM v_company WHERE pk = 1;
+
+SELECT data -> 'company' = 'null'::jsonb AS test_object_nulled
+FROM tv_user WHERE pk = 10;
+
+-- ===== UNINSTALL TESTS =====
+
+\echo '=== Test 9: Uninstall removes triggers and functions ==='
+SELECT jsonb_ivm_uninstall_triggers('tv_feed') = 1 AS test_uninstalled,
+       jsonb_ivm_uninstall_triggers('tv_feed') = 0 AS test_uninstall_idempotent;
+
+SELECT NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgrelid = 'tv_post'::regclass AND tgname LIKE 'jsonb_ivm_dependency_%')
+    AS test_trigger_dropped,
+       to_regprocedure('jsonb_ivm.dependency_3_trigger()') IS NULL AS test_function_dropped;
+
+-- Source changes no longer reach the projection
+INSERT INTO tv_post VALUES (3, 100, 10, '{"id": 3, "title": "Untracked"}');
+
+SELECT jsonb_array_length(doc -> 'posts') = 1 AS test_not_maintained
+FROM tv_feed WHERE id = 100;
+
+\echo '=== Test 10: Installing without dependencies raises ==='
+DO $$
+BEGIN
+    PERFORM jsonb_ivm_install_triggers('v_company');
+    RAISE EXCEPTION 'expected missing dependencies error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Table ''v_company'' has no registered dependencies%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+-- Cleanup
+SELECT jsonb_ivm_uninstall_triggers('tv_user') + jsonb_ivm_uninstall_triggers('tv_post') = 2
+    AS test_uninstall_remaining;
+DROP EXTENSION jsonb_ivm CASCADE;
+DROP TABLE tv_feed, tv_post, tv_user, v_company;
+
+\echo '========================================='
+\echo 'All trigger generation tests passed!'
+\echo '========================================='
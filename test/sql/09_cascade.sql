@@ -0,0 +1,130 @@
+-- Test multi-level cascade propagation (jsonb_ivm_cascade)
+
+\set ECHO all
+\set ON_ERROR_STOP on
+
+-- Clean up from previous test runs
+DROP EXTENSION IF EXISTS jsonb_ivm CASCADE;
+DROP TABLE IF EXISTS tv_feed, tv_post, tv_user, v_company CASCADE;
+DROP FUNCTION IF EXISTS cascade_row();
+CREATE EXTENSION jsonb_ivm;
+
+CREATE TABLE v_company (pk integer PRIMARY KEY, data jsonb);
+CREATE TABLE tv_user (pk integer PRIMARY KEY, fk_company integer, data jsonb);
+CREATE TABLE tv_post (pk integer PRIMARY KEY, fk_feed integer, fk_user integer, data jsonb);
+CREATE TABLE tv_feed (pk integer PRIMARY KEY, fk_company integer, data jsonb);
+
+INSERT INTO v_company VALUES (1, '{"id": 1, "name": "ACME"}');
+INSERT INTO tv_user VALUES (10, 1, '{"id": 10, "name": "Alice", "company": {"id": 1, "name": "ACME"}}');
+INSERT INTO tv_post VALUES
+    (1, 100, 10, '{"id": 1, "title": "Hello", "author": {"id": 10, "name": "Alice", "company": {"id": 1, "name": "ACME"}}}'),
+    (2, 100, 10, '{"id": 2, "title": "World", "author": {"id": 10, "name": "Alice", "company": {"id": 1, "name": "ACME"}}}');
+INSERT INTO tv_feed VALUES
+    (100, 1, (SELECT jsonb_build_object('posts', jsonb_agg(data ORDER BY pk)) FROM tv_post)),
+    (200, 1, '{"posts": []}');
+
+-- Registered in reverse order: the plan must not depend on it
+SELECT jsonb_ivm_register_dependency('tv_post', 'tv_feed', 'posts', 'array', 'id', 'fk_feed');
+SELECT jsonb_ivm_register_dependency('tv_user', 'tv_post', 'author', 'object', NULL, 'fk_user');
+SELECT jsonb_ivm_register_dependency('v_company', 'tv_user', 'company', 'object', NULL, 'fk_company');
+
+CREATE FUNCTION cascade_row() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    PERFORM jsonb_ivm_cascade(TG_RELID::regclass::text, to_jsonb(OLD), to_jsonb(NEW));
+    RETURN NULL;
+END $$;
+
+CREATE TRIGGER cascade_company AFTER INSERT OR UPDATE OR DELETE ON v_company
+FOR EACH ROW EXECUTE FUNCTION cascade_row();
+
+-- ===== PLAN TESTS =====
+
+\echo '=== Test 1: Plan follows the dependency graph ==='
+SELECT step, source_table, target_table, kind FROM jsonb_ivm_cascade_plan('v_company');
+-- Expected: 1 v_company -> tv_user, 2 tv_user -> tv_post, 3 tv_post -> tv_feed
+
+SELECT count(*) = 0 AS test_leaf_has_no_plan FROM jsonb_ivm_cascade_plan('tv_feed');
+
+-- ===== PROPAGATION TESTS =====
+
+\echo '=== Test 2: A company rename reaches every level ==='
+SELECT step, target_table, rows_updated
+FROM jsonb_ivm_cascade('v_company', '{"pk": 1, "data": {"id": 1, "name": "ACME"}}',
+                       '{"pk": 1, "data": {"id": 1, "name": "ACME"}}');
+-- Expected: tv_user 1, tv_post 2, tv_feed 1 (both posts in one batch update)
+
+UPDATE v_company SET data = jsonb_set(data, '{name}', '"ACME Corp"') WHERE pk = 1;
+
+SELECT data -> 'company' ->> 'name' = 'ACME Corp' AS test_user_patched
+FROM tv_user WHERE pk = 10;
+
+SELECT bool_and(data -> 'author' -> 'company' ->> 'name' = 'ACME Corp') AS test_posts_patched
+FROM tv_post;
+
+SELECT bool_and(post -> 'author' -> 'company' ->> 'name' = 'ACME Corp') AS test_feed_patched
+FROM tv_feed, jsonb_array_elements(data -> 'posts') post
+WHERE pk = 100;
+
+\echo '=== Test 3: Inserting a source row appends it ==='
+INSERT INTO tv_post VALUES (3, 200, 10, '{"id": 3, "title": "New"}');
+SELECT target_table, rows_updated
+FROM jsonb_ivm_cascade('tv_post', NULL, (SELECT to_jsonb(p) FROM tv_post p WHERE pk = 3));
+
+SELECT data -> 'posts' = '[{"id": 3, "title": "New"}]'::jsonb AS test_insert_appended
+FROM tv_feed WHERE pk = 200;
+
+\echo '=== Test 4: Changing the foreign key moves the element ==='
+SELECT rows_updated = 2 AS test_move_updates_both_feeds
+FROM jsonb_ivm_cascade('tv_post',
+                       (SELECT to_jsonb(p) FROM tv_post p WHERE pk = 3),
+                       (SELECT to_jsonb(p) || '{"fk_feed": 100}' FROM tv_post p WHERE pk = 3));
+UPDATE tv_post SET fk_feed = 100 WHERE pk = 3;
+
+SELECT (SELECT jsonb_array_length(data -> 'posts') FROM tv_feed WHERE pk = 100) = 3 AS test_moved_in,
+       (SELECT data -> 'posts' FROM tv_feed WHERE pk = 200) = '[]'::jsonb AS test_moved_out;
+
+\echo '=== Test 5: Deleting a source row removes it ==='
+SELECT rows_updated = 1 AS test_delete_applied
+FROM jsonb_ivm_cascade('tv_post', (SELECT to_jsonb(p) FROM tv_post p WHERE pk = 3), NULL);
+DELETE FROM tv_post WHERE pk = 3;
+
+SELECT jsonb_array_length(data -> 'posts') = 2 AS test_delete_removed
+FROM tv_feed WHERE pk = 100;
+
+-- ===== CYCLE TESTS =====
+
+\echo '=== Test 6: Declarations closing a cycle are rejected ==='
+DO $$
+BEGIN
+    PERFORM jsonb_ivm_register_dependency('tv_feed', 'v_company', 'feeds', 'array', 'id', 'fk_company');
+    RAISE EXCEPTION 'expected cycle error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Dependency of ''v_company'' on ''tv_feed'' would create a cycle: %' THEN
+        RAISE;
+    END IF;
+END $$;
+
+DO $$
+BEGIN
+    PERFORM jsonb_ivm_register_dependency('tv_user', 'tv_user', 'manager', 'object', NULL, 'fk_company');
+    RAISE EXCEPTION 'expected self-dependency error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE '%would create a cycle: tv_user -> tv_user' THEN
+        RAISE;
+    END IF;
+END $$;
+
+SELECT count(*) = 3 AS test_cycle_not_registered FROM jsonb_ivm.dependency;
+
+\echo '=== Test 7: Replacing a declaration does not count it twice ==='
+SELECT jsonb_ivm_register_dependency('v_company', 'tv_user', 'company', 'object', NULL, 'fk_company') > 0
+    AS test_reregister_allowed;
+
+-- Cleanup
+DROP EXTENSION jsonb_ivm CASCADE;
+DROP TABLE tv_feed, tv_post, tv_user, v_company;
+DROP FUNCTION cascade_row();
+
+\echo '========================================='
+\echo 'All cascade tests passed!'
+\echo '========================================='
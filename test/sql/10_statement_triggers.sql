@@ -0,0 +1,153 @@
+-- Test statement-level cascade triggers (jsonb_ivm_cascade_trigger)
+
+\set ECHO all
+\set ON_ERROR_STOP on
+
+-- Clean up from previous test runs
+DROP EXTENSION IF EXISTS jsonb_ivm CASCADE;
+DROP TABLE IF EXISTS tv_board, tv_label, tv_feed, tv_post, tv_user, v_company CASCADE;
+CREATE EXTENSION jsonb_ivm;
+
+CREATE TABLE v_company (pk integer PRIMARY KEY, data jsonb);
+CREATE TABLE tv_user (pk integer PRIMARY KEY, fk_company integer, data jsonb);
+CREATE TABLE tv_post (pk integer PRIMARY KEY, fk_feed integer, fk_author integer, fk_reviewer integer, data jsonb);
+CREATE TABLE tv_feed (pk integer PRIMARY KEY, data jsonb);
+
+SELECT jsonb_ivm_register_dependency('v_company', 'tv_user', 'company', 'object', NULL, 'fk_company');
+SELECT jsonb_ivm_register_dependency('tv_user', 'tv_post', 'author', 'object', NULL, 'fk_author');
+SELECT jsonb_ivm_register_dependency('tv_user', 'tv_post', 'reviewer', 'object', NULL, 'fk_reviewer');
+SELECT jsonb_ivm_register_dependency('tv_post', 'tv_feed', 'posts', 'array', 'id', 'fk_feed');
+
+-- 100 companies, 1000 users, 2000 posts spread over 10 feeds
+INSERT INTO v_company
+SELECT i, jsonb_build_object('id', i, 'name', 'Company ' || i)
+FROM generate_series(1, 100) i;
+
+INSERT INTO tv_user
+SELECT i, i % 100 + 1,
+       jsonb_build_object('id', i, 'company', jsonb_build_object('id', i % 100 + 1, 'name', 'Company ' || (i % 100 + 1)))
+FROM generate_series(1, 1000) i;
+
+INSERT INTO tv_post
+SELECT i, i % 10 + 1, i % 1000 + 1, (i + 1) % 1000 + 1,
+       jsonb_build_object('id', i,
+                          'author', (SELECT data FROM tv_user WHERE pk = i % 1000 + 1),
+                          'reviewer', (SELECT data FROM tv_user WHERE pk = (i + 1) % 1000 + 1))
+FROM generate_series(1, 2000) i;
+
+INSERT INTO tv_feed
+SELECT f, jsonb_build_object('posts', (SELECT jsonb_agg(data ORDER BY pk) FROM tv_post WHERE fk_feed = f))
+FROM generate_series(1, 10) f;
+
+CREATE TRIGGER v_company_cascade_insert AFTER INSERT ON v_company
+REFERENCING NEW TABLE AS new_rows
+FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger();
+
+CREATE TRIGGER v_company_cascade_update AFTER UPDATE ON v_company
+REFERENCING OLD TABLE AS old_rows NEW TABLE AS new_rows
+FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger();
+
+CREATE TRIGGER v_company_cascade_delete AFTER DELETE ON v_company
+REFERENCING OLD TABLE AS old_rows
+FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger();
+
+-- ===== PLAN TESTS =====
+
+\echo '=== Test 1: author and reviewer share one combined step ==='
+SELECT step, target_table, count(*) AS dependencies
+FROM jsonb_ivm_cascade_plan('v_company')
+GROUP BY step, target_table
+ORDER BY step;
+-- Expected: 1 tv_user 1, 2 tv_post 2, 3 tv_feed 1
+
+-- ===== BULK PROPAGATION TESTS =====
+
+\echo '=== Test 2: One statement renaming every company reaches every level ==='
+UPDATE v_company SET data = data || jsonb_build_object('name', 'Renamed ' || pk);
+
+SELECT bool_and(data -> 'company' ->> 'name' = 'Renamed ' || fk_company) AS test_users_patched
+FROM tv_user;
+
+SELECT bool_and(data -> 'author' -> 'company' ->> 'name' LIKE 'Renamed %'
+            AND data -> 'reviewer' -> 'company' ->> 'name' LIKE 'Renamed %') AS test_posts_patched
+FROM tv_post;
+
+SELECT bool_and(post -> 'author' = p.data -> 'author' AND post -> 'reviewer' = p.data -> 'reviewer')
+    AS test_feeds_patched
+FROM tv_feed f, jsonb_array_elements(f.data -> 'posts') post
+JOIN tv_post p ON p.pk = (post ->> 'id')::integer;
+
+SELECT bool_and(jsonb_array_length(data -> 'posts') = 200) AS test_no_duplicates
+FROM tv_feed;
+
+\echo '=== Test 3: Statements touching no rows are no-ops ==='
+UPDATE v_company SET data = data WHERE pk < 0;
+
+\echo '=== Test 4: Bulk delete nulls the embedded objects ==='
+DELETE FROM v_company WHERE pk <= 10;
+
+SELECT count(*) = 100 AS test_users_nulled
+FROM tv_user WHERE data -> 'company' = 'null'::jsonb;
+
+SELECT count(*) = 0 AS test_others_kept
+FROM tv_user WHERE fk_company > 10 AND data -> 'company' = 'null'::jsonb;
+
+\echo '=== Test 5: Bulk insert patches the referencing rows ==='
+INSERT INTO v_company
+SELECT i, jsonb_build_object('id', i, 'name', 'Restored ' || i)
+FROM generate_series(1, 10) i;
+
+SELECT bool_and(data -> 'company' ->> 'name' = 'Restored ' || fk_company) AS test_users_restored
+FROM tv_user WHERE fk_company <= 10;
+
+-- ===== NON-INTEGER KEY TESTS =====
+
+\echo '=== Test 6: Array elements matched by UUID are patched ==='
+CREATE TABLE tv_label (pk uuid PRIMARY KEY, fk_board integer, data jsonb);
+CREATE TABLE tv_board (pk integer PRIMARY KEY, data jsonb);
+
+SELECT jsonb_ivm_register_dependency('tv_label', 'tv_board', 'labels', 'array', 'id', 'fk_board');
+
+INSERT INTO tv_label
+SELECT id, i % 2 + 1, jsonb_build_object('id', id, 'name', 'Label ' || i)
+FROM generate_series(1, 6) i, LATERAL (SELECT md5(i::text)::uuid AS id) u;
+
+INSERT INTO tv_board
+SELECT b, jsonb_build_object('labels', (SELECT jsonb_agg(data ORDER BY pk) FROM tv_label WHERE fk_board = b))
+FROM generate_series(1, 2) b;
+
+CREATE TRIGGER tv_label_cascade_update AFTER UPDATE ON tv_label
+REFERENCING OLD TABLE AS old_rows NEW TABLE AS new_rows
+FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger();
+
+UPDATE tv_label SET data = data || jsonb_build_object('name', upper(data ->> 'name'));
+
+SELECT bool_and(label ->> 'name' LIKE 'LABEL %') AS test_uuid_elements_patched,
+       count(*) = 6 AS test_no_duplicates
+FROM tv_board, jsonb_array_elements(data -> 'labels') label;
+
+-- ===== VALIDATION TESTS =====
+
+\echo '=== Test 7: Row-level use is rejected ==='
+CREATE TRIGGER v_company_cascade_row AFTER UPDATE ON v_company
+FOR EACH ROW EXECUTE FUNCTION jsonb_ivm_cascade_trigger();
+
+DO $$
+BEGIN
+    UPDATE v_company SET data = data WHERE pk = 1;
+    RAISE EXCEPTION 'expected statement-level error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'jsonb_ivm_cascade_trigger must be fired AFTER ... FOR EACH STATEMENT%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+DROP TRIGGER v_company_cascade_row ON v_company;
+
+-- Cleanup
+DROP EXTENSION jsonb_ivm CASCADE;
+DROP TABLE tv_board, tv_label, tv_feed, tv_post, tv_user, v_company;
+
+\echo '========================================='
+\echo 'All statement trigger tests passed!'
+\echo '========================================='
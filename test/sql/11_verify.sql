@@ -0,0 +1,139 @@
+-- Test projection consistency checks (jsonb_ivm_verify)
+
+\set ECHO all
+\set ON_ERROR_STOP on
+
+-- Clean up from previous test runs
+DROP EXTENSION IF EXISTS jsonb_ivm CASCADE;
+DROP TABLE IF EXISTS tv_big, tv_feed, tv_post, tv_user, users, companies CASCADE;
+CREATE EXTENSION jsonb_ivm;
+
+CREATE TABLE companies (id integer PRIMARY KEY, name text);
+CREATE TABLE users (id integer PRIMARY KEY, name text, company_id integer);
+CREATE TABLE tv_post (pk integer PRIMARY KEY, fk_feed integer, data jsonb);
+CREATE TABLE tv_user (pk integer PRIMARY KEY, data jsonb);
+CREATE TABLE tv_feed (pk integer PRIMARY KEY, data jsonb);
+
+INSERT INTO companies VALUES (1, 'ACME'), (2, 'Globex');
+INSERT INTO users SELECT i, 'User ' || i, i % 2 + 1 FROM generate_series(1, 100) i;
+INSERT INTO tv_post SELECT i, i % 3 + 1, jsonb_build_object('id', i, 'title', 'Post ' || i) FROM generate_series(1, 30) i;
+
+SELECT jsonb_ivm_register_projection('tv_user', definition => $q$
+    SELECT u.id, jsonb_build_object('id', u.id, 'name', u.name,
+                                    'company', jsonb_build_object('id', c.id, 'name', c.name))
+    FROM users u JOIN companies c ON c.id = u.company_id
+$q$);
+
+INSERT INTO tv_user
+SELECT u.id, jsonb_build_object('id', u.id, 'name', u.name,
+                                'company', jsonb_build_object('id', c.id, 'name', c.name))
+FROM users u JOIN companies c ON c.id = u.company_id;
+
+-- ===== CONSISTENT PROJECTIONS =====
+
+\echo '=== Test 1: A freshly built projection has no drift ==='
+SELECT count(*) = 0 AS test_no_drift FROM jsonb_ivm_verify('tv_user');
+
+-- ===== DRIFT DETECTION =====
+
+\echo '=== Test 2: Changed, missing and extra rows are reported ==='
+UPDATE companies SET name = 'ACME Corp' WHERE id = 1;   -- not propagated: drift
+UPDATE tv_user SET data = data - 'name' WHERE pk = 5;
+DELETE FROM tv_user WHERE pk = 3;
+INSERT INTO tv_user VALUES (1000, '{"id": 1000}');
+
+SELECT count(*) = 53 AS test_drift_count FROM jsonb_ivm_verify('tv_user');
+
+SELECT diff = '[{"path": "company.name", "stored": "ACME", "expected": "ACME Corp"}]'::jsonb
+    AS test_changed_value
+FROM jsonb_ivm_verify('tv_user') WHERE key = '4';
+
+SELECT diff = '[{"path": "name", "expected": "User 5"}]'::jsonb AS test_missing_key
+FROM jsonb_ivm_verify('tv_user') WHERE key = '5';
+
+SELECT diff -> 0 ->> 'path' = '' AND NOT diff -> 0 ? 'stored' AS test_missing_row
+FROM jsonb_ivm_verify('tv_user') WHERE key = '3';
+
+SELECT diff = '[{"path": "", "stored": {"id": 1000}}]'::jsonb AS test_extra_row
+FROM jsonb_ivm_verify('tv_user') WHERE key = '1000';
+
+\echo '=== Test 3: Sampling checks a consistent subset of keys ==='
+SELECT count(*) BETWEEN 1 AND 52 AS test_sampled
+FROM jsonb_ivm_verify('tv_user', sample_pct => 50);
+
+SELECT (SELECT count(*) FROM jsonb_ivm_verify('tv_user', sample_pct => 50)) =
+       (SELECT count(*) FROM jsonb_ivm_verify('tv_user', sample_pct => 50)) AS test_sample_stable;
+
+-- ===== UNORDERED ARRAYS =====
+
+\echo '=== Test 4: Registered array dependencies ignore element order ==='
+SELECT jsonb_ivm_register_dependency('tv_post', 'tv_feed', 'posts', 'array', 'id', 'fk_feed');
+
+-- Stored newest first, canonical query oldest first
+INSERT INTO tv_feed
+SELECT fk_feed, jsonb_build_object('posts', jsonb_agg(data ORDER BY pk DESC))
+FROM tv_post GROUP BY fk_feed;
+
+SELECT count(*) = 0 AS test_order_ignored
+FROM jsonb_ivm_verify('tv_feed', $q$
+    SELECT fk_feed, jsonb_build_object('posts', jsonb_agg(data ORDER BY pk))
+    FROM tv_post GROUP BY fk_feed;
+$q$);
+
+UPDATE tv_post SET data = jsonb_set(data, '{title}', '"Edited"') WHERE pk = 3;
+
+SELECT diff = '[{"path": "posts[id=3].title", "stored": "Post 3", "expected": "Edited"}]'::jsonb
+    AS test_element_paired_by_key
+FROM jsonb_ivm_verify('tv_feed', $q$
+    SELECT fk_feed, jsonb_build_object('posts', jsonb_agg(data ORDER BY pk))
+    FROM tv_post GROUP BY fk_feed
+$q$);
+
+-- ===== LARGE NUMBERS =====
+
+\echo '=== Test 5: Ids above 2^53 compare exactly ==='
+CREATE TABLE tv_big (pk bigint PRIMARY KEY, data jsonb);
+INSERT INTO tv_big VALUES (1, '{"id": 9007199254740993, "n": 1.0}');
+
+SELECT count(*) = 0 AS test_equal_numbers
+FROM jsonb_ivm_verify('tv_big', $q$
+    SELECT 1, '{"id": 9007199254740993, "n": 1}'::jsonb
+$q$);
+
+SELECT diff = '[{"path": "id", "stored": 9007199254740993, "expected": 9007199254740992}]'::jsonb
+    AS test_large_id_drift
+FROM jsonb_ivm_verify('tv_big', $q$
+    SELECT 1, '{"id": 9007199254740992, "n": 1}'::jsonb
+$q$);
+
+-- ===== VALIDATION TESTS =====
+
+\echo '=== Test 6: A rebuild query is required ==='
+DO $$
+BEGIN
+    PERFORM jsonb_ivm_verify('tv_feed');
+    RAISE EXCEPTION 'expected missing definition error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'No rebuild query given and no definition registered for ''tv_feed''' THEN
+        RAISE;
+    END IF;
+END $$;
+
+\echo '=== Test 7: sample_pct must be a percentage ==='
+DO $$
+BEGIN
+    PERFORM jsonb_ivm_verify('tv_user', sample_pct => 0);
+    RAISE EXCEPTION 'expected sample_pct error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'sample_pct must be greater than 0 and at most 100%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+-- Cleanup
+DROP EXTENSION jsonb_ivm CASCADE;
+DROP TABLE tv_big, tv_feed, tv_post, tv_user, users, companies;
+
+\echo '========================================='
+\echo 'All verify tests passed!'
+\echo '========================================='
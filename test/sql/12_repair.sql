@@ -0,0 +1,99 @@
+-- Test incremental repair of drifted projections (jsonb_ivm_repair)
+
+\set ECHO all
+\set ON_ERROR_STOP on
+
+-- Clean up from previous test runs
+DROP EXTENSION IF EXISTS jsonb_ivm CASCADE;
+DROP TABLE IF EXISTS tv_feed, tv_post, repair_log CASCADE;
+DROP FUNCTION IF EXISTS log_repair();
+CREATE EXTENSION jsonb_ivm;
+
+CREATE TABLE tv_post (pk integer PRIMARY KEY, fk_feed integer, data jsonb);
+CREATE TABLE tv_feed (pk integer PRIMARY KEY, data jsonb);
+CREATE TABLE repair_log (pk integer);
+
+INSERT INTO tv_post SELECT i, 1, jsonb_build_object('id', i, 'title', 'Post ' || i) FROM generate_series(1, 5) i;
+SELECT jsonb_ivm_register_dependency('tv_post', 'tv_feed', 'posts', 'array', 'id', 'fk_feed');
+
+-- Stored newest first, as incremental inserts left it
+INSERT INTO tv_feed VALUES
+    (1, (SELECT jsonb_build_object('name', 'Main', 'posts', jsonb_agg(data ORDER BY pk DESC)) FROM tv_post)),
+    (2, '{"name": "Empty", "posts": []}'),
+    (3, '{"name": "Big", "total": 9007199254740993}');
+
+CREATE FUNCTION log_repair() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO repair_log VALUES (NEW.pk);
+    RETURN NULL;
+END $$;
+
+CREATE TRIGGER tv_feed_log AFTER UPDATE ON tv_feed
+FOR EACH ROW EXECUTE FUNCTION log_repair();
+
+-- ===== NO-OP REPAIRS =====
+
+\echo '=== Test 1: A consistent row is not written ==='
+SELECT jsonb_ivm_repair('tv_feed', '1',
+    (SELECT jsonb_build_object('name', 'Main', 'posts', jsonb_agg(data ORDER BY pk)) FROM tv_post))
+    = '[]'::jsonb AS test_nothing_to_repair;
+
+SELECT count(*) = 0 AS test_no_update_fired FROM repair_log;
+
+-- ===== MINIMAL PATCHES =====
+
+\echo '=== Test 2: Only drifted values change ==='
+UPDATE tv_post SET data = jsonb_set(data, '{title}', '"Edited"') WHERE pk = 3;
+
+SELECT jsonb_ivm_repair('tv_feed', '1',
+    (SELECT jsonb_build_object('name', 'Main', 'posts', jsonb_agg(data ORDER BY pk)) FROM tv_post))
+    = '[{"path": "posts[id=3].title", "stored": "Post 3", "expected": "Edited"}]'::jsonb AS test_diff_returned;
+
+SELECT count(*) = 1 AS test_one_update FROM repair_log;
+
+SELECT data -> 'posts' -> 2 ->> 'title' = 'Edited' AS test_value_repaired,
+       jsonb_path_query_array(data, '$.posts[*].id') = '[5, 4, 3, 2, 1]'::jsonb AS test_order_kept
+FROM tv_feed WHERE pk = 1;
+
+\echo '=== Test 3: Missing and extra elements are added and removed ==='
+SELECT jsonb_array_length(jsonb_ivm_repair('tv_feed', '1',
+    '{"name": "Main", "posts": [{"id": 1, "title": "Post 1"}, {"id": 2, "title": "Post 2"},
+                                {"id": 3, "title": "Edited"}, {"id": 6, "title": "Post 6"}]}')) = 3
+    AS test_three_differences;
+
+SELECT jsonb_path_query_array(data, '$.posts[*].id') = '[3, 2, 1, 6]'::jsonb AS test_elements_synced
+FROM tv_feed WHERE pk = 1;
+
+\echo '=== Test 4: Other rows are untouched ==='
+SELECT data = '{"name": "Empty", "posts": []}'::jsonb AS test_other_row_kept
+FROM tv_feed WHERE pk = 2;
+
+\echo '=== Test 5: Integers above 2^53 are repaired exactly ==='
+SELECT jsonb_ivm_repair('tv_feed', '3', '{"name": "Big", "total": 9007199254740992}')
+    = '[{"path": "total", "stored": 9007199254740993, "expected": 9007199254740992}]'::jsonb
+    AS test_large_integer_diff;
+
+SELECT data ->> 'total' = '9007199254740992' AS test_large_integer_repaired
+FROM tv_feed WHERE pk = 3;
+
+-- ===== VALIDATION TESTS =====
+
+\echo '=== Test 6: Missing rows are rejected ==='
+DO $$
+BEGIN
+    PERFORM jsonb_ivm_repair('tv_feed', '99', '{}');
+    RAISE EXCEPTION 'expected missing row error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Row ''99'' not found in ''tv_feed''' THEN
+        RAISE;
+    END IF;
+END $$;
+
+-- Cleanup
+DROP EXTENSION jsonb_ivm CASCADE;
+DROP TABLE tv_feed, tv_post, repair_log;
+DROP FUNCTION log_repair();
+
+\echo '========================================='
+\echo 'All repair tests passed!'
+\echo '========================================='
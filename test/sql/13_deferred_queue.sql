@@ -0,0 +1,139 @@
+-- Test deferred cascades (jsonb_ivm_cascade_trigger('deferred') and jsonb_ivm_flush)
+
+\set ECHO all
+\set ON_ERROR_STOP on
+
+-- Clean up from previous test runs
+DROP EXTENSION IF EXISTS jsonb_ivm CASCADE;
+DROP TABLE IF EXISTS tv_feed, tv_post, tv_user, feed_writes CASCADE;
+DROP FUNCTION IF EXISTS count_feed_write();
+CREATE EXTENSION jsonb_ivm;
+
+CREATE TABLE tv_user (pk integer PRIMARY KEY, data jsonb);
+CREATE TABLE tv_post (pk integer PRIMARY KEY, fk_feed integer, fk_author integer, data jsonb);
+CREATE TABLE tv_feed (pk integer PRIMARY KEY, data jsonb);
+CREATE TABLE feed_writes (pk integer);
+
+SELECT jsonb_ivm_register_dependency('tv_user', 'tv_post', 'author', 'object', NULL, 'fk_author');
+SELECT jsonb_ivm_register_dependency('tv_post', 'tv_feed', 'posts', 'array', 'id', 'fk_feed');
+
+INSERT INTO tv_user VALUES (1, '{"id": 1, "name": "Alice", "followers": 0}');
+INSERT INTO tv_post
+SELECT i, 1, 1, jsonb_build_object('id', i, 'author', '{"id": 1, "name": "Alice", "followers": 0}'::jsonb)
+FROM generate_series(1, 3) i;
+INSERT INTO tv_feed VALUES (1, (SELECT jsonb_build_object('posts', jsonb_agg(data ORDER BY pk)) FROM tv_post));
+
+CREATE FUNCTION count_feed_write() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO feed_writes VALUES (NEW.pk);
+    RETURN NULL;
+END $$;
+
+CREATE TRIGGER tv_feed_writes AFTER UPDATE ON tv_feed
+FOR EACH ROW EXECUTE FUNCTION count_feed_write();
+
+CREATE TRIGGER tv_user_cascade_insert AFTER INSERT ON tv_user
+REFERENCING NEW TABLE AS new_rows
+FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger('deferred');
+
+CREATE TRIGGER tv_user_cascade_update AFTER UPDATE ON tv_user
+REFERENCING OLD TABLE AS old_rows NEW TABLE AS new_rows
+FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger('deferred');
+
+CREATE TRIGGER tv_user_cascade_delete AFTER DELETE ON tv_user
+REFERENCING OLD TABLE AS old_rows
+FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger('deferred');
+
+-- ===== QUEUEING =====
+
+\echo '=== Test 1: Deferred triggers queue changes without applying them ==='
+UPDATE tv_user SET data = jsonb_set(data, '{followers}', to_jsonb((data ->> 'followers')::int + 1)) WHERE pk = 1;
+UPDATE tv_user SET data = jsonb_set(data, '{followers}', to_jsonb((data ->> 'followers')::int + 1)) WHERE pk = 1;
+UPDATE tv_user SET data = jsonb_set(data, '{followers}', to_jsonb((data ->> 'followers')::int + 1)) WHERE pk = 1;
+
+SELECT count(*) = 3 AS test_three_queued FROM jsonb_ivm.change_queue;
+
+SELECT bool_and(data -> 'author' ->> 'followers' = '0') AS test_not_applied_yet
+FROM tv_post;
+
+-- ===== COALESCING =====
+
+\echo '=== Test 2: Flush applies the coalesced change in one write per row ==='
+SELECT jsonb_ivm_flush() = 3 AS test_three_consumed;
+
+SELECT count(*) = 0 AS test_queue_drained FROM jsonb_ivm.change_queue;
+
+SELECT bool_and(data -> 'author' ->> 'followers' = '3') AS test_posts_patched
+FROM tv_post;
+
+SELECT bool_and(post -> 'author' ->> 'followers' = '3') AS test_feed_patched
+FROM tv_feed, jsonb_array_elements(data -> 'posts') post;
+
+SELECT count(*) = 1 AS test_one_feed_write FROM feed_writes;
+
+\echo '=== Test 3: Insert followed by delete cancels out ==='
+TRUNCATE feed_writes;
+INSERT INTO tv_user VALUES (2, '{"id": 2, "name": "Bob"}');
+DELETE FROM tv_user WHERE pk = 2;
+
+SELECT jsonb_ivm_flush() = 2 AS test_two_consumed;
+SELECT count(*) = 0 AS test_nothing_written FROM feed_writes;
+
+\echo '=== Test 4: A change reverted before the flush is dropped ==='
+UPDATE tv_user SET data = jsonb_set(data, '{name}', '"Alicia"') WHERE pk = 1;
+UPDATE tv_user SET data = jsonb_set(data, '{name}', '"Alice"') WHERE pk = 1;
+
+SELECT jsonb_ivm_flush() = 2 AS test_revert_consumed;
+SELECT count(*) = 0 AS test_revert_not_written FROM feed_writes;
+
+\echo '=== Test 5: max_batch counts source rows, not changes ==='
+UPDATE tv_user SET data = jsonb_set(data, '{name}', '"A"') WHERE pk = 1;
+UPDATE tv_user SET data = jsonb_set(data, '{name}', '"B"') WHERE pk = 1;
+UPDATE tv_user SET data = jsonb_set(data, '{name}', '"C"') WHERE pk = 1;
+INSERT INTO tv_user VALUES (2, '{"id": 2, "name": "Bob"}'), (3, '{"id": 3, "name": "Carol"}');
+
+SELECT jsonb_ivm_flush(1) = 3 AS test_row_taken_whole;
+SELECT (SELECT data -> 'author' ->> 'name' FROM tv_post WHERE pk = 1) = 'C' AS test_latest_applied;
+SELECT jsonb_ivm_flush(1) = 1 AS test_second_row;
+SELECT jsonb_ivm_flush(1) = 1 AS test_third_row;
+SELECT jsonb_ivm_flush(1) = 0 AS test_queue_empty;
+
+\echo '=== Test 6: queue_stats reports the backlog ==='
+UPDATE tv_user SET data = jsonb_set(data, '{name}', '"Bobby"') WHERE pk = 2;
+UPDATE tv_user SET data = jsonb_set(data, '{name}', '"Robert"') WHERE pk = 2;
+
+SELECT pending_changes = 2 AND pending_rows = 1 AND lag IS NOT NULL AS test_backlog_reported,
+       NOT worker_running AS test_no_worker
+FROM jsonb_ivm.queue_stats;
+
+SELECT jsonb_ivm_flush() = 2 AS test_backlog_consumed;
+SELECT pending_changes = 0 AND oldest_enqueued_at IS NULL AS test_backlog_cleared
+FROM jsonb_ivm.queue_stats;
+
+-- ===== VALIDATION TESTS =====
+
+\echo '=== Test 7: Unknown trigger arguments are rejected ==='
+CREATE TRIGGER tv_user_cascade_bad AFTER INSERT ON tv_user
+REFERENCING NEW TABLE AS new_rows
+FOR EACH STATEMENT EXECUTE FUNCTION jsonb_ivm_cascade_trigger('later');
+
+DO $$
+BEGIN
+    INSERT INTO tv_user VALUES (4, '{"id": 4}');
+    RAISE EXCEPTION 'expected argument error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'jsonb_ivm_cascade_trigger takes no argument or ''deferred''%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+DROP TRIGGER tv_user_cascade_bad ON tv_user;
+
+-- Cleanup
+DROP EXTENSION jsonb_ivm CASCADE;
+DROP TABLE tv_feed, tv_post, tv_user, feed_writes;
+DROP FUNCTION count_feed_write();
+
+\echo '========================================='
+\echo 'All deferred queue tests passed!'
+\echo '========================================='
@@ -0,0 +1,129 @@
+-- Test trigger-free maintenance from logical decoding (jsonb_ivm_consume_changes)
+-- Needs wal_level = logical and the test_decoding plugin; skipped otherwise
+
+\set ECHO all
+\set ON_ERROR_STOP on
+
+SELECT current_setting('wal_level') <> 'logical' AS skip_decoding \gset
+\if :skip_decoding
+\echo 'wal_level is not logical: skipping logical decoding tests'
+\quit
+\endif
+
+-- Clean up from previous test runs
+SELECT pg_drop_replication_slot(slot_name) FROM pg_replication_slots
+WHERE slot_name IN ('jsonb_ivm_test', 'jsonb_ivm_pgoutput');
+DROP EXTENSION IF EXISTS jsonb_ivm CASCADE;
+DROP TABLE IF EXISTS tv_feed, tv_post, tv_user CASCADE;
+CREATE EXTENSION jsonb_ivm;
+
+CREATE TABLE tv_user (pk integer PRIMARY KEY, data jsonb);
+CREATE TABLE tv_post (pk integer PRIMARY KEY, fk_feed integer, fk_author integer, data jsonb);
+CREATE TABLE tv_feed (pk integer PRIMARY KEY, data jsonb);
+ALTER TABLE tv_user REPLICA IDENTITY FULL;
+
+SELECT jsonb_ivm_register_dependency('tv_user', 'tv_post', 'author', 'object', NULL, 'fk_author');
+SELECT jsonb_ivm_register_dependency('tv_post', 'tv_feed', 'posts', 'array', 'id', 'fk_feed');
+
+INSERT INTO tv_user VALUES (1, '{"id": 1, "name": "Alice"}'), (2, '{"id": 2, "name": "Bob"}');
+INSERT INTO tv_post
+SELECT i, 1, 1 + i % 2, jsonb_build_object('id', i, 'author', u.data)
+FROM generate_series(1, 4) i JOIN tv_user u ON u.pk = 1 + i % 2;
+INSERT INTO tv_feed VALUES (1, (SELECT jsonb_build_object('posts', jsonb_agg(data ORDER BY pk)) FROM tv_post));
+
+-- Changes from here on are decoded; no trigger is installed
+SELECT 'created' FROM pg_create_logical_replication_slot('jsonb_ivm_test', 'test_decoding');
+
+-- ===== TRIGGER-FREE WRITES =====
+
+\echo '=== Test 1: Writes to base tables do not touch projections ==='
+UPDATE tv_user SET data = jsonb_set(data, '{name}', '"Alicia"') WHERE pk = 1;
+
+SELECT bool_and(data -> 'author' ->> 'name' = 'Alice') AS test_not_applied_yet
+FROM tv_post WHERE fk_author = 1;
+
+-- ===== CONSUMING =====
+
+\echo '=== Test 2: Consuming applies committed changes through every level ==='
+SELECT jsonb_ivm_consume_changes('jsonb_ivm_test') = 1 AS test_one_change;
+
+SELECT bool_and(data -> 'author' ->> 'name' = 'Alicia') AS test_posts_patched
+FROM tv_post WHERE fk_author = 1;
+
+SELECT count(*) = 2 AS test_feed_patched
+FROM tv_feed, jsonb_array_elements(data -> 'posts') post
+WHERE post -> 'author' ->> 'name' = 'Alicia';
+
+\echo '=== Test 3: Applied transactions are not consumed twice ==='
+SELECT jsonb_ivm_consume_changes('jsonb_ivm_test') = 0 AS test_nothing_new;
+
+SELECT transactions >= 1 AND changes = 1 AS test_progress_recorded
+FROM jsonb_ivm.decoding_progress WHERE slot_name = 'jsonb_ivm_test';
+
+\echo '=== Test 4: Changes of several transactions are coalesced per row ==='
+UPDATE tv_user SET data = jsonb_set(data, '{name}', '"B"') WHERE pk = 2;
+UPDATE tv_user SET data = jsonb_set(data, '{name}', '"Bobby"') WHERE pk = 2;
+BEGIN;
+INSERT INTO tv_user VALUES (3, '{"id": 3, "name": "Carol"}');
+DELETE FROM tv_user WHERE pk = 3;
+COMMIT;
+
+SELECT jsonb_ivm_consume_changes('jsonb_ivm_test') = 4 AS test_four_changes;
+
+SELECT bool_and(data -> 'author' ->> 'name' = 'Bobby') AS test_latest_applied
+FROM tv_post WHERE fk_author = 2;
+
+\echo '=== Test 5: Deleted rows are cascaded ==='
+DELETE FROM tv_user WHERE pk = 2;
+SELECT jsonb_ivm_consume_changes('jsonb_ivm_test') = 1 AS test_delete_consumed;
+
+SELECT bool_and(data -> 'author' = 'null'::jsonb) AS test_author_removed
+FROM tv_post WHERE fk_author = 2;
+
+\echo '=== Test 6: Rolled back changes are never applied ==='
+BEGIN;
+UPDATE tv_user SET data = jsonb_set(data, '{name}', '"Nobody"') WHERE pk = 1;
+ROLLBACK;
+
+SELECT jsonb_ivm_consume_changes('jsonb_ivm_test') = 0 AS test_rollback_ignored;
+SELECT bool_and(data -> 'author' ->> 'name' = 'Alicia') AS test_posts_unchanged
+FROM tv_post WHERE fk_author = 1;
+
+-- ===== VALIDATION TESTS =====
+
+\echo '=== Test 7: Only test_decoding slots are accepted ==='
+SELECT 'created' FROM pg_create_logical_replication_slot('jsonb_ivm_pgoutput', 'pgoutput');
+
+DO $$
+BEGIN
+    PERFORM jsonb_ivm_consume_changes('jsonb_ivm_pgoutput');
+    RAISE EXCEPTION 'expected plugin error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Replication slot ''jsonb_ivm_pgoutput'' uses plugin ''pgoutput''%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+\echo '=== Test 8: Base tables need REPLICA IDENTITY FULL ==='
+ALTER TABLE tv_user REPLICA IDENTITY DEFAULT;
+UPDATE tv_user SET data = jsonb_set(data, '{name}', '"Al"') WHERE pk = 1;
+
+DO $$
+BEGIN
+    PERFORM jsonb_ivm_consume_changes('jsonb_ivm_test');
+    RAISE EXCEPTION 'expected replica identity error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'Table ''tv_user'' needs REPLICA IDENTITY FULL%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+-- Cleanup
+SELECT pg_drop_replication_slot('jsonb_ivm_test');
+SELECT pg_drop_replication_slot('jsonb_ivm_pgoutput');
+DROP EXTENSION jsonb_ivm CASCADE;
+DROP TABLE tv_feed, tv_post, tv_user;
+
+\echo '========================================='
+\echo 'All logical decoding tests passed!'
+\echo '========================================='
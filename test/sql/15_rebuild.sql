@@ -0,0 +1,107 @@
+-- Test batched projection rebuilds (jsonb_ivm_rebuild, jsonb_ivm_rebuild_batch)
+
+\set ECHO all
+\set ON_ERROR_STOP on
+
+-- Clean up from previous test runs
+DROP EXTENSION IF EXISTS jsonb_ivm CASCADE;
+DROP TABLE IF EXISTS tv_post, tv_tag, posts, users CASCADE;
+CREATE EXTENSION jsonb_ivm;
+
+CREATE TABLE users (pk integer PRIMARY KEY, name text);
+CREATE TABLE posts (pk integer PRIMARY KEY, fk_author integer, title text);
+CREATE TABLE tv_post (pk integer PRIMARY KEY, data jsonb);
+CREATE TABLE tv_tag (pk integer PRIMARY KEY, data jsonb);
+
+INSERT INTO users VALUES (1, 'Alice'), (2, 'Bob');
+INSERT INTO posts SELECT i, 1 + i % 2, 'Post ' || i FROM generate_series(1, 10) i;
+
+SELECT jsonb_ivm_register_projection('tv_post', 'pk', 'data',
+    'SELECT p.pk, jsonb_build_object(''id'', p.pk, ''title'', p.title, ''author'', u.name)
+     FROM posts p JOIN users u ON u.pk = p.fk_author');
+SELECT jsonb_ivm_register_projection('tv_tag', 'pk', 'data');
+
+-- ===== INITIAL POPULATION =====
+
+\echo '=== Test 1: An empty projection is populated in batches ==='
+CALL jsonb_ivm_rebuild('tv_post', 3);
+
+SELECT count(*) = 10 AS test_all_rows_inserted FROM tv_post;
+
+SELECT batches = 4 AND rows_checked = 10 AND rows_written = 10
+       AND last_key = '10' AND finished_at IS NOT NULL AS test_progress_recorded
+FROM jsonb_ivm.rebuild_progress WHERE target_table = 'tv_post'::regclass;
+
+SELECT count(*) = 0 AS test_consistent FROM jsonb_ivm_verify('tv_post');
+
+\echo '=== Test 2: Up-to-date documents are not rewritten ==='
+CALL jsonb_ivm_rebuild('tv_post', 100);
+
+SELECT batches = 1 AND rows_checked = 10 AND rows_written = 0 AS test_nothing_rewritten
+FROM jsonb_ivm.rebuild_progress WHERE target_table = 'tv_post'::regclass;
+
+-- ===== RESUMING =====
+
+\echo '=== Test 3: An interrupted rebuild resumes after its last batch ==='
+UPDATE tv_post SET data = '{}';
+
+SELECT rows_checked = 4 AND rows_written = 4 AND last_key = '4' AND NOT done AS test_first_batch
+FROM jsonb_ivm_rebuild_batch('tv_post', 4);
+
+CALL jsonb_ivm_rebuild('tv_post', 4);
+
+SELECT batches = 3 AND rows_checked = 10 AND rows_written = 10 AS test_resumed
+FROM jsonb_ivm.rebuild_progress WHERE target_table = 'tv_post'::regclass;
+
+SELECT count(*) = 0 AS test_rebuilt FROM jsonb_ivm_verify('tv_post');
+
+-- ===== FILTERING =====
+
+\echo '=== Test 4: where_clause limits the rebuilt rows ==='
+UPDATE tv_post SET data = '{}';
+CALL jsonb_ivm_rebuild('tv_post', 100, 'key <= 2');
+
+SELECT count(*) FILTER (WHERE data <> '{}') = 2 AS test_two_rebuilt,
+       count(*) FILTER (WHERE data = '{}') = 8 AS test_others_untouched
+FROM tv_post;
+
+\echo '=== Test 5: Schema changes are applied to every document ==='
+CALL jsonb_ivm_rebuild('tv_post');
+SELECT jsonb_ivm_register_projection('tv_post', 'pk', 'data',
+    'SELECT p.pk, jsonb_build_object(''id'', p.pk, ''title'', p.title, ''author'', jsonb_build_object(''name'', u.name))
+     FROM posts p JOIN users u ON u.pk = p.fk_author');
+CALL jsonb_ivm_rebuild('tv_post');
+
+SELECT bool_and(jsonb_typeof(data -> 'author') = 'object') AS test_new_shape FROM tv_post;
+
+-- ===== VALIDATION TESTS =====
+
+\echo '=== Test 6: Projections without a definition are rejected ==='
+DO $$
+BEGIN
+    PERFORM * FROM jsonb_ivm_rebuild_batch('tv_tag');
+    RAISE EXCEPTION 'expected missing definition error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'No definition registered for ''tv_tag''%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+\echo '=== Test 7: batch_size must be positive ==='
+DO $$
+BEGIN
+    PERFORM * FROM jsonb_ivm_rebuild_batch('tv_post', 0);
+    RAISE EXCEPTION 'expected batch_size error';
+EXCEPTION WHEN others THEN
+    IF SQLERRM NOT LIKE 'batch_size must be at least 1%' THEN
+        RAISE;
+    END IF;
+END $$;
+
+-- Cleanup
+DROP EXTENSION jsonb_ivm CASCADE;
+DROP TABLE tv_post, tv_tag, posts, users;
+
+\echo '========================================='
+\echo 'All rebuild tests passed!'
+\echo '========================================='